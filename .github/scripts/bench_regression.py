@@ -0,0 +1,41 @@
+#!/usr/bin/env python3
+"""Fail if any benchmark's mean regressed by more than the allowed threshold.
+
+Usage: bench_regression.py previous.json current.json [threshold_percent]
+Both files are produced by `critcmp --export <baseline>`.
+"""
+import json
+import sys
+
+
+def means(path):
+    with open(path) as f:
+        benchmarks = json.load(f)["benchmarks"]
+    return {
+        name: bench["criterion_estimates_v1"]["mean"]["point_estimate"]
+        for name, bench in benchmarks.items()
+    }
+
+
+def main():
+    previous, current = means(sys.argv[1]), means(sys.argv[2])
+    threshold = float(sys.argv[3]) if len(sys.argv) > 3 else 20.0
+
+    regressions = []
+    for name, mean in sorted(current.items()):
+        if name not in previous:
+            continue
+        change = (mean / previous[name] - 1.0) * 100.0
+        print(f"{name}: {change:+.1f}%")
+        if change > threshold:
+            regressions.append((name, change))
+
+    if regressions:
+        print(f"\n{len(regressions)} benchmark(s) regressed by more than {threshold:.0f}%:")
+        for name, change in regressions:
+            print(f"  {name}: {change:+.1f}%")
+        sys.exit(1)
+
+
+if __name__ == "__main__":
+    main()
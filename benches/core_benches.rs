@@ -0,0 +1,206 @@
+//! Criterion benchmarks for the core diff pipeline.
+//!
+//! Directory fixtures are generated on first run under `benches/fixtures/`
+//! (git-ignored) and reused afterwards.
+
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+use diffy::core::diff::DiffEngine;
+use diffy::core::tree::{FileTreeBuilder, DEFAULT_BATCH_SIZE};
+use diffy::core::types::{DiffHunk, DiffLine, DiffLineKind, FileDiff};
+use diffy::DiffyCore;
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+const FIXTURES_DIR: &str = "benches/fixtures";
+
+/// Files in the `analyze_batched` fixture. Set `DIFFY_BENCH_FILES=1000000` for
+/// the million-file run; generating that fixture takes a while the first time.
+const DEFAULT_BATCHED_FILES: usize = 20_000;
+
+/// Above this many files the single-pass peak is not measured, as a single
+/// pass takes too long to be worth waiting for
+const SINGLE_PASS_LIMIT: usize = 100_000;
+
+/// Tracks live and peak heap bytes so benchmarks can report memory use,
+/// which criterion does not measure
+struct PeakAllocator;
+
+static ALLOCATED: AtomicUsize = AtomicUsize::new(0);
+static PEAK: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for PeakAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = System.alloc(layout);
+        if !ptr.is_null() {
+            let allocated = ALLOCATED.fetch_add(layout.size(), Ordering::Relaxed) + layout.size();
+            PEAK.fetch_max(allocated, Ordering::Relaxed);
+        }
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout);
+        ALLOCATED.fetch_sub(layout.size(), Ordering::Relaxed);
+    }
+}
+
+#[global_allocator]
+static GLOBAL: PeakAllocator = PeakAllocator;
+
+/// Most heap bytes allocated at once while `f` runs, beyond those already live
+fn peak_bytes_during(f: impl FnOnce()) -> usize {
+    let baseline = ALLOCATED.load(Ordering::Relaxed);
+    PEAK.store(baseline, Ordering::Relaxed);
+    f();
+    PEAK.load(Ordering::Relaxed) - baseline
+}
+
+/// Left/right trees with `file_count` files spread over 10 per directory,
+/// where every tenth file differs
+fn flat_fixture(file_count: usize) -> PathBuf {
+    let root = Path::new(FIXTURES_DIR).join(format!("files_{}", file_count));
+    if root.exists() {
+        return root;
+    }
+
+    for i in 0..file_count {
+        let relative = format!("dir_{}/file_{}.txt", i / 10, i);
+        let contents = format!("file {}\nshared line\n", i);
+        write(&root.join("left").join(&relative), &contents);
+        let right_contents = if i % 10 == 0 { format!("{}changed\n", contents) } else { contents };
+        write(&root.join("right").join(&relative), &right_contents);
+    }
+    root
+}
+
+/// Left/right trees nested `depth` levels deep with a few files per level
+fn nested_fixture(depth: usize) -> PathBuf {
+    let root = Path::new(FIXTURES_DIR).join(format!("nested_{}", depth));
+    if root.exists() {
+        return root;
+    }
+
+    let mut relative = PathBuf::new();
+    for level in 0..depth {
+        relative.push(format!("level_{}", level));
+        for i in 0..5 {
+            let file = relative.join(format!("file_{}.txt", i));
+            let contents = format!("level {} file {}\n", level, i);
+            write(&root.join("left").join(&file), &contents);
+            let right_contents = if i == 0 { format!("{}changed\n", contents) } else { contents };
+            write(&root.join("right").join(&file), &right_contents);
+        }
+    }
+    root
+}
+
+fn write(path: &Path, contents: &str) {
+    std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+    std::fs::write(path, contents).unwrap();
+}
+
+/// A text of `lines` lines and a copy with `percent_changed`% of lines rewritten
+fn text_pair(lines: usize, percent_changed: usize) -> (String, String) {
+    let step = 100 / percent_changed;
+    let left = (0..lines).map(|i| format!("line {}\n", i)).collect();
+    let right = (0..lines)
+        .map(|i| if i % step == 0 { format!("changed {}\n", i) } else { format!("line {}\n", i) })
+        .collect();
+    (left, right)
+}
+
+fn bench_analyze(c: &mut Criterion) {
+    let mut group = c.benchmark_group("analyze");
+    group.sample_size(10);
+
+    for file_count in [100, 1_000, 10_000] {
+        let root = flat_fixture(file_count);
+        let core = DiffyCore::new(root.join("left"), root.join("right"));
+        group.bench_with_input(BenchmarkId::from_parameter(file_count), &core, |b, core| {
+            b.iter(|| core.analyze().unwrap())
+        });
+    }
+    group.finish();
+}
+
+/// Reports the peak memory of batched and single-pass analysis, then times
+/// the batched one. The peak includes the returned result, which grows with
+/// the file count either way; batching bounds everything else.
+fn bench_analyze_batched(c: &mut Criterion) {
+    let file_count = std::env::var("DIFFY_BENCH_FILES")
+        .ok()
+        .and_then(|count| count.parse().ok())
+        .unwrap_or(DEFAULT_BATCHED_FILES);
+    let root = flat_fixture(file_count);
+    let core = DiffyCore::new(root.join("left"), root.join("right"));
+
+    for batch_size in [1_000, DEFAULT_BATCH_SIZE] {
+        let peak = peak_bytes_during(|| {
+            core.analyze_batched(batch_size).unwrap();
+        });
+        println!("analyze_batched/{} files, batches of {}: peak {} MiB", file_count, batch_size, peak >> 20);
+    }
+    if file_count <= SINGLE_PASS_LIMIT {
+        let peak = peak_bytes_during(|| {
+            core.analyze().unwrap();
+        });
+        println!("analyze/{} files: peak {} MiB", file_count, peak >> 20);
+    }
+
+    let mut group = c.benchmark_group("analyze_batched");
+    group.sample_size(10);
+    group.bench_with_input(BenchmarkId::from_parameter(file_count), &core, |b, core| {
+        b.iter(|| core.analyze_batched(DEFAULT_BATCH_SIZE).unwrap())
+    });
+    group.finish();
+}
+
+fn bench_diff_strings(c: &mut Criterion) {
+    let mut group = c.benchmark_group("diff_strings");
+    group.sample_size(10);
+    let engine = DiffEngine::new();
+
+    for lines in [100, 10_000, 100_000] {
+        for percent_changed in [1, 10, 50] {
+            let (left, right) = text_pair(lines, percent_changed);
+            let id = BenchmarkId::new(format!("{}_lines", lines), format!("{}%", percent_changed));
+            group.bench_with_input(id, &(left, right), |b, (left, right)| {
+                b.iter(|| engine.diff_strings(black_box(left), black_box(right)))
+            });
+        }
+    }
+    group.finish();
+}
+
+fn bench_tree_build(c: &mut Criterion) {
+    let root = nested_fixture(10);
+    let builder = FileTreeBuilder::new(root.join("left"), root.join("right"));
+    c.bench_function("tree_build/nested_10_levels", |b| b.iter(|| builder.build().unwrap()));
+}
+
+fn bench_total_additions(c: &mut Criterion) {
+    let hunk = DiffHunk {
+        old_start: 1,
+        old_lines: 4,
+        new_start: 1,
+        new_lines: 4,
+        lines: [DiffLineKind::Context, DiffLineKind::Deletion, DiffLineKind::Addition, DiffLineKind::Context]
+            .into_iter()
+            .map(|kind| DiffLine { kind, content: "line".to_string(), old_line_number: None, new_line_number: None })
+            .collect(),
+    };
+    let diff = FileDiff {
+        left_content: None,
+        right_content: None,
+        hunks: vec![hunk; 1_000],
+        is_binary: false,
+        similarity: 0.5,
+        binary: None,
+    };
+
+    c.bench_function("file_diff/total_additions_1000_hunks", |b| b.iter(|| black_box(&diff).total_additions()));
+}
+
+criterion_group!(benches, bench_analyze, bench_analyze_batched, bench_diff_strings, bench_tree_build, bench_total_additions);
+criterion_main!(benches);
@@ -0,0 +1,73 @@
+#![no_main]
+
+use diffy::core::diff::DiffEngine;
+use diffy::core::types::{DiffHunk, DiffLineKind};
+use libfuzzer_sys::fuzz_target;
+
+/// Separates the left and right texts in the fuzz input; without it the input
+/// is split at the midpoint.
+const DELIMITER: u8 = 0xff;
+
+fuzz_target!(|data: &[u8]| {
+    let (left, right) = match data.iter().position(|&b| b == DELIMITER) {
+        Some(index) => (&data[..index], &data[index + 1..]),
+        None => data.split_at(data.len() / 2),
+    };
+
+    let (Ok(left), Ok(right)) = (std::str::from_utf8(left), std::str::from_utf8(right)) else {
+        return;
+    };
+
+    let diff = DiffEngine::new().diff_strings(left, right);
+
+    if left != right {
+        assert!(!diff.hunks.is_empty(), "no hunks for differing inputs");
+    }
+
+    for hunk in &diff.hunks {
+        let context = count(hunk, DiffLineKind::Context);
+        assert_eq!(hunk.old_lines, context + count(hunk, DiffLineKind::Deletion), "old_lines mismatch");
+        assert_eq!(hunk.new_lines, context + count(hunk, DiffLineKind::Addition), "new_lines mismatch");
+    }
+
+    assert_eq!(apply(left, &diff.hunks), lines(right), "applying the diff to left did not produce right");
+});
+
+fn count(hunk: &DiffHunk, kind: DiffLineKind) -> u32 {
+    hunk.lines.iter().filter(|line| line.kind == kind).count() as u32
+}
+
+/// Split into lines the same way the engine does (keeping any '\r')
+fn lines(text: &str) -> Vec<String> {
+    text.split_inclusive('\n')
+        .map(|line| line.trim_end_matches('\n').to_string())
+        .collect()
+}
+
+/// Rebuild the right-hand text from the left-hand lines and the hunks
+fn apply(left: &str, hunks: &[DiffHunk]) -> Vec<String> {
+    let left_lines = lines(left);
+    let mut output = Vec::new();
+    let mut next_old = 0usize;
+
+    for hunk in hunks {
+        for line in &hunk.lines {
+            match line.kind {
+                DiffLineKind::Context | DiffLineKind::Deletion => {
+                    let old_index = line.old_line_number.expect("old line number") as usize - 1;
+                    assert!(old_index >= next_old, "hunks overlap or go backwards");
+                    output.extend_from_slice(&left_lines[next_old..old_index]);
+                    assert_eq!(left_lines[old_index], line.content, "context/deletion does not match left");
+                    if line.kind == DiffLineKind::Context {
+                        output.push(line.content.clone());
+                    }
+                    next_old = old_index + 1;
+                }
+                DiffLineKind::Addition => output.push(line.content.clone()),
+            }
+        }
+    }
+
+    output.extend_from_slice(&left_lines[next_old..]);
+    output
+}
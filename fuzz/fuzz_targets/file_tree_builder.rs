@@ -0,0 +1,61 @@
+#![no_main]
+
+use diffy::core::tree::FileTreeBuilder;
+use libfuzzer_sys::fuzz_target;
+use std::path::Path;
+
+// Each input line describes one file: `<side><path>=<contents>`, where side is
+// 'l', 'r' or 'b' (both). Paths are sanitised to stay inside the temp dirs.
+fuzz_target!(|data: &[u8]| {
+    let Ok(text) = std::str::from_utf8(data) else {
+        return;
+    };
+
+    let (Ok(left), Ok(right)) = (tempfile::tempdir(), tempfile::tempdir()) else {
+        return;
+    };
+
+    for line in text.lines().take(64) {
+        let mut chars = line.chars();
+        let side = chars.next();
+        let Some((path, contents)) = chars.as_str().split_once('=') else {
+            continue;
+        };
+        let Some(path) = sanitise(path) else {
+            continue;
+        };
+
+        match side {
+            Some('l') => write(left.path(), &path, contents),
+            Some('r') => write(right.path(), &path, contents),
+            Some('b') => {
+                write(left.path(), &path, contents);
+                write(right.path(), &path, contents);
+            }
+            _ => {}
+        }
+    }
+
+    let builder = FileTreeBuilder::new(left.path().to_path_buf(), right.path().to_path_buf());
+    let _ = builder.build();
+});
+
+fn sanitise(path: &str) -> Option<String> {
+    let parts: Vec<&str> = path
+        .split('/')
+        .filter(|part| !part.is_empty() && *part != "." && *part != ".." && !part.contains('\0'))
+        .take(8)
+        .collect();
+    (!parts.is_empty()).then(|| parts.join("/"))
+}
+
+fn write(root: &Path, relative_path: &str, contents: &str) {
+    let path = root.join(relative_path);
+    if let Some(parent) = path.parent() {
+        // Conflicting file/directory names are expected; just skip them
+        if std::fs::create_dir_all(parent).is_err() {
+            return;
+        }
+    }
+    let _ = std::fs::write(path, contents);
+}
@@ -0,0 +1,162 @@
+//! Shell completion scripts for `diffy completions`. The scripts come from
+//! `clap_complete`, so new flags and subcommands are picked up without
+//! changes here; on top of that, path arguments such as `--left` and `--right`
+//! get completions that suggest files and directories in each shell.
+
+use clap::{Arg, Command, ValueEnum, ValueHint};
+use std::collections::BTreeSet;
+
+/// Shells `diffy completions` can write a script for
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum Shell {
+    Bash,
+    Zsh,
+    Fish,
+    #[value(name = "powershell")]
+    PowerShell,
+}
+
+impl From<Shell> for clap_complete::Shell {
+    fn from(shell: Shell) -> Self {
+        match shell {
+            Shell::Bash => clap_complete::Shell::Bash,
+            Shell::Zsh => clap_complete::Shell::Zsh,
+            Shell::Fish => clap_complete::Shell::Fish,
+            Shell::PowerShell => clap_complete::Shell::PowerShell,
+        }
+    }
+}
+
+/// The completion script for `shell`, completing `command` and its subcommands
+pub fn generate(shell: Shell, mut command: Command) -> String {
+    command.build();
+    let name = command.get_name().to_string();
+    let mut path_flags = BTreeSet::new();
+    collect_path_flags(&command, &mut path_flags);
+
+    let mut script = Vec::new();
+    clap_complete::generate(clap_complete::Shell::from(shell), &mut command, &name, &mut script);
+    let script = String::from_utf8_lossy(&script).into_owned();
+
+    match shell {
+        Shell::Bash => format!("{}\n{}", header(shell, &name), script.trim_start()),
+        Shell::Zsh => zsh(&name, &script),
+        Shell::Fish => fish(&name, &script),
+        Shell::PowerShell => powershell(&name, &script, &path_flags),
+    }
+}
+
+/// The comment at the top of each script, with its installation steps
+fn header(shell: Shell, name: &str) -> String {
+    let (title, steps) = match shell {
+        Shell::Bash => ("bash", vec![format!("{name} completions bash > ~/.local/share/bash-completion/completions/{name}")]),
+        Shell::Zsh => (
+            "zsh",
+            vec![
+                format!("{name} completions zsh > ~/.zfunc/_{name}"),
+                "and add this line to ~/.zshrc before compinit:".to_string(),
+                "  fpath=(~/.zfunc $fpath)".to_string(),
+            ],
+        ),
+        Shell::Fish => ("fish", vec![format!("{name} completions fish > ~/.config/fish/completions/{name}.fish")]),
+        Shell::PowerShell => (
+            "PowerShell",
+            vec![
+                "add this line to your profile (the file at $PROFILE):".to_string(),
+                format!("  {name} completions powershell | Out-String | Invoke-Expression"),
+            ],
+        ),
+    };
+    let steps: String = steps.iter().map(|step| format!("#   {}\n", step)).collect();
+    format!("# {title} completion for {name}\n#\n# Installation:\n{steps}")
+}
+
+/// `-s` and `--long` for every option of `command` and its subcommands that takes a path
+fn collect_path_flags(command: &Command, flags: &mut BTreeSet<String>) {
+    for arg in command.get_arguments().filter(|arg| takes_path(arg)) {
+        flags.extend(arg.get_short().map(|short| format!("-{}", short)));
+        flags.extend(arg.get_long().map(|long| format!("--{}", long)));
+    }
+    for subcommand in command.get_subcommands() {
+        collect_path_flags(subcommand, flags);
+    }
+}
+
+fn takes_path(arg: &Arg) -> bool {
+    !arg.is_positional()
+        && matches!(arg.get_value_hint(), ValueHint::AnyPath | ValueHint::FilePath | ValueHint::DirPath)
+}
+
+/// Complete paths with `_path_files -W`, relative to the current directory,
+/// instead of `_files`
+fn zsh(name: &str, script: &str) -> String {
+    let function = format!("_{}_path", name.replace('-', "_"));
+    let (compdef, rest) = script.split_once('\n').unwrap_or((script, ""));
+    format!(
+        "{compdef}\n{header}\n\
+         # Paths relative to the current directory\n\
+         {function}() {{\n\
+         \x20   _path_files -W \"$PWD\" \"$@\"\n\
+         }}\n\
+         {rest}",
+        header = header(Shell::Zsh, name),
+        rest = rest.replace(":_files", &format!(":{}", function)),
+    )
+}
+
+/// Complete paths with `__fish_complete_path` instead of fish's default file
+/// completion
+fn fish(name: &str, script: &str) -> String {
+    let function = format!("__{}_complete_path", name.replace('-', "_"));
+    format!(
+        "{header}\n\
+         # Files and directories for path arguments such as --left and --right\n\
+         function {function}\n\
+         \x20   __fish_complete_path (commandline -ct)\n\
+         end\n\
+         \n\
+         {script}",
+        header = header(Shell::Fish, name),
+        script = script.replace("-r -F", &format!("-r -f -a '({})'", function)),
+    )
+}
+
+/// Quote text as a PowerShell single-quoted string
+fn powershell_quote(text: &str) -> String {
+    format!("'{}'", text.replace('\'', "''"))
+}
+
+/// Offer the directories and files under the current location after a path option;
+/// clap_complete's PowerShell script only completes flags and subcommands
+fn powershell(name: &str, script: &str, path_flags: &BTreeSet<String>) -> String {
+    let flags: Vec<String> = path_flags.iter().map(|flag| powershell_quote(flag)).collect();
+    let paths = format!(
+        "    # Directories and files for path arguments such as --left and --right
+    $before = @($commandElements | Where-Object {{ $_.Extent.EndOffset -lt $cursorPosition }})
+    if ($before.Count -gt 1 -and @({flags}) -ccontains $before[-1].ToString()) {{
+        $directory = ''
+        $separator = $wordToComplete.LastIndexOfAny([char[]]@('/', '\\'))
+        if ($separator -ge 0) {{ $directory = $wordToComplete.Substring(0, $separator + 1) }}
+        $base = Join-Path $PWD.ProviderPath $directory
+        if (-not [System.IO.Directory]::Exists($base)) {{ return }}
+        $completions = @([System.IO.Directory]::GetDirectories($base) | ForEach-Object {{
+            $path = $directory + [System.IO.Path]::GetFileName($_) + [System.IO.Path]::DirectorySeparatorChar
+            [CompletionResult]::new($path, $path, [CompletionResultType]::ProviderContainer, $path)
+        }})
+        $completions += @([System.IO.Directory]::GetFiles($base) | ForEach-Object {{
+            $path = $directory + [System.IO.Path]::GetFileName($_)
+            [CompletionResult]::new($path, $path, [CompletionResultType]::ProviderItem, $path)
+        }})
+    }}
+
+",
+        flags = flags.join(", ")
+    );
+
+    let marker = "    $completions.Where{";
+    let script = match script.find(marker) {
+        Some(index) => format!("{}{}{}", &script[..index], paths, &script[index..]),
+        None => script.to_string(),
+    };
+    format!("{}\n{}", header(Shell::PowerShell, name), script.trim_start())
+}
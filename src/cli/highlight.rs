@@ -0,0 +1,64 @@
+//! Syntax highlighting for diff content in the TUI. The real highlighter needs
+//! the `syntax-highlighting` feature; without it `SyntaxHighlighter::new`
+//! returns `None` and diffs render in plain colors.
+
+use ratatui::text::Span;
+use std::path::Path;
+
+/// Styled spans for every line of a file, without line endings
+pub type HighlightedLines = Vec<Vec<Span<'static>>>;
+
+#[cfg(feature = "syntax-highlighting")]
+pub struct SyntaxHighlighter {
+    assets: bat::assets::HighlightingAssets,
+}
+
+#[cfg(feature = "syntax-highlighting")]
+impl SyntaxHighlighter {
+    const THEME: &'static str = "Monokai Extended";
+
+    pub fn new() -> Option<Self> {
+        Some(Self { assets: bat::assets::HighlightingAssets::from_binary() })
+    }
+
+    /// Highlight `content` using the language matching `path`'s name or
+    /// extension, or `None` when the language is unknown
+    pub fn highlight(&self, path: &Path, content: &str) -> Option<HighlightedLines> {
+        use ratatui::style::{Color, Style};
+        use syntect::easy::HighlightLines;
+        use syntect::util::LinesWithEndings;
+
+        let mapping = bat::SyntaxMapping::builtin();
+        let syntax = self.assets.get_syntax_for_path(path, &mapping).ok()?;
+        let mut highlighter = HighlightLines::new(syntax.syntax, self.assets.get_theme(Self::THEME));
+
+        LinesWithEndings::from(content)
+            .map(|line| {
+                let ranges = highlighter.highlight_line(line, syntax.syntax_set).ok()?;
+                Some(
+                    ranges
+                        .into_iter()
+                        .map(|(style, text)| {
+                            let color = Color::Rgb(style.foreground.r, style.foreground.g, style.foreground.b);
+                            Span::styled(text.trim_end_matches(['\n', '\r']).to_string(), Style::default().fg(color))
+                        })
+                        .collect(),
+                )
+            })
+            .collect()
+    }
+}
+
+#[cfg(not(feature = "syntax-highlighting"))]
+pub struct SyntaxHighlighter;
+
+#[cfg(not(feature = "syntax-highlighting"))]
+impl SyntaxHighlighter {
+    pub fn new() -> Option<Self> {
+        None
+    }
+
+    pub fn highlight(&self, _path: &Path, _content: &str) -> Option<HighlightedLines> {
+        None
+    }
+}
@@ -1,3 +1,7 @@
+pub mod completions;
+mod highlight;
+pub mod picker;
+pub mod pretty;
 pub mod tui;
 
-pub use tui::TuiApp;
\ No newline at end of file
+pub use tui::{DiffViewMode, TuiApp};
\ No newline at end of file
@@ -0,0 +1,309 @@
+//! Two-panel path picker shown when `diffy` runs in a terminal without
+//! `--left` and `--right`. Each panel browses directories; the left one picks
+//! the left path and the right one the right path.
+
+use anyhow::Result;
+use crossterm::{
+    event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers},
+    execute,
+    terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
+};
+use ratatui::{
+    backend::{Backend, CrosstermBackend},
+    layout::{Constraint, Direction, Layout, Rect},
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, List, ListItem, ListState, Paragraph},
+    Frame, Terminal,
+};
+use std::io::{self, IsTerminal};
+use std::path::{Path, PathBuf};
+
+/// Whether the picker can be shown: both stdin and stdout are a terminal
+pub fn is_available() -> bool {
+    io::stdin().is_terminal() && io::stdout().is_terminal()
+}
+
+/// What a key press in the picker led to
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PickerEvent {
+    Continue,
+    /// Both paths were chosen and confirmed
+    Picked(PathBuf, PathBuf),
+    Cancelled,
+}
+
+/// Which part of the picker has focus; `Tab` cycles through them
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Focus {
+    Left,
+    Right,
+    Confirm,
+}
+
+struct PickerEntry {
+    name: String,
+    path: PathBuf,
+    is_dir: bool,
+}
+
+struct Panel {
+    title: &'static str,
+    dir: PathBuf,
+    /// The listing of `dir`, directories first; the first row picks `dir` itself
+    entries: Vec<PickerEntry>,
+    state: ListState,
+    selected: Option<PathBuf>,
+}
+
+impl Panel {
+    fn new(title: &'static str, dir: PathBuf) -> Result<Self> {
+        let mut panel = Self { title, dir: dir.clone(), entries: Vec::new(), state: ListState::default(), selected: None };
+        panel.open(dir)?;
+        Ok(panel)
+    }
+
+    /// List `dir`, leaving the panel unchanged if it can't be read
+    fn open(&mut self, dir: PathBuf) -> Result<()> {
+        let mut entries = Vec::new();
+        for entry in std::fs::read_dir(&dir)? {
+            let entry = entry?;
+            let path = entry.path();
+            entries.push(PickerEntry { name: entry.file_name().to_string_lossy().into_owned(), is_dir: path.is_dir(), path });
+        }
+        entries.sort_by(|a, b| b.is_dir.cmp(&a.is_dir).then_with(|| a.name.to_lowercase().cmp(&b.name.to_lowercase())));
+        entries.insert(0, PickerEntry { name: ".".to_string(), path: dir.clone(), is_dir: true });
+
+        // Coming back up keeps the directory we left highlighted
+        let previous = std::mem::replace(&mut self.dir, dir);
+        let index = entries.iter().skip(1).position(|entry| entry.path == previous).map_or(0, |index| index + 1);
+        self.entries = entries;
+        self.state.select(Some(index));
+        Ok(())
+    }
+
+    fn current(&self) -> Option<&PickerEntry> {
+        self.entries.get(self.state.selected()?)
+    }
+
+    fn move_by(&mut self, offset: isize) {
+        let last = self.entries.len().saturating_sub(1) as isize;
+        let index = self.state.selected().unwrap_or(0) as isize + offset;
+        self.state.select(Some(index.clamp(0, last) as usize));
+    }
+}
+
+pub struct PathPicker {
+    panels: [Panel; 2],
+    focus: Focus,
+    status_message: Option<String>,
+}
+
+impl PathPicker {
+    /// A picker with both panels browsing `start`
+    pub fn new(start: impl AsRef<Path>) -> Result<Self> {
+        let start = std::fs::canonicalize(start)?;
+        Ok(Self {
+            panels: [Panel::new("Left", start.clone())?, Panel::new("Right", start)?],
+            focus: Focus::Left,
+            status_message: None,
+        })
+    }
+
+    /// The paths picked so far, left then right
+    pub fn selection(&self) -> (Option<&Path>, Option<&Path>) {
+        (self.panels[0].selected.as_deref(), self.panels[1].selected.as_deref())
+    }
+
+    /// Show the picker until both paths are confirmed (`Some`) or it is closed (`None`)
+    pub fn run(&mut self) -> Result<Option<(PathBuf, PathBuf)>> {
+        enable_raw_mode()?;
+        let mut stdout = io::stdout();
+        execute!(stdout, EnterAlternateScreen)?;
+        let mut terminal = Terminal::new(CrosstermBackend::new(stdout))?;
+
+        let result = self.run_picker(&mut terminal);
+
+        disable_raw_mode()?;
+        execute!(terminal.backend_mut(), LeaveAlternateScreen)?;
+        terminal.show_cursor()?;
+
+        result
+    }
+
+    fn run_picker<B: Backend>(&mut self, terminal: &mut Terminal<B>) -> Result<Option<(PathBuf, PathBuf)>> {
+        loop {
+            terminal.draw(|f| self.ui(f))?;
+            if let Event::Key(key) = event::read()? {
+                if key.kind != KeyEventKind::Press {
+                    continue;
+                }
+                match self.handle_key(key) {
+                    PickerEvent::Continue => {}
+                    PickerEvent::Picked(left, right) => return Ok(Some((left, right))),
+                    PickerEvent::Cancelled => return Ok(None),
+                }
+            }
+        }
+    }
+
+    /// Apply one key press: arrows move, `Enter` opens a directory, picks the
+    /// highlighted path or confirms, `Backspace` goes up and `Tab` switches panels
+    pub fn handle_key(&mut self, key: KeyEvent) -> PickerEvent {
+        self.status_message = None;
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+        match key.code {
+            KeyCode::Esc | KeyCode::Char('q') => return PickerEvent::Cancelled,
+            KeyCode::Char('c') if ctrl => return PickerEvent::Cancelled,
+            KeyCode::Tab => self.cycle_focus(true),
+            KeyCode::BackTab => self.cycle_focus(false),
+            KeyCode::Enter if self.focus == Focus::Confirm => return self.confirm(),
+            _ => {}
+        }
+
+        let focus = self.focus;
+        let Some(panel) = self.panel_mut(focus) else {
+            return PickerEvent::Continue;
+        };
+        match key.code {
+            KeyCode::Up | KeyCode::Char('k') => panel.move_by(-1),
+            KeyCode::Down | KeyCode::Char('j') => panel.move_by(1),
+            KeyCode::PageUp => panel.move_by(-10),
+            KeyCode::PageDown => panel.move_by(10),
+            KeyCode::Home => panel.move_by(isize::MIN / 2),
+            KeyCode::End => panel.move_by(isize::MAX / 2),
+            KeyCode::Backspace | KeyCode::Left => {
+                if let Some(parent) = panel.dir.parent().map(Path::to_path_buf) {
+                    self.open_dir(focus, parent);
+                }
+            }
+            KeyCode::Enter | KeyCode::Right => {
+                let Some(entry) = panel.current() else {
+                    return PickerEvent::Continue;
+                };
+                let (path, is_dir, is_self) = (entry.path.clone(), entry.is_dir, entry.name == ".");
+                if is_dir && !is_self {
+                    self.open_dir(focus, path);
+                } else if key.code == KeyCode::Enter {
+                    panel.selected = Some(path);
+                    self.cycle_focus(true);
+                }
+            }
+            _ => {}
+        }
+        PickerEvent::Continue
+    }
+
+    fn panel_mut(&mut self, focus: Focus) -> Option<&mut Panel> {
+        match focus {
+            Focus::Left => Some(&mut self.panels[0]),
+            Focus::Right => Some(&mut self.panels[1]),
+            Focus::Confirm => None,
+        }
+    }
+
+    fn open_dir(&mut self, focus: Focus, dir: PathBuf) {
+        let Some(panel) = self.panel_mut(focus) else {
+            return;
+        };
+        if let Err(e) = panel.open(dir.clone()) {
+            self.status_message = Some(format!("Can't open {}: {}", dir.display(), e));
+        }
+    }
+
+    fn cycle_focus(&mut self, forward: bool) {
+        self.focus = match (self.focus, forward) {
+            (Focus::Left, true) | (Focus::Confirm, false) => Focus::Right,
+            (Focus::Right, true) | (Focus::Left, false) => Focus::Confirm,
+            (Focus::Confirm, true) | (Focus::Right, false) => Focus::Left,
+        };
+    }
+
+    fn confirm(&mut self) -> PickerEvent {
+        let (Some(left), Some(right)) = self.selection() else {
+            self.status_message = Some("Pick a left and a right path first".to_string());
+            return PickerEvent::Continue;
+        };
+        if left.is_dir() != right.is_dir() {
+            self.status_message = Some("Pick two directories or two files".to_string());
+            return PickerEvent::Continue;
+        }
+        PickerEvent::Picked(left.to_path_buf(), right.to_path_buf())
+    }
+
+    fn ui(&mut self, f: &mut Frame) {
+        let rows = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Min(0), Constraint::Length(3), Constraint::Length(1)])
+            .split(f.size());
+        let panels = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
+            .split(rows[0]);
+
+        for (index, focus) in [Focus::Left, Focus::Right].into_iter().enumerate() {
+            let active = self.focus == focus;
+            Self::render_panel(f, panels[index], &mut self.panels[index], active);
+        }
+        self.render_confirm(f, rows[1]);
+
+        let footer = match &self.status_message {
+            Some(message) => Paragraph::new(message.as_str()).style(Style::default().fg(Color::Red)),
+            None => Paragraph::new("↑/↓ move · Enter open or pick · Backspace up · Tab switch panel · Esc quit")
+                .style(Style::default().fg(Color::DarkGray)),
+        };
+        f.render_widget(footer, rows[2]);
+    }
+
+    fn render_panel(f: &mut Frame, area: Rect, panel: &mut Panel, active: bool) {
+        let items: Vec<ListItem> = panel
+            .entries
+            .iter()
+            .map(|entry| {
+                let picked = panel.selected.as_ref() == Some(&entry.path);
+                let (icon, name) = match (entry.is_dir, entry.name == ".") {
+                    (_, true) => ("✔", "(use this directory)".to_string()),
+                    (true, false) => ("📁", format!("{}/", entry.name)),
+                    (false, false) => ("📄", entry.name.clone()),
+                };
+                let style = if picked { Style::default().fg(Color::Green).add_modifier(Modifier::BOLD) } else { Style::default() };
+                ListItem::new(Line::from(vec![Span::raw(icon), Span::raw(" "), Span::styled(name, style)]))
+            })
+            .collect();
+
+        let border = if active { Style::default().fg(Color::Cyan) } else { Style::default() };
+        let list = List::new(items)
+            .block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .border_style(border)
+                    .title(format!("{}: {}", panel.title, panel.dir.display())),
+            )
+            .highlight_style(Style::default().add_modifier(Modifier::REVERSED))
+            .highlight_symbol("▶ ");
+        f.render_stateful_widget(list, area, &mut panel.state);
+    }
+
+    /// The picked paths and the button that starts the comparison
+    fn render_confirm(&self, f: &mut Frame, area: Rect) {
+        let (left, right) = self.selection();
+        let describe = |path: Option<&Path>| path.map_or("(not picked)".to_string(), |path| path.display().to_string());
+        let ready = left.is_some() && right.is_some();
+
+        let mut button = Style::default().add_modifier(Modifier::BOLD);
+        if !ready {
+            button = button.fg(Color::DarkGray);
+        }
+        if self.focus == Focus::Confirm {
+            button = button.add_modifier(Modifier::REVERSED);
+        }
+        let line = Line::from(vec![
+            Span::styled("[ Compare ]", button),
+            Span::raw("  "),
+            Span::styled(describe(left), Style::default().fg(Color::Red)),
+            Span::raw(" ↔ "),
+            Span::styled(describe(right), Style::default().fg(Color::Green)),
+        ]);
+        f.render_widget(Paragraph::new(line).block(Block::default().borders(Borders::ALL)), area);
+    }
+}
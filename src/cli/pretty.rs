@@ -0,0 +1,33 @@
+//! Colored `--format unified` output for terminals. Highlighting uses bat
+//! and needs the `bat-output` feature; without it the diff prints as plain
+//! text.
+
+use anyhow::Result;
+use std::io::IsTerminal;
+
+/// Whether printed diffs should be colored: stdout is a terminal and neither
+/// `--no-color` nor the `NO_COLOR` environment variable turns color off
+pub fn use_color(no_color: bool) -> bool {
+    !no_color && std::env::var_os("NO_COLOR").is_none() && std::io::stdout().is_terminal()
+}
+
+/// Print a unified diff to stdout, highlighted with bat's `diff` syntax when
+/// `colored` is set and the `bat-output` feature is enabled
+pub fn print_diff(diff: &str, colored: bool) -> Result<()> {
+    #[cfg(feature = "bat-output")]
+    if colored {
+        let true_color = std::env::var("COLORTERM").is_ok_and(|term| term == "truecolor" || term == "24bit");
+        bat::PrettyPrinter::new()
+            .input_from_bytes(diff.as_bytes())
+            .language("diff")
+            .true_color(true_color)
+            .print()
+            .map_err(|e| anyhow::anyhow!("Failed to highlight the diff: {}", e))?;
+        return Ok(());
+    }
+    #[cfg(not(feature = "bat-output"))]
+    let _ = colored;
+
+    print!("{}", diff);
+    Ok(())
+}
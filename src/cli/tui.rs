@@ -1,7 +1,9 @@
-use crate::core::{DiffyCore, types::{DiffResult, FileEntry, DiffStatus, FileDiff}};
+use crate::core::{DiffyCore, diff::DiffEngine, export::{self, ExportFormat}, types::{BlameLine, DiffResult, FileEntry, DiffStatus, FileDiff, FileMetadata, SortMode, WordDiffLine, WordSpan}};
+use crate::cli::highlight::{HighlightedLines, SyntaxHighlighter};
+use crate::config::TuiConfig;
 use anyhow::Result;
 use crossterm::{
-    event::{self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode, KeyEventKind},
+    event::{self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers},
     execute,
     terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
 };
@@ -10,38 +12,453 @@ use ratatui::{
     layout::{Constraint, Direction, Layout, Rect},
     style::{Color, Modifier, Style},
     text::{Line, Span},
-    widgets::{Block, Borders, List, ListItem, ListState, Paragraph, Wrap},
+    widgets::{Bar, BarChart, BarGroup, Block, Borders, Cell, Clear, Gauge, List, ListItem, ListState, Paragraph, Row, Table, Tabs, Wrap},
     Frame, Terminal,
 };
-use std::collections::HashSet;
-use std::io;
-use std::path::PathBuf;
+use std::collections::{HashMap, HashSet};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use std::time::{Duration, Instant};
+
+/// How often the loading screen checks for progress and redraws
+const LOADING_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+const SPINNER_FRAMES: [char; 10] = ['⠋', '⠙', '⠹', '⠸', '⠼', '⠴', '⠦', '⠧', '⠇', '⠏'];
+
+/// Lines scrolled by `j` and `k`
+const LINE_SCROLL_STEP: u16 = 3;
+
+/// Rows of the metadata view
+const METADATA_FIELDS: [&str; 5] = ["Modified", "Size", "Permissions", "Symlink", "Symlink target"];
+
+/// Watch refreshes with fewer changed paths than this use incremental analysis
+const INCREMENTAL_REFRESH_LIMIT: usize = 10;
+
+/// Characters of the author name shown in the blame column
+const BLAME_AUTHOR_WIDTH: usize = 10;
+
+/// Written to the current directory by the "Export as patch" command
+const PATCH_EXPORT_FILE: &str = "diffy.patch";
+
+/// An action offered by the `Ctrl+P` command palette
+struct TuiCommand {
+    name: &'static str,
+    description: &'static str,
+    /// Key that runs the command directly, shown next to it in the palette
+    keybinding: Option<KeyCode>,
+    action: fn(&mut TuiApp) -> Result<()>,
+}
+
+const COMMANDS: &[TuiCommand] = &[
+    TuiCommand {
+        name: "Toggle unified/side-by-side",
+        description: "Switch between the unified and side-by-side views",
+        keybinding: None,
+        action: |app| {
+            app.diff_view_mode = match app.diff_view_mode {
+                DiffViewMode::SideBySide => DiffViewMode::Unified,
+                _ => DiffViewMode::SideBySide,
+            };
+            Ok(())
+        },
+    },
+    TuiCommand {
+        name: "Unified view",
+        description: "Show changes with removed and added lines interleaved",
+        keybinding: Some(KeyCode::Char('u')),
+        action: |app| {
+            app.diff_view_mode = DiffViewMode::Unified;
+            Ok(())
+        },
+    },
+    TuiCommand {
+        name: "Side-by-side view",
+        description: "Show the old and new file next to each other",
+        keybinding: Some(KeyCode::Char('s')),
+        action: |app| {
+            app.diff_view_mode = DiffViewMode::SideBySide;
+            Ok(())
+        },
+    },
+    TuiCommand {
+        name: "Word diff view",
+        description: "Highlight the changed words within lines",
+        keybinding: Some(KeyCode::Char('w')),
+        action: |app| {
+            app.diff_view_mode = DiffViewMode::WordDiff;
+            app.refresh_word_diff();
+            Ok(())
+        },
+    },
+    TuiCommand {
+        name: "Metadata view",
+        description: "Compare modification time, size, permissions and symlinks",
+        keybinding: Some(KeyCode::Char('m')),
+        action: |app| {
+            app.diff_view_mode = DiffViewMode::Metadata;
+            Ok(())
+        },
+    },
+    TuiCommand {
+        name: "Toggle blame column",
+        description: "Show the commit and author of each line",
+        keybinding: Some(KeyCode::Char('b')),
+        action: |app| {
+            app.toggle_blame();
+            Ok(())
+        },
+    },
+    TuiCommand {
+        name: "Jump to next change",
+        description: "Scroll to the next hunk, or open the next changed file",
+        keybinding: Some(KeyCode::Char('n')),
+        action: |app| app.jump_to_change(true),
+    },
+    TuiCommand {
+        name: "Jump to previous change",
+        description: "Scroll to the previous hunk, or open the previous changed file",
+        keybinding: Some(KeyCode::Char('N')),
+        action: |app| app.jump_to_change(false),
+    },
+    TuiCommand {
+        name: "More context",
+        description: "Show another unchanged line around each change, joining hunks that meet",
+        keybinding: Some(KeyCode::Char('+')),
+        action: |app| {
+            app.change_context(true);
+            Ok(())
+        },
+    },
+    TuiCommand {
+        name: "Less context",
+        description: "Show one unchanged line fewer around each change",
+        keybinding: Some(KeyCode::Char('-')),
+        action: |app| {
+            app.change_context(false);
+            Ok(())
+        },
+    },
+    TuiCommand {
+        name: "Go to line",
+        description: "Scroll the open diff to a line number",
+        keybinding: Some(KeyCode::Char(':')),
+        action: |app| {
+            app.command_input = Some(CommandInput::default());
+            Ok(())
+        },
+    },
+    TuiCommand {
+        name: "Filter by added",
+        description: "Only list added files in the tree",
+        keybinding: None,
+        action: |app| {
+            app.set_status_filter(Some(DiffStatus::Added));
+            Ok(())
+        },
+    },
+    TuiCommand {
+        name: "Filter by removed",
+        description: "Only list removed files in the tree",
+        keybinding: None,
+        action: |app| {
+            app.set_status_filter(Some(DiffStatus::Removed));
+            Ok(())
+        },
+    },
+    TuiCommand {
+        name: "Filter by modified",
+        description: "Only list modified files in the tree",
+        keybinding: None,
+        action: |app| {
+            app.set_status_filter(Some(DiffStatus::Modified));
+            Ok(())
+        },
+    },
+    TuiCommand {
+        name: "Show all files",
+        description: "Clear the status filter",
+        keybinding: None,
+        action: |app| {
+            app.set_status_filter(None);
+            Ok(())
+        },
+    },
+    TuiCommand {
+        name: "Expand all directories",
+        description: "Open every directory in the tree",
+        keybinding: Some(KeyCode::Char('a')),
+        action: |app| {
+            app.expand_all();
+            Ok(())
+        },
+    },
+    TuiCommand {
+        name: "Collapse all directories",
+        description: "Close every directory in the tree",
+        keybinding: Some(KeyCode::Char('z')),
+        action: |app| {
+            app.collapse_all();
+            Ok(())
+        },
+    },
+    TuiCommand {
+        name: "Cycle sort order",
+        description: "Sort the tree by name, status or size",
+        keybinding: Some(KeyCode::Char('S')),
+        action: |app| {
+            app.sort_mode = app.sort_mode.next();
+            app.refresh_tree_view();
+            Ok(())
+        },
+    },
+    TuiCommand {
+        name: "Open in new tab",
+        description: "Open the selected file in a new tab (Ctrl+T)",
+        keybinding: None,
+        action: |app| app.open_selected_file(true),
+    },
+    TuiCommand {
+        name: "Close tab",
+        description: "Close the current tab (Ctrl+W)",
+        keybinding: None,
+        action: |app| {
+            app.close_active_tab();
+            Ok(())
+        },
+    },
+    TuiCommand {
+        name: "Previous tab",
+        description: "Switch to the tab on the left (Ctrl+[ where the terminal sends it)",
+        keybinding: None,
+        action: |app| {
+            app.switch_tab(false);
+            Ok(())
+        },
+    },
+    TuiCommand {
+        name: "Next tab",
+        description: "Switch to the tab on the right (Ctrl+])",
+        keybinding: None,
+        action: |app| {
+            app.switch_tab(true);
+            Ok(())
+        },
+    },
+    TuiCommand {
+        name: "Open in editor",
+        description: "Edit the open file with $VISUAL or $EDITOR",
+        keybinding: None,
+        action: |app| app.open_in_editor(),
+    },
+    TuiCommand {
+        name: "Copy diff",
+        description: "Copy the open file's unified diff to the clipboard",
+        keybinding: None,
+        action: |app| {
+            app.copy_diff();
+            Ok(())
+        },
+    },
+    TuiCommand {
+        name: "Export as patch",
+        description: "Write every change to diffy.patch in the current directory",
+        keybinding: None,
+        action: |app| app.export_patch(),
+    },
+    TuiCommand {
+        name: "Summarize changes",
+        description: "Describe the whole comparison in a few sentences",
+        keybinding: Some(KeyCode::Char('?')),
+        action: |app| {
+            app.show_explanation();
+            Ok(())
+        },
+    },
+    TuiCommand {
+        name: "Quit",
+        description: "Leave diffy",
+        keybinding: Some(KeyCode::Char('q')),
+        action: |app| {
+            app.should_quit = true;
+            Ok(())
+        },
+    },
+];
+
+impl TuiCommand {
+    /// Whether every word of `query` appears in the name or description
+    fn matches(&self, query: &str) -> bool {
+        let text = format!("{} {}", self.name, self.description).to_lowercase();
+        query.to_lowercase().split_whitespace().all(|word| text.contains(word))
+    }
+
+    fn key_label(&self, app: &TuiApp) -> String {
+        match self.keybinding {
+            Some(KeyCode::Char(c)) => app.bound_key(c).to_string(),
+            Some(key) => format!("{:?}", key),
+            None => String::new(),
+        }
+    }
+}
+
+/// Colors of added, removed and modified files and lines
+struct StatusColors {
+    added: Color,
+    removed: Color,
+    modified: Color,
+}
+
+impl Default for StatusColors {
+    fn default() -> Self {
+        Self { added: Color::Green, removed: Color::Red, modified: Color::Yellow }
+    }
+}
 
 #[derive(Clone, Copy, PartialEq)]
 pub enum DiffViewMode {
     Unified,
     SideBySide,
+    WordDiff,
+    /// Modification time, size, permissions and symlink details of both sides
+    Metadata,
 }
 
 pub struct TuiApp {
     core: DiffyCore,
     diff_result: Option<DiffResult>,
+    /// How long the latest full or incremental analysis took
+    analysis_time: Option<Duration>,
+    /// Set while the initial analysis runs on a background thread
+    loading: Option<LoadingState>,
     tree_state: ListState,
     tree_items: Vec<TreeDisplayItem>,
     collapsed_dirs: HashSet<PathBuf>,
     selected_file: Option<PathBuf>,
     current_diff: Option<FileDiff>,
+    /// Word diff of `current_diff`, computed on first use in `WordDiff` mode
+    current_word_diff: Option<Vec<WordDiffLine>>,
+    /// Show who last changed each line next to the unified diff (`b`)
+    blame_mode: bool,
+    /// Blame of the selected file, loaded while `blame_mode` is on
+    current_blame: Option<Vec<BlameLine>>,
+    /// Summary of the whole comparison shown over everything else (`?`)
+    explanation: Option<String>,
+    /// Open `Ctrl+P` command palette
+    palette: Option<Palette>,
+    /// Only files with this status are listed in the tree
+    status_filter: Option<DiffStatus>,
+    /// Set after another program used the terminal, e.g. an editor
+    needs_full_redraw: bool,
+    /// `None` when highlighting is disabled or not compiled in
+    highlighter: Option<SyntaxHighlighter>,
+    /// Right side of `current_diff` highlighted line by line, used for context lines
+    current_highlight: Option<HighlightedLines>,
+    /// Left and right metadata of the open file
+    current_metadata: (Option<FileMetadata>, Option<FileMetadata>),
     diff_view_mode: DiffViewMode,
+    /// Order of entries within each directory of the file tree, cycled with `S`
+    sort_mode: SortMode,
     scroll_offset: u16,
+    /// Last scroll offset of every file viewed, restored when it is opened again
+    scroll_positions: HashMap<PathBuf, u16>,
+    /// Where the diff panel was last drawn, used to size page scrolls
+    diff_area: Rect,
+    /// Rows the open diff takes up in the current view mode
+    current_diff_line_count: usize,
+    /// Open files; the active one is mirrored in `selected_file`, `current_diff`
+    /// and `scroll_offset` while it is shown
+    tabs: Vec<TabState>,
+    active_tab: usize,
+    /// Text typed after `:` (or Ctrl+G) while command mode is open
+    command_input: Option<CommandInput>,
+    /// Shown in the bottom row until the next key press, e.g. a failed command
+    status_message: Option<String>,
+    colors: StatusColors,
+    /// Keys rebound in the `[tui]` configuration, mapped to the default key of
+    /// their command; default keys of rebound commands map to `None`
+    key_map: HashMap<char, Option<char>>,
     should_quit: bool,
 }
 
+/// Text typed into the command palette and the highlighted match
+#[derive(Default)]
+struct Palette {
+    input: CommandInput,
+    selected: usize,
+}
+
+impl Palette {
+    fn matches(&self) -> Vec<&'static TuiCommand> {
+        COMMANDS.iter().filter(|command| command.matches(&self.input.text)).collect()
+    }
+}
+
+/// Sent from the analysis thread to the UI thread
+enum LoadMessage {
+    Progress(usize, usize),
+    Done(Box<Result<DiffResult>>),
+}
+
+struct LoadingState {
+    receiver: mpsc::Receiver<LoadMessage>,
+    /// Latest `(compared, total)` from `analyze_with_progress`
+    progress: (usize, usize),
+    started: Instant,
+}
+
+#[derive(Default)]
+struct CommandInput {
+    text: String,
+    /// Cursor position in characters
+    cursor: usize,
+}
+
+impl CommandInput {
+    fn byte_index(&self, cursor: usize) -> usize {
+        self.text.char_indices().nth(cursor).map_or(self.text.len(), |(index, _)| index)
+    }
+
+    fn insert(&mut self, c: char) {
+        let index = self.byte_index(self.cursor);
+        self.text.insert(index, c);
+        self.cursor += 1;
+    }
+
+    fn backspace(&mut self) {
+        if self.cursor > 0 {
+            self.cursor -= 1;
+            let index = self.byte_index(self.cursor);
+            self.text.remove(index);
+        }
+    }
+
+    fn delete(&mut self) {
+        if self.cursor < self.text.chars().count() {
+            let index = self.byte_index(self.cursor);
+            self.text.remove(index);
+        }
+    }
+}
+
+/// A file opened in its own tab
+struct TabState {
+    path: PathBuf,
+    diff: FileDiff,
+    scroll_offset: u16,
+    /// Hunk at the top of the view when the tab was last left
+    hunk_index: usize,
+}
+
 #[derive(Clone)]
 struct TreeDisplayItem {
     path: PathBuf,
     display_name: String,
     status: DiffStatus,
     is_directory: bool,
+    is_hardlinked: bool,
+    /// Similarity of a modified file, when line stats were computed
+    similarity: Option<f32>,
     indent_level: usize,
 }
 
@@ -50,17 +467,102 @@ impl TuiApp {
         Self {
             core,
             diff_result: None,
+            analysis_time: None,
+            loading: None,
             tree_state: ListState::default(),
             tree_items: Vec::new(),
             collapsed_dirs: HashSet::new(),
             selected_file: None,
             current_diff: None,
+            current_word_diff: None,
+            blame_mode: false,
+            current_blame: None,
+            explanation: None,
+            palette: None,
+            status_filter: None,
+            needs_full_redraw: false,
+            highlighter: SyntaxHighlighter::new(),
+            current_highlight: None,
+            current_metadata: (None, None),
             diff_view_mode: DiffViewMode::Unified,
+            sort_mode: SortMode::default(),
             scroll_offset: 0,
+            scroll_positions: HashMap::new(),
+            diff_area: Rect::default(),
+            current_diff_line_count: 0,
+            tabs: Vec::new(),
+            active_tab: 0,
+            command_input: None,
+            status_message: None,
+            colors: StatusColors::default(),
+            key_map: HashMap::new(),
             should_quit: false,
         }
     }
 
+    /// Use the colors and keys from the `[tui]` configuration
+    pub fn with_config(mut self, config: &TuiConfig) -> Result<Self> {
+        let color = |name: &str, value: &str| {
+            value.parse::<Color>().map_err(|_| anyhow::anyhow!("tui.{} is not a color: {:?}", name, value))
+        };
+        self.colors = StatusColors {
+            added: color("added_color", &config.added_color)?,
+            removed: color("removed_color", &config.removed_color)?,
+            modified: color("modified_color", &config.modified_color)?,
+        };
+
+        let mut bound = HashMap::new();
+        for (key, name, default) in config.key_bindings() {
+            let mut chars = key.chars();
+            let (Some(key), None) = (chars.next(), chars.next()) else {
+                anyhow::bail!("tui.{} must be a single character, got {:?}", name, key);
+            };
+            if let Some(other) = bound.insert(key, (name, default)).map(|(other, _)| other) {
+                anyhow::bail!("tui.{} and tui.{} are both set to {:?}", other, name, key);
+            }
+        }
+        self.key_map = HashMap::new();
+        for (&key, &(_, default)) in bound.iter().filter(|(key, (_, default))| *key != default) {
+            self.key_map.entry(default).or_insert(None);
+            self.key_map.insert(key, Some(default));
+        }
+        Ok(self)
+    }
+
+    /// The key that runs the command bound to `default` out of the box
+    fn bound_key(&self, default: char) -> char {
+        self.key_map
+            .iter()
+            .find(|(_, mapped)| **mapped == Some(default))
+            .map_or(default, |(key, _)| *key)
+    }
+
+    /// `key` as the default bindings see it: a rebound command key becomes its
+    /// default key, and the default key of a rebound command does nothing
+    fn remap_key(&self, mut key: KeyEvent) -> Option<KeyEvent> {
+        if let KeyCode::Char(c) = key.code {
+            if !key.modifiers.contains(KeyModifiers::CONTROL) {
+                if let Some(mapped) = self.key_map.get(&c) {
+                    key.code = KeyCode::Char((*mapped)?);
+                }
+            }
+        }
+        Some(key)
+    }
+
+    /// Start in `mode` instead of the unified view
+    pub fn with_view_mode(mut self, mode: DiffViewMode) -> Self {
+        self.diff_view_mode = mode;
+        self
+    }
+
+    /// Turn syntax highlighting of context lines on or off. Highlighting is on
+    /// by default, and only available with the `syntax-highlighting` feature.
+    pub fn with_syntax_highlighting(mut self, enabled: bool) -> Self {
+        self.highlighter = if enabled { SyntaxHighlighter::new() } else { None };
+        self
+    }
+
     pub fn run(&mut self) -> Result<()> {
         // Setup terminal
         enable_raw_mode()?;
@@ -69,8 +571,8 @@ impl TuiApp {
         let backend = CrosstermBackend::new(stdout);
         let mut terminal = Terminal::new(backend)?;
 
-        // Load initial data
-        self.load_diff_result()?;
+        // Analyze in the background so the loading screen can be drawn
+        self.start_loading();
 
         // Main loop
         let result = self.run_app(&mut terminal);
@@ -87,20 +589,102 @@ impl TuiApp {
         result
     }
 
-    fn load_diff_result(&mut self) -> Result<()> {
-        let diff_result = self.core.analyze()?;
-        
+    fn start_loading(&mut self) {
+        let (sender, receiver) = mpsc::channel();
+        let core = self.core.clone();
+        std::thread::spawn(move || {
+            let progress_sender = sender.clone();
+            let result = core.analyze_with_progress(move |done, total| {
+                let _ = progress_sender.send(LoadMessage::Progress(done, total));
+            });
+            let _ = sender.send(LoadMessage::Done(Box::new(result)));
+        });
+
+        self.loading = Some(LoadingState { receiver, progress: (0, 0), started: Instant::now() });
+    }
+
+    /// Take any messages from the analysis thread, showing the result once it arrives
+    fn poll_loading(&mut self) -> Result<()> {
+        let Some(loading) = self.loading.as_mut() else { return Ok(()) };
+
+        let result = loop {
+            match loading.receiver.try_recv() {
+                Ok(LoadMessage::Progress(done, total)) => loading.progress = (done, total),
+                Ok(LoadMessage::Done(result)) => break result,
+                Err(mpsc::TryRecvError::Empty) => return Ok(()),
+                Err(mpsc::TryRecvError::Disconnected) => anyhow::bail!("The analysis thread stopped unexpectedly"),
+            }
+        };
+
+        self.analysis_time = Some(loading.started.elapsed());
+        self.loading = None;
+        self.show_diff_result((*result)?);
+        Ok(())
+    }
+
+    fn show_diff_result(&mut self, diff_result: DiffResult) {
         // Collect all directories and mark them as collapsed by default
         Self::collect_directories(&diff_result.tree, &mut self.collapsed_dirs);
         
-        self.tree_items = Self::flatten_tree(&diff_result.tree, 0, &self.collapsed_dirs);
+        self.tree_items = Self::flatten_tree(&diff_result.tree, 0, &self.collapsed_dirs, self.sort_mode, self.status_filter.as_ref());
         if !self.tree_items.is_empty() {
             self.tree_state.select(Some(0));
         }
         self.diff_result = Some(diff_result);
+    }
+
+    /// Refresh the tree after the watcher reports `changed_paths`. Small change
+    /// sets only rescan the affected subtree; larger ones fall back to a full analysis.
+    pub fn handle_watch_event(&mut self, changed_paths: &[PathBuf]) -> Result<()> {
+        let started = Instant::now();
+        let diff_result = match &self.diff_result {
+            Some(previous) if changed_paths.len() < INCREMENTAL_REFRESH_LIMIT => {
+                self.core.incremental_analyze(previous, changed_paths)?
+            }
+            _ => self.core.analyze()?,
+        };
+
+        self.analysis_time = Some(started.elapsed());
+        self.diff_result = Some(diff_result);
+        self.refresh_tree_view();
+
+        // Reload the open diff in case the selected file was among the changes
+        if let Some(selected_file) = self.selected_file.clone() {
+            let previous_hunks = self.current_diff.as_ref().map(|diff| diff.hunks.len());
+            self.current_diff = Self::diff_file(&self.core, &selected_file).ok();
+            // The saved position only makes sense if the hunks stayed the same
+            if self.current_diff.as_ref().map(|diff| diff.hunks.len()) != previous_hunks {
+                self.scroll_positions.remove(&selected_file);
+                self.scroll_offset = 0;
+            }
+            self.current_word_diff = None;
+            self.refresh_word_diff();
+            self.current_blame = None;
+            self.refresh_blame();
+            self.current_highlight = self.highlight_current_diff();
+            self.current_metadata = self.core.get_file_metadata(&selected_file);
+        }
+        for tab in &mut self.tabs {
+            if let Ok(diff) = Self::diff_file(&self.core, &tab.path) {
+                if diff.hunks.len() != tab.diff.hunks.len() {
+                    self.scroll_positions.remove(&tab.path);
+                    tab.scroll_offset = 0;
+                }
+                tab.diff = diff;
+            }
+        }
         Ok(())
     }
 
+    /// The diff of `path`, with hunks whose context lines meet shown as one
+    fn diff_file(core: &DiffyCore, path: &Path) -> Result<FileDiff> {
+        let mut diff = core.get_file_diff(path)?;
+        if let Some(original) = diff.left_content.clone() {
+            diff.hunks = DiffEngine::merge_close_hunks(std::mem::take(&mut diff.hunks), 1, &original);
+        }
+        Ok(diff)
+    }
+
     fn collect_directories(entry: &FileEntry, collapsed_dirs: &mut HashSet<PathBuf>) {
         if entry.is_directory && !entry.relative_path.as_os_str().is_empty() {
             collapsed_dirs.insert(entry.relative_path.clone());
@@ -111,7 +695,13 @@ impl TuiApp {
         }
     }
 
-    fn flatten_tree(entry: &FileEntry, indent_level: usize, collapsed_dirs: &HashSet<PathBuf>) -> Vec<TreeDisplayItem> {
+    fn flatten_tree(
+        entry: &FileEntry,
+        indent_level: usize,
+        collapsed_dirs: &HashSet<PathBuf>,
+        sort_mode: SortMode,
+        status_filter: Option<&DiffStatus>,
+    ) -> Vec<TreeDisplayItem> {
         let mut items = Vec::new();
         
         if !entry.relative_path.as_os_str().is_empty() {
@@ -126,6 +716,8 @@ impl TuiApp {
                 display_name,
                 status: entry.status.clone(),
                 is_directory: entry.is_directory,
+                is_hardlinked: entry.is_hardlinked(),
+                similarity: entry.similarity,
                 indent_level,
             });
         }
@@ -134,36 +726,101 @@ impl TuiApp {
         let is_collapsed = entry.is_directory && collapsed_dirs.contains(&entry.relative_path);
         
         if !is_collapsed {
-            // Sort children: directories first, then files
             let mut sorted_children = entry.children.clone();
-            sorted_children.sort_by(|a, b| {
-                match (a.is_directory, b.is_directory) {
-                    (true, false) => std::cmp::Ordering::Less,
-                    (false, true) => std::cmp::Ordering::Greater,
-                    _ => a.relative_path.cmp(&b.relative_path),
-                }
-            });
+            sorted_children.sort_by(sort_mode.comparator());
 
             for child in &sorted_children {
+                if status_filter.is_some_and(|status| !Self::has_status(child, status)) {
+                    continue;
+                }
                 let child_indent = if entry.relative_path.as_os_str().is_empty() {
                     indent_level
                 } else {
                     indent_level + 1
                 };
-                items.extend(Self::flatten_tree(child, child_indent, collapsed_dirs));
+                items.extend(Self::flatten_tree(child, child_indent, collapsed_dirs, sort_mode, status_filter));
             }
         }
 
         items
     }
 
+    /// Whether `entry` is a file with `status` or a directory containing one
+    fn has_status(entry: &FileEntry, status: &DiffStatus) -> bool {
+        if entry.is_directory {
+            entry.children.iter().any(|child| Self::has_status(child, status))
+        } else {
+            entry.status == *status
+        }
+    }
+
     fn run_app<B: Backend>(&mut self, terminal: &mut Terminal<B>) -> Result<()> {
         loop {
+            if std::mem::take(&mut self.needs_full_redraw) {
+                terminal.clear()?;
+            }
             terminal.draw(|f| self.ui(f))?;
 
+            // Keep redrawing the loading screen until the analysis finishes
+            if self.loading.is_some() {
+                self.poll_loading()?;
+                if !event::poll(LOADING_POLL_INTERVAL)? {
+                    continue;
+                }
+            }
+
             if let Event::Key(key) = event::read()? {
                 if key.kind == KeyEventKind::Press {
+                    if self.loading.is_some() {
+                        if self.remap_key(key).is_some_and(|key| key.code == KeyCode::Char('q')) {
+                            break;
+                        }
+                        continue;
+                    }
+
+                    self.status_message = None;
+                    // Any key closes the summary
+                    if self.explanation.take().is_some() {
+                        continue;
+                    }
+                    if self.palette.is_some() {
+                        self.handle_palette_key(key)?;
+                        continue;
+                    }
+                    if self.command_input.is_some() {
+                        self.handle_command_key(key)?;
+                        continue;
+                    }
+                    let Some(key) = self.remap_key(key) else {
+                        continue;
+                    };
+
+                    let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
                     match key.code {
+                        KeyCode::Char(':') => {
+                            self.command_input = Some(CommandInput::default());
+                        }
+                        KeyCode::Char('p') if ctrl => {
+                            self.palette = Some(Palette::default());
+                        }
+                        KeyCode::Char('g') if ctrl => {
+                            self.command_input = Some(CommandInput::default());
+                        }
+                        KeyCode::Char('t') if ctrl => {
+                            self.open_selected_file(true)?;
+                        }
+                        KeyCode::Char('w') if ctrl => {
+                            self.close_active_tab();
+                        }
+                        // Most terminals send Ctrl+[ as a plain Esc, which is
+                        // deliberately not a tab key, so Ctrl+[ only works where
+                        // the terminal reports it as such; Ctrl+] may arrive as Ctrl+5
+                        KeyCode::Char('[') if ctrl => {
+                            self.switch_tab(false);
+                        }
+                        KeyCode::Char(']') | KeyCode::Char('5') if ctrl => {
+                            self.switch_tab(true);
+                        }
                         KeyCode::Char('q') => {
                             self.should_quit = true;
                         }
@@ -185,20 +842,64 @@ impl TuiApp {
                         KeyCode::Char(' ') => {
                             self.toggle_current_directory();
                         }
+                        KeyCode::Char('a') => {
+                            self.expand_all();
+                        }
+                        KeyCode::Char('z') => {
+                            self.collapse_all();
+                        }
                         KeyCode::Char('u') => {
                             self.diff_view_mode = DiffViewMode::Unified;
                         }
                         KeyCode::Char('s') => {
                             self.diff_view_mode = DiffViewMode::SideBySide;
                         }
-                        KeyCode::PageDown | KeyCode::Char('j') => {
-                            self.scroll_down();
+                        KeyCode::Char('S') => {
+                            self.sort_mode = self.sort_mode.next();
+                            self.refresh_tree_view();
                         }
-                        KeyCode::PageUp | KeyCode::Char('k') => {
-                            self.scroll_up();
+                        KeyCode::Char('w') => {
+                            self.diff_view_mode = DiffViewMode::WordDiff;
+                            self.refresh_word_diff();
+                        }
+                        KeyCode::Char('m') => {
+                            self.diff_view_mode = DiffViewMode::Metadata;
+                        }
+                        KeyCode::Char('b') => {
+                            self.toggle_blame();
+                        }
+                        KeyCode::Char('n') => {
+                            self.jump_to_change(true)?;
+                        }
+                        KeyCode::Char('N') => {
+                            self.jump_to_change(false)?;
+                        }
+                        KeyCode::Char('?') => {
+                            self.show_explanation();
+                        }
+                        KeyCode::Char('+') => {
+                            self.change_context(true);
+                        }
+                        KeyCode::Char('-') => {
+                            self.change_context(false);
+                        }
+                        KeyCode::PageDown => {
+                            self.scroll_down(self.page_step());
+                        }
+                        KeyCode::PageUp => {
+                            self.scroll_up(self.page_step());
+                        }
+                        KeyCode::Char('j') => {
+                            self.scroll_down(LINE_SCROLL_STEP);
+                        }
+                        KeyCode::Char('k') => {
+                            self.scroll_up(LINE_SCROLL_STEP);
                         }
                         KeyCode::Home => {
-                            self.scroll_offset = 0;
+                            self.set_scroll_offset(0);
+                        }
+                        KeyCode::End => {
+                            self.scroll_to_end();
                         }
                         _ => {}
                     }
@@ -212,7 +913,151 @@ impl TuiApp {
         Ok(())
     }
 
+    fn handle_command_key(&mut self, key: KeyEvent) -> Result<()> {
+        let Some(input) = self.command_input.as_mut() else { return Ok(()) };
+        match key.code {
+            KeyCode::Esc => self.command_input = None,
+            KeyCode::Enter => {
+                let command = input.text.trim().to_string();
+                self.command_input = None;
+                self.run_command(&command)?;
+            }
+            KeyCode::Backspace => input.backspace(),
+            KeyCode::Delete => input.delete(),
+            KeyCode::Left => input.cursor = input.cursor.saturating_sub(1),
+            KeyCode::Right => input.cursor = (input.cursor + 1).min(input.text.chars().count()),
+            KeyCode::Home => input.cursor = 0,
+            KeyCode::End => input.cursor = input.text.chars().count(),
+            KeyCode::Char(c) => input.insert(c),
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn handle_palette_key(&mut self, key: KeyEvent) -> Result<()> {
+        let Some(palette) = self.palette.as_mut() else { return Ok(()) };
+        let input = &mut palette.input;
+        match key.code {
+            KeyCode::Esc => self.palette = None,
+            KeyCode::Enter => {
+                let command = palette.matches().get(palette.selected).copied();
+                self.palette = None;
+                // A failed command is reported instead of closing the TUI
+                if let Some(command) = command {
+                    if let Err(e) = (command.action)(self) {
+                        self.status_message = Some(format!("{} failed: {:#}", command.name, e));
+                    }
+                }
+                return Ok(());
+            }
+            KeyCode::Up => palette.selected = palette.selected.saturating_sub(1),
+            KeyCode::Down => palette.selected = (palette.selected + 1).min(palette.matches().len().saturating_sub(1)),
+            KeyCode::Left => input.cursor = input.cursor.saturating_sub(1),
+            KeyCode::Right => input.cursor = (input.cursor + 1).min(input.text.chars().count()),
+            KeyCode::Home => input.cursor = 0,
+            KeyCode::End => input.cursor = input.text.chars().count(),
+            KeyCode::Backspace | KeyCode::Delete | KeyCode::Char(_) => {
+                match key.code {
+                    KeyCode::Backspace => input.backspace(),
+                    KeyCode::Delete => input.delete(),
+                    KeyCode::Char(c) => input.insert(c),
+                    _ => unreachable!(),
+                }
+                palette.selected = 0;
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// `:N` scrolls to line N, `:edit <path>` (or just `:<path>`) opens a file
+    fn run_command(&mut self, command: &str) -> Result<()> {
+        if command.is_empty() {
+            return Ok(());
+        }
+        if let Ok(line) = command.parse::<usize>() {
+            self.set_scroll_offset(self.row_for_line(line));
+            return Ok(());
+        }
+
+        let path = command
+            .strip_prefix("edit ")
+            .or_else(|| command.strip_prefix("e "))
+            .unwrap_or(command)
+            .trim();
+        self.open_path(Path::new(path))
+    }
+
+    /// Diff panel row showing line `line` of the right file, or the closest
+    /// line after it when that line is not part of any hunk
+    fn row_for_line(&self, line: usize) -> u16 {
+        let Some(diff) = &self.current_diff else { return 0 };
+        let target = line.saturating_sub(1);
+
+        let row = match (&self.diff_view_mode, &self.current_word_diff) {
+            (DiffViewMode::SideBySide, _) => target,
+            (DiffViewMode::WordDiff, Some(lines)) => {
+                // Removed lines do not exist in the right file
+                let mut right_lines = 0;
+                lines
+                    .iter()
+                    .position(|word_line| {
+                        if matches!(word_line, WordDiffLine::Removed(_)) {
+                            return false;
+                        }
+                        right_lines += 1;
+                        right_lines > target
+                    })
+                    .unwrap_or(lines.len().saturating_sub(1))
+            }
+            _ if diff.hunks.is_empty() => target,
+            _ => {
+                let mut rows: usize = 0;
+                let mut found = None;
+                'hunks: for hunk in &diff.hunks {
+                    rows += 1;
+                    for diff_line in &hunk.lines {
+                        let number = diff_line.new_line_number.or(diff_line.old_line_number).unwrap_or(0);
+                        if number as usize >= line {
+                            found = Some(rows);
+                            break 'hunks;
+                        }
+                        rows += 1;
+                    }
+                }
+                found.unwrap_or(rows.saturating_sub(1))
+            }
+        };
+        row.min(u16::MAX as usize) as u16
+    }
+
+    /// Reveal `path` in the tree and open its diff
+    fn open_path(&mut self, path: &Path) -> Result<()> {
+        let is_file = self
+            .diff_result
+            .as_ref()
+            .and_then(|result| result.tree.find(path))
+            .is_some_and(|entry| !entry.is_directory);
+        if !is_file {
+            self.status_message = Some(format!("No such file: {}", path.display()));
+            return Ok(());
+        }
+
+        for ancestor in path.ancestors().skip(1) {
+            self.collapsed_dirs.remove(ancestor);
+        }
+        self.refresh_tree_view();
+        if let Some(index) = self.tree_items.iter().position(|item| item.path == path) {
+            self.tree_state.select(Some(index));
+            self.open_selected_file(false)?;
+        }
+        Ok(())
+    }
+
     fn next_item(&mut self) {
+        if self.tree_items.is_empty() {
+            return;
+        }
         let i = match self.tree_state.selected() {
             Some(i) => {
                 if i >= self.tree_items.len() - 1 {
@@ -227,6 +1072,9 @@ impl TuiApp {
     }
 
     fn previous_item(&mut self) {
+        if self.tree_items.is_empty() {
+            return;
+        }
         let i = match self.tree_state.selected() {
             Some(i) => {
                 if i == 0 {
@@ -241,24 +1089,359 @@ impl TuiApp {
     }
 
     fn select_current_item(&mut self) -> Result<()> {
-        if let Some(i) = self.tree_state.selected() {
-            if let Some(item) = self.tree_items.get(i) {
-                if !item.is_directory {
-                    self.selected_file = Some(item.path.clone());
-                    self.current_diff = Some(self.core.get_file_diff(&item.path)?);
-                    self.scroll_offset = 0; // Reset scroll when selecting new file
-                }
+        self.open_selected_file(false)
+    }
+
+    /// Show the selected file in the active tab, or in a new tab after it
+    fn open_selected_file(&mut self, in_new_tab: bool) -> Result<()> {
+        let Some(item) = self.tree_state.selected().and_then(|i| self.tree_items.get(i)) else {
+            return Ok(());
+        };
+        if item.is_directory {
+            return Ok(());
+        }
+
+        let tab = TabState {
+            path: item.path.clone(),
+            diff: Self::diff_file(&self.core, &item.path)?,
+            scroll_offset: self.scroll_positions.get(&item.path).copied().unwrap_or(0),
+            hunk_index: 0,
+        };
+        if in_new_tab || self.tabs.is_empty() {
+            self.save_active_tab();
+            self.tabs.push(tab);
+            self.show_tab(self.tabs.len() - 1);
+        } else {
+            self.tabs[self.active_tab] = tab;
+            self.show_tab(self.active_tab);
+        }
+        Ok(())
+    }
+
+    /// Store the scroll position of the active tab before another is shown
+    fn save_active_tab(&mut self) {
+        let hunk_index = self.current_hunk_index();
+        if let Some(tab) = self.tabs.get_mut(self.active_tab) {
+            tab.scroll_offset = self.scroll_offset;
+            tab.hunk_index = hunk_index;
+        }
+    }
+
+    fn show_tab(&mut self, index: usize) {
+        self.active_tab = index;
+        match self.tabs.get(index) {
+            Some(tab) => {
+                self.selected_file = Some(tab.path.clone());
+                self.current_diff = Some(tab.diff.clone());
+                self.scroll_offset = tab.scroll_offset;
+            }
+            None => {
+                self.selected_file = None;
+                self.current_diff = None;
+                self.scroll_offset = 0;
+            }
+        }
+        self.current_word_diff = None;
+        self.refresh_word_diff();
+        self.current_blame = None;
+        self.refresh_blame();
+        self.current_highlight = self.highlight_current_diff();
+        self.current_metadata = match &self.selected_file {
+            Some(path) => self.core.get_file_metadata(path),
+            None => (None, None),
+        };
+    }
+
+    fn switch_tab(&mut self, forward: bool) {
+        let count = self.tabs.len();
+        if count < 2 {
+            return;
+        }
+        self.save_active_tab();
+        let index = if forward { (self.active_tab + 1) % count } else { (self.active_tab + count - 1) % count };
+        self.show_tab(index);
+    }
+
+    /// Show one more or one fewer line of context around each change in every
+    /// open tab, keeping the change at the top of the view in place
+    fn change_context(&mut self, more: bool) {
+        let context = self.core.diff_options.context_lines;
+        let context = if more { context + 1 } else { context.saturating_sub(1) };
+        self.core.diff_options.context_lines = context;
+
+        // Right-side line of the first change in the hunk at the top of the view
+        let top_line = self
+            .current_diff
+            .as_ref()
+            .and_then(|diff| diff.hunks.get(self.current_hunk_index()))
+            .map(|hunk| hunk.new_start as usize + hunk.before_context().len());
+        for tab in &mut self.tabs {
+            if let Ok(diff) = Self::diff_file(&self.core, &tab.path) {
+                self.scroll_positions.remove(&tab.path);
+                tab.scroll_offset = 0;
+                tab.diff = diff;
+            }
+        }
+        self.show_tab(self.active_tab);
+        if let Some(line) = top_line {
+            self.set_scroll_offset(self.row_for_line(line));
+        }
+        self.status_message = Some(format!("Showing {} lines of context", context));
+    }
+
+    fn close_active_tab(&mut self) {
+        if self.tabs.is_empty() {
+            return;
+        }
+        self.tabs.remove(self.active_tab);
+        self.show_tab(self.active_tab.min(self.tabs.len().saturating_sub(1)));
+    }
+
+    /// Index of the hunk at the top of the unified view
+    fn current_hunk_index(&self) -> usize {
+        let Some(diff) = &self.current_diff else { return 0 };
+        let mut rows = 0;
+        for (index, hunk) in diff.hunks.iter().enumerate() {
+            // One row for the @@ header plus one per line
+            rows += 1 + hunk.lines.len();
+            if rows > self.scroll_offset as usize {
+                return index;
+            }
+        }
+        diff.hunks.len().saturating_sub(1)
+    }
+
+    fn refresh_word_diff(&mut self) {
+        if self.diff_view_mode != DiffViewMode::WordDiff || self.current_word_diff.is_some() {
+            return;
+        }
+        if let Some(diff) = self.current_diff.as_ref().filter(|diff| !diff.is_binary) {
+            let engine = DiffEngine::with_options(self.core.diff_options.clone());
+            self.current_word_diff = Some(engine.word_diff(
+                diff.left_content.as_deref().map(String::as_str).unwrap_or(""),
+                diff.right_content.as_deref().map(String::as_str).unwrap_or(""),
+            ));
+        }
+    }
+
+    /// Blame is shown alongside the unified view
+    fn toggle_blame(&mut self) {
+        self.blame_mode = !self.blame_mode;
+        if self.blame_mode {
+            self.diff_view_mode = DiffViewMode::Unified;
+        }
+        self.refresh_blame();
+    }
+
+    /// First row of each hunk of the open diff in the current view mode
+    fn hunk_rows(&self) -> Vec<u16> {
+        let Some(diff) = &self.current_diff else { return Vec::new() };
+        match self.diff_view_mode {
+            DiffViewMode::Metadata => Vec::new(),
+            DiffViewMode::Unified => {
+                // One row for each @@ header plus one per line
+                let mut row = 0;
+                diff.hunks
+                    .iter()
+                    .map(|hunk| {
+                        let start = row;
+                        row += 1 + hunk.lines.len();
+                        start.min(u16::MAX as usize) as u16
+                    })
+                    .collect()
+            }
+            _ => diff.hunks.iter().map(|hunk| self.row_for_line(hunk.new_start.max(1) as usize)).collect(),
+        }
+    }
+
+    /// Scroll to the next or previous hunk of the open diff, moving on to the
+    /// next or previous changed file in the tree past either end
+    fn jump_to_change(&mut self, forward: bool) -> Result<()> {
+        let rows = self.hunk_rows();
+        let current = self.scroll_offset;
+        let target = if forward {
+            rows.iter().find(|row| **row > current)
+        } else {
+            rows.iter().rev().find(|row| **row < current)
+        };
+        if let Some(row) = target {
+            self.set_scroll_offset(*row);
+            return Ok(());
+        }
+
+        let start = self
+            .selected_file
+            .as_ref()
+            .and_then(|path| self.tree_items.iter().position(|item| &item.path == path))
+            .or(self.tree_state.selected());
+        let is_changed = |item: &TreeDisplayItem| !item.is_directory && item.status != DiffStatus::Unchanged;
+        let next = if forward {
+            let from = start.map_or(0, |index| index + 1);
+            self.tree_items.iter().skip(from).position(is_changed).map(|index| index + from)
+        } else {
+            self.tree_items[..start.unwrap_or(0)].iter().rposition(is_changed)
+        };
+        let Some(index) = next else {
+            self.status_message = Some(format!("No {} changes", if forward { "later" } else { "earlier" }));
+            return Ok(());
+        };
+
+        self.tree_state.select(Some(index));
+        self.open_selected_file(false)?;
+        let rows = self.hunk_rows();
+        let row = if forward { rows.first() } else { rows.last() };
+        self.set_scroll_offset(row.copied().unwrap_or(0));
+        Ok(())
+    }
+
+    fn set_status_filter(&mut self, status: Option<DiffStatus>) {
+        self.status_filter = status;
+        self.refresh_tree_view();
+    }
+
+    /// Suspend the TUI and edit the open file (the right side, or the left if
+    /// it was removed), then pick up any changes
+    fn open_in_editor(&mut self) -> Result<()> {
+        let Some(path) = self.selected_file.clone() else {
+            self.status_message = Some("No file is open".to_string());
+            return Ok(());
+        };
+        let right = self.core.right_path.join(&path);
+        let file = if right.exists() { right } else { self.core.left_path.join(&path) };
+
+        let editor = std::env::var("VISUAL")
+            .or_else(|_| std::env::var("EDITOR"))
+            .unwrap_or_else(|_| "vi".to_string());
+        let mut words = editor.split_whitespace();
+        let program = words.next().unwrap_or("vi");
+
+        disable_raw_mode()?;
+        execute!(io::stdout(), LeaveAlternateScreen, DisableMouseCapture)?;
+        let status = std::process::Command::new(program).args(words).arg(&file).status();
+        enable_raw_mode()?;
+        execute!(io::stdout(), EnterAlternateScreen, EnableMouseCapture)?;
+        self.needs_full_redraw = true;
+
+        match status {
+            Ok(_) => self.handle_watch_event(&[file]),
+            Err(e) => {
+                self.status_message = Some(format!("Could not start {}: {}", program, e));
+                Ok(())
             }
         }
+    }
+
+    /// Copy the open file's unified diff with an OSC 52 escape sequence, which
+    /// most terminals forward to the system clipboard, also over SSH
+    fn copy_diff(&mut self) {
+        let (Some(path), Some(diff)) = (&self.selected_file, &self.current_diff) else {
+            self.status_message = Some("No file is open".to_string());
+            return;
+        };
+        let status = self
+            .diff_result
+            .as_ref()
+            .and_then(|result| result.tree.find(path))
+            .map_or(DiffStatus::Modified, |entry| entry.status.clone());
+        let text = export::unified_file(path, &status, diff);
+
+        let mut stdout = io::stdout();
+        let copied = write!(stdout, "\x1b]52;c;{}\x07", base64_encode(text.as_bytes())).and_then(|_| stdout.flush());
+        self.status_message = Some(match copied {
+            Ok(()) => format!("Copied the diff of {}", path.display()),
+            Err(e) => format!("Could not copy the diff: {}", e),
+        });
+    }
+
+    fn export_patch(&mut self) -> Result<()> {
+        let Some(result) = &self.diff_result else { return Ok(()) };
+        let patch = self.core.export(result, ExportFormat::Unified)?;
+        std::fs::write(PATCH_EXPORT_FILE, patch)?;
+        self.status_message = Some(format!("Wrote {}", PATCH_EXPORT_FILE));
         Ok(())
     }
 
-    fn scroll_down(&mut self) {
-        self.scroll_offset = self.scroll_offset.saturating_add(3);
+    fn show_explanation(&mut self) {
+        let Some(result) = &self.diff_result else { return };
+        self.explanation = Some(self.core.explain(result));
     }
 
-    fn scroll_up(&mut self) {
-        self.scroll_offset = self.scroll_offset.saturating_sub(3);
+    fn refresh_blame(&mut self) {
+        if !self.blame_mode || self.current_blame.is_some() {
+            return;
+        }
+        if let Some(path) = &self.selected_file {
+            self.current_blame = self.core.get_file_blame(path, None).ok();
+        }
+    }
+
+    /// The blame column for a line of the right side: short hash and author,
+    /// blank for lines without a commit. `None` unless blame mode is on.
+    fn blame_span(&self, line_number: Option<u32>) -> Option<Span<'static>> {
+        if !self.blame_mode {
+            return None;
+        }
+        let blame = line_number
+            .zip(self.current_blame.as_ref())
+            .and_then(|(number, lines)| lines.get((number as usize).checked_sub(1)?));
+        let text = match blame.and_then(|line| line.commit_hash.as_deref().zip(line.author.as_deref())) {
+            Some((hash, author)) => {
+                let author: String = author.chars().take(BLAME_AUTHOR_WIDTH).collect();
+                format!("{:.7} {:<width$} ", hash, author, width = BLAME_AUTHOR_WIDTH)
+            }
+            None => " ".repeat(BLAME_AUTHOR_WIDTH + 9),
+        };
+        Some(Span::styled(text, Style::default().fg(Color::DarkGray)))
+    }
+
+    fn highlight_current_diff(&self) -> Option<HighlightedLines> {
+        let highlighter = self.highlighter.as_ref()?;
+        let path = self.selected_file.as_ref()?;
+        let diff = self.current_diff.as_ref().filter(|diff| !diff.is_binary)?;
+        highlighter.highlight(path, diff.right_content.as_deref()?)
+    }
+
+    /// Scroll the open file, remembering the position for when it is reopened
+    fn set_scroll_offset(&mut self, offset: u16) {
+        self.scroll_offset = offset;
+        if let Some(path) = &self.selected_file {
+            self.scroll_positions.insert(path.clone(), offset);
+        }
+    }
+
+    fn scroll_down(&mut self, step: u16) {
+        self.set_scroll_offset(self.scroll_offset.saturating_add(step));
+    }
+
+    fn scroll_up(&mut self, step: u16) {
+        self.set_scroll_offset(self.scroll_offset.saturating_sub(step));
+    }
+
+    /// The diff panel height minus two rows, so a line of context stays in view
+    fn page_step(&self) -> u16 {
+        self.diff_area.height.saturating_sub(2).max(1)
+    }
+
+    /// Scroll so the last line of the diff sits at the bottom of the panel
+    fn scroll_to_end(&mut self) {
+        let visible_rows = self.diff_area.height.saturating_sub(2) as usize;
+        let offset = self.current_diff_line_count.saturating_sub(visible_rows);
+        self.set_scroll_offset(offset.min(u16::MAX as usize) as u16);
+    }
+
+    /// Rows taken up by the open diff in the current view mode
+    fn diff_line_count(&self) -> usize {
+        let Some(diff) = &self.current_diff else { return 0 };
+        let content_lines = |content: Option<&String>| content.map_or(0, |content| content.lines().count());
+
+        match (&self.diff_view_mode, &self.current_word_diff) {
+            (DiffViewMode::Metadata, _) => METADATA_FIELDS.len() + 1,
+            (DiffViewMode::SideBySide, _) => content_lines(diff.left_content.as_deref()).max(content_lines(diff.right_content.as_deref())),
+            (DiffViewMode::WordDiff, Some(lines)) => lines.len(),
+            _ if diff.hunks.is_empty() => content_lines(diff.left_content.as_deref().or(diff.right_content.as_deref())),
+            // One row for each @@ header plus one per line
+            _ => diff.hunks.iter().map(|hunk| 1 + hunk.lines.len()).sum(),
+        }
     }
 
     fn toggle_current_directory(&mut self) {
@@ -298,41 +1481,241 @@ impl TuiApp {
         }
     }
 
+    /// Expand every directory, keeping the current selection
+    fn expand_all(&mut self) {
+        self.collapsed_dirs.clear();
+        self.refresh_tree_view();
+    }
+
+    /// Collapse every directory and select the first top-level one
+    fn collapse_all(&mut self) {
+        if let Some(diff_result) = &self.diff_result {
+            Self::collect_directories(&diff_result.tree, &mut self.collapsed_dirs);
+        }
+        self.refresh_tree_view();
+
+        let first_dir = self.tree_items.iter().position(|item| item.is_directory && item.indent_level == 0);
+        if let Some(index) = first_dir.or((!self.tree_items.is_empty()).then_some(0)) {
+            self.tree_state.select(Some(index));
+        }
+    }
+
     fn refresh_tree_view(&mut self) {
         if let Some(ref diff_result) = self.diff_result.clone() {
             let selected_path = self.tree_state.selected()
                 .and_then(|i| self.tree_items.get(i))
                 .map(|item| item.path.clone());
             
-            self.tree_items = Self::flatten_tree(&diff_result.tree, 0, &self.collapsed_dirs);
+            self.tree_items = Self::flatten_tree(&diff_result.tree, 0, &self.collapsed_dirs, self.sort_mode, self.status_filter.as_ref());
             
-            // Try to maintain selection
-            if let Some(selected_path) = selected_path {
+            // Try to maintain selection; a filter that matches nothing leaves none
+            if self.tree_items.is_empty() {
+                self.tree_state.select(None);
+            } else if let Some(selected_path) = selected_path {
                 if let Some(new_index) = self.tree_items.iter().position(|item| item.path == selected_path) {
                     self.tree_state.select(Some(new_index));
-                } else if !self.tree_items.is_empty() {
+                } else {
                     self.tree_state.select(Some(0));
                 }
-            } else if !self.tree_items.is_empty() {
+            } else {
                 self.tree_state.select(Some(0));
             }
         }
     }
 
     fn ui(&mut self, f: &mut Frame) {
+        if let Some(loading) = &self.loading {
+            Self::render_loading(f, loading);
+            return;
+        }
+
+        let mut area = f.size();
+        if self.command_input.is_some() || self.status_message.is_some() {
+            let rows = Layout::default()
+                .direction(Direction::Vertical)
+                .constraints([Constraint::Min(0), Constraint::Length(1)])
+                .split(area);
+            area = rows[0];
+            self.render_command_line(f, rows[1]);
+        }
+
+        // Comparisons with a description, such as git branches, show it on top
+        if let Some(description) = &self.core.description {
+            let rows = Layout::default()
+                .direction(Direction::Vertical)
+                .constraints([Constraint::Length(1), Constraint::Min(0)])
+                .split(area);
+            let header = Paragraph::new(format!(" {} ", description))
+                .style(Style::default().fg(Color::Black).bg(Color::Cyan).add_modifier(Modifier::BOLD));
+            f.render_widget(header, rows[0]);
+            area = rows[1];
+        }
+
         let chunks = Layout::default()
             .direction(Direction::Horizontal)
             .constraints([Constraint::Percentage(25), Constraint::Percentage(75)])
-            .split(f.size());
+            .split(area);
 
         // File tree panel
         self.render_file_tree(f, chunks[0]);
 
-        // Diff panel
-        self.render_diff_panel(f, chunks[1]);
+        // Diff panel, with a tab bar above it once a file is open
+        self.diff_area = chunks[1];
+        if !self.tabs.is_empty() {
+            let diff_chunks = Layout::default()
+                .direction(Direction::Vertical)
+                .constraints([Constraint::Length(1), Constraint::Min(0)])
+                .split(chunks[1]);
+            self.render_tab_bar(f, diff_chunks[0]);
+            self.diff_area = diff_chunks[1];
+        }
+        self.current_diff_line_count = self.diff_line_count();
+        self.render_diff_panel(f, self.diff_area);
+
+        if let Some(explanation) = &self.explanation {
+            Self::render_explanation(f, f.size(), explanation);
+        }
+        if let Some(palette) = &self.palette {
+            self.render_palette(f, f.size(), palette);
+        }
+    }
+
+    /// The `Ctrl+P` palette: the typed filter above the matching commands
+    fn render_palette(&self, f: &mut Frame, area: Rect, palette: &Palette) {
+        let rows = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Percentage(20), Constraint::Percentage(60), Constraint::Percentage(20)])
+            .split(area);
+        let columns = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Percentage(20), Constraint::Percentage(60), Constraint::Percentage(20)])
+            .split(rows[1]);
+
+        let block = Block::default().borders(Borders::ALL).title("Commands");
+        let inner = block.inner(columns[1]);
+        f.render_widget(Clear, columns[1]);
+        f.render_widget(block, columns[1]);
+        let parts = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Length(1), Constraint::Min(0)])
+            .split(inner);
+
+        let input = Paragraph::new(format!("> {}", palette.input.text)).style(Style::default().fg(Color::Yellow));
+        f.render_widget(input, parts[0]);
+        let cursor_x = parts[0].x.saturating_add(2 + palette.input.cursor as u16).min(parts[0].right().saturating_sub(1));
+        f.set_cursor(cursor_x, parts[0].y);
+
+        let matches = palette.matches();
+        let items: Vec<ListItem> = matches
+            .iter()
+            .map(|command| {
+                ListItem::new(Line::from(vec![
+                    Span::styled(format!("{:<28}", command.name), Style::default().add_modifier(Modifier::BOLD)),
+                    Span::styled(format!("{:<4}", command.key_label(self)), Style::default().fg(Color::Yellow)),
+                    Span::styled(command.description, Style::default().fg(Color::DarkGray)),
+                ]))
+            })
+            .collect();
+        let mut state = ListState::default();
+        state.select((!matches.is_empty()).then_some(palette.selected));
+        let list = List::new(items).highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+        f.render_stateful_widget(list, parts[1], &mut state);
+    }
+
+    /// The `?` summary in a box over the middle of the screen
+    fn render_explanation(f: &mut Frame, area: Rect, explanation: &str) {
+        let rows = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Percentage(30), Constraint::Percentage(40), Constraint::Percentage(30)])
+            .split(area);
+        let columns = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Percentage(20), Constraint::Percentage(60), Constraint::Percentage(20)])
+            .split(rows[1]);
+
+        let summary = Paragraph::new(explanation)
+            .block(Block::default().borders(Borders::ALL).title("Summary · any key to close"))
+            .wrap(Wrap { trim: true });
+        f.render_widget(Clear, columns[1]);
+        f.render_widget(summary, columns[1]);
+    }
+
+    /// A spinner with the file count and a progress bar, centered on screen
+    fn render_loading(f: &mut Frame, loading: &LoadingState) {
+        let rows = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Percentage(45), Constraint::Length(2), Constraint::Min(0)])
+            .split(f.size());
+        let columns = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Percentage(25), Constraint::Percentage(50), Constraint::Percentage(25)])
+            .split(rows[1]);
+        let lines = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Length(1), Constraint::Length(1)])
+            .split(columns[1]);
+
+        let frame = (loading.started.elapsed().as_millis() / LOADING_POLL_INTERVAL.as_millis()) as usize;
+        let spinner = SPINNER_FRAMES[frame % SPINNER_FRAMES.len()];
+        let (done, total) = loading.progress;
+        let text = if total == 0 {
+            format!("{} Scanning files...", spinner)
+        } else {
+            format!("{} Analyzing {} files...", spinner, total)
+        };
+        f.render_widget(Paragraph::new(text).alignment(ratatui::layout::Alignment::Center), lines[0]);
+
+        let ratio = if total == 0 { 0.0 } else { done.min(total) as f64 / total as f64 };
+        let gauge = Gauge::default()
+            .gauge_style(Style::default().fg(Color::Green).bg(Color::DarkGray))
+            .ratio(ratio);
+        f.render_widget(gauge, lines[1]);
+    }
+
+    fn render_command_line(&self, f: &mut Frame, area: Rect) {
+        if let Some(input) = &self.command_input {
+            let paragraph = Paragraph::new(format!(":{}", input.text))
+                .style(Style::default().fg(Color::Black).bg(Color::Yellow));
+            f.render_widget(paragraph, area);
+            let cursor_x = area.x.saturating_add(1 + input.cursor as u16).min(area.right().saturating_sub(1));
+            f.set_cursor(cursor_x, area.y);
+        } else if let Some(message) = &self.status_message {
+            let paragraph = Paragraph::new(message.as_str()).style(Style::default().fg(Color::Red));
+            f.render_widget(paragraph, area);
+        }
+    }
+
+    fn render_tab_bar(&self, f: &mut Frame, area: Rect) {
+        let titles: Vec<Line> = self
+            .tabs
+            .iter()
+            .enumerate()
+            .map(|(index, tab)| {
+                let name = tab.path.file_name().unwrap_or_default().to_string_lossy();
+                let hunk_index = if index == self.active_tab { self.current_hunk_index() } else { tab.hunk_index };
+                match tab.diff.hunks.len() {
+                    0 => Line::from(name.to_string()),
+                    hunks => Line::from(format!("{} [{}/{}]", name, hunk_index + 1, hunks)),
+                }
+            })
+            .collect();
+
+        let tabs = Tabs::new(titles)
+            .select(self.active_tab)
+            .style(Style::default().fg(Color::DarkGray))
+            .highlight_style(Style::default().fg(Color::White).add_modifier(Modifier::BOLD))
+            .divider("│");
+        f.render_widget(tabs, area);
     }
 
     fn render_file_tree(&mut self, f: &mut Frame, area: Rect) {
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Percentage(80), Constraint::Percentage(20)])
+            .split(area);
+        self.render_stats(f, chunks[1]);
+        let area = chunks[0];
+
         let items: Vec<ListItem> = self
             .tree_items
             .iter()
@@ -351,20 +1734,38 @@ impl TuiApp {
                     "  "
                 };
                 
-                let icon = if item.is_directory { "📁" } else { "📄" };
+                let icon = if item.is_directory {
+                    "📁"
+                } else if item.is_hardlinked {
+                    "🔗"
+                } else {
+                    "📄"
+                };
                 let status_icon = item.status.icon();
                 let color = match item.status {
-                    DiffStatus::Added => Color::Green,
-                    DiffStatus::Removed => Color::Red,
-                    DiffStatus::Modified => Color::Yellow,
+                    DiffStatus::Added => self.colors.added,
+                    DiffStatus::Removed => self.colors.removed,
+                    DiffStatus::Modified => self.colors.modified,
                     DiffStatus::Unchanged => Color::White,
                     DiffStatus::Conflicted => Color::Magenta,
                 };
 
+                // Without line stats, e.g. for a core set up elsewhere, only the
+                // open file's similarity is known
+                let similarity = item.similarity.or_else(|| {
+                    let diff = self.current_diff.as_ref().filter(|diff| !diff.is_binary)?;
+                    (item.status == DiffStatus::Modified && self.selected_file.as_ref() == Some(&item.path))
+                        .then_some(diff.similarity)
+                });
+                let similarity = similarity
+                    .map(|similarity| format!("({:.0}%) ", similarity * 100.0))
+                    .unwrap_or_default();
+
                 ListItem::new(Line::from(vec![
                     Span::raw(indent),
                     Span::styled(status_icon, Style::default().fg(color)),
                     Span::raw(" "),
+                    Span::styled(similarity, Style::default().fg(Color::DarkGray)),
                     Span::styled(tree_connector, Style::default().fg(Color::DarkGray)),
                     Span::styled(expand_indicator, Style::default().fg(Color::DarkGray)),
                     Span::raw(icon),
@@ -374,41 +1775,116 @@ impl TuiApp {
             })
             .collect();
 
+        let mut title = "Files".to_string();
+        if let Some(status) = &self.status_filter {
+            title.push_str(&format!(" · {:?} only", status).to_lowercase());
+        }
+        if self.sort_mode != SortMode::Name {
+            title.push_str(&format!(" · by {}", self.sort_mode.name()));
+        }
         let list = List::new(items)
-            .block(Block::default().borders(Borders::ALL).title("Files"))
+            .block(Block::default().borders(Borders::ALL).title(title))
             .highlight_style(Style::default().add_modifier(Modifier::REVERSED))
             .highlight_symbol("▶ ");
 
         f.render_stateful_widget(list, area, &mut self.tree_state);
     }
 
+    /// Changed file counts as bars scaled to the total, plus analysis timing
+    fn render_stats(&self, f: &mut Frame, area: Rect) {
+        let block = Block::default().borders(Borders::ALL).title("Stats");
+        let inner = block.inner(area);
+        f.render_widget(block, area);
+
+        let Some(result) = &self.diff_result else {
+            f.render_widget(Paragraph::new("Analyzing..."), inner);
+            return;
+        };
+
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Length(5), Constraint::Min(0)])
+            .split(inner);
+
+        let bar = |label: &'static str, count: usize, color: Color| {
+            Bar::default()
+                .label(Line::from(label))
+                .value(count as u64)
+                .style(Style::default().fg(color))
+                .value_style(Style::default().fg(Color::Black).bg(color))
+        };
+        let chart = BarChart::default()
+            .direction(Direction::Horizontal)
+            .bar_width(1)
+            .bar_gap(0)
+            .max(result.total_files.max(1) as u64)
+            .data(BarGroup::default().bars(&[
+                bar("+", result.added_count, self.colors.added),
+                bar("-", result.removed_count, self.colors.removed),
+                bar("~", result.modified_count, self.colors.modified),
+                bar("=", result.unchanged_count, Color::Gray),
+                bar("b", result.binary_count, Color::Magenta),
+            ]));
+        f.render_widget(chart, chunks[0]);
+
+        let mut summary = format!("{} files", result.total_files);
+        if let Some(elapsed) = self.analysis_time {
+            summary.push_str(&format!(" in {:.2}s", elapsed.as_secs_f64()));
+        }
+        f.render_widget(Paragraph::new(summary).style(Style::default().fg(Color::DarkGray)), chunks[1]);
+    }
+
     fn render_diff_panel(&self, f: &mut Frame, area: Rect) {
         if let Some(diff) = &self.current_diff {
             match self.diff_view_mode {
                 DiffViewMode::Unified => self.render_unified_diff(f, area, diff),
                 DiffViewMode::SideBySide => self.render_side_by_side_diff(f, area, diff),
+                DiffViewMode::WordDiff => match &self.current_word_diff {
+                    Some(lines) => self.render_word_diff(f, area, lines),
+                    None => self.render_unified_diff(f, area, diff),
+                },
+                DiffViewMode::Metadata => self.render_metadata(f, area),
             }
         } else {
             let mode_text = match self.diff_view_mode {
                 DiffViewMode::Unified => "Unified",
                 DiffViewMode::SideBySide => "Side-by-Side",
+                DiffViewMode::WordDiff => "Word Diff",
+                DiffViewMode::Metadata => "Metadata",
             };
             
+            let key = |default: char| self.bound_key(default);
             let help_text = vec![
                 Line::from("File Navigation:"),
                 Line::from("  ↑/↓ arrows - Navigate file tree"),
                 Line::from("  Enter - View file diff"),
                 Line::from("  ←/→ arrows - Collapse/expand directory"),
                 Line::from("  Space - Toggle directory"),
+                Line::from(format!("  {}/{} - Expand/collapse all directories", key('a'), key('z'))),
+                Line::from(format!("  {} - Sort by name, status or size", key('S'))),
                 Line::from(""),
                 Line::from("Diff Controls:"),
-                Line::from("  u - Unified diff mode"),
-                Line::from("  s - Side-by-side mode"),
-                Line::from("  j/PageDown - Scroll down"),
-                Line::from("  k/PageUp - Scroll up"),
-                Line::from("  Home - Scroll to top"),
+                Line::from(format!("  {} - Unified diff mode", key('u'))),
+                Line::from(format!("  {} - Side-by-side mode", key('s'))),
+                Line::from(format!("  {} - Word diff mode", key('w'))),
+                Line::from(format!("  {} - Metadata view", key('m'))),
+                Line::from(format!("  {} - Toggle blame column", key('b'))),
+                Line::from(format!("  {}/{} - Next/previous change", key('n'), key('N'))),
+                Line::from(format!("  {}/{} - More/less context around changes", key('+'), key('-'))),
+                Line::from("  j/k - Scroll down/up"),
+                Line::from("  PageDown/PageUp - Scroll by a page"),
+                Line::from("  Home/End - Scroll to top/bottom"),
+                Line::from("  :N or Ctrl+G - Go to line N"),
+                Line::from("  :edit <path> - Open a file"),
+                Line::from(""),
+                Line::from("Tabs:"),
+                Line::from("  Ctrl+T - Open file in a new tab"),
+                Line::from("  Ctrl+W - Close tab"),
+                Line::from("  Ctrl+[ / Ctrl+] - Previous/next tab"),
                 Line::from(""),
-                Line::from("  q - Quit"),
+                Line::from("  Ctrl+P - Command palette"),
+                Line::from(format!("  {} - Summarize all changes", key('?'))),
+                Line::from(format!("  {} - Quit", key('q'))),
                 Line::from(""),
                 Line::from(format!("Current mode: {}", mode_text)),
             ];
@@ -423,8 +1899,16 @@ impl TuiApp {
         if diff.hunks.is_empty() {
             let content = diff.left_content.as_deref()
                 .or(diff.right_content.as_deref())
+                .map(String::as_str)
                 .unwrap_or("File not found");
-            let lines: Vec<Line> = content.lines().map(|line| Line::from(line)).collect();
+            let lines: Vec<Line> = content
+                .lines()
+                .enumerate()
+                .map(|(index, line)| {
+                    let blame = self.blame_span(diff.right_content.as_ref().map(|_| index as u32 + 1));
+                    Line::from(blame.into_iter().chain([Span::raw(line)]).collect::<Vec<_>>())
+                })
+                .collect();
             let paragraph = Paragraph::new(lines)
                 .block(Block::default().borders(Borders::ALL).title("No Changes"))
                 .wrap(Wrap { trim: false })
@@ -437,28 +1921,46 @@ impl TuiApp {
         
         for hunk in &diff.hunks {
             // Add hunk header with full background
-            diff_lines.push(Line::from(vec![
-                Span::styled(
-                    format!("@@ -{},{} +{},{} @@", 
-                        hunk.old_start, hunk.old_lines, 
-                        hunk.new_start, hunk.new_lines),
-                    Style::default().fg(Color::Cyan).bg(Color::DarkGray)
-                )
-            ]));
+            let mut header = Vec::from_iter(self.blame_span(None));
+            header.push(Span::styled(
+                format!("@@ -{},{} +{},{} @@", 
+                    hunk.old_start, hunk.old_lines, 
+                    hunk.new_start, hunk.new_lines),
+                Style::default().fg(Color::Cyan).bg(Color::DarkGray)
+            ));
+            diff_lines.push(Line::from(header));
 
             // Add diff lines with background colors
             for line in &hunk.lines {
+                // Context lines are the same on both sides, so take their colors
+                // from the highlighted right side
+                let highlighted = match line.kind {
+                    crate::core::types::DiffLineKind::Context => line
+                        .new_line_number
+                        .zip(self.current_highlight.as_ref())
+                        .and_then(|(number, lines)| lines.get((number as usize).checked_sub(1)?)),
+                    _ => None,
+                };
+                let blame = self.blame_span(line.new_line_number);
+                if let Some(spans) = highlighted {
+                    let mut line_spans = Vec::from_iter(blame);
+                    line_spans.push(Span::raw(" "));
+                    line_spans.extend(spans.iter().cloned());
+                    diff_lines.push(Line::from(line_spans));
+                    continue;
+                }
+
                 let (fg_color, bg_color, prefix) = match line.kind {
-                    crate::core::types::DiffLineKind::Addition => (Color::Green, Color::Rgb(0, 64, 0), "+"),
-                    crate::core::types::DiffLineKind::Deletion => (Color::Red, Color::Rgb(64, 0, 0), "-"),
+                    crate::core::types::DiffLineKind::Addition => (self.colors.added, Color::Rgb(0, 64, 0), "+"),
+                    crate::core::types::DiffLineKind::Deletion => (self.colors.removed, Color::Rgb(64, 0, 0), "-"),
                     crate::core::types::DiffLineKind::Context => (Color::White, Color::Reset, " "),
                 };
 
                 // Create single span for the entire line to avoid rendering issues
                 let full_line = format!("{}{}", prefix, line.content);
-                diff_lines.push(Line::from(vec![
-                    Span::styled(full_line, Style::default().fg(fg_color).bg(bg_color))
-                ]));
+                let mut line_spans = Vec::from_iter(blame);
+                line_spans.push(Span::styled(full_line, Style::default().fg(fg_color).bg(bg_color)));
+                diff_lines.push(Line::from(line_spans));
             }
         }
 
@@ -469,6 +1971,69 @@ impl TuiApp {
         f.render_widget(diff_paragraph, area);
     }
 
+    fn render_word_diff(&self, f: &mut Frame, area: Rect, lines: &[WordDiffLine]) {
+        // Changed words get a brighter background than the rest of their line
+        let spans = |prefix: &'static str, spans: &[WordSpan], fg: Color, dim: Color, bright: Color| {
+            let mut line = vec![Span::styled(prefix, Style::default().fg(fg).bg(dim))];
+            line.extend(spans.iter().map(|span| {
+                let bg = if span.changed { bright } else { dim };
+                Span::styled(span.text.clone(), Style::default().fg(fg).bg(bg))
+            }));
+            Line::from(line)
+        };
+
+        let diff_lines: Vec<Line> = lines
+            .iter()
+            .map(|line| match line {
+                WordDiffLine::Context(text) => Line::from(format!(" {}", text)),
+                WordDiffLine::Added(added) => spans("+", added, self.colors.added, Color::Rgb(0, 40, 0), Color::Rgb(0, 96, 0)),
+                WordDiffLine::Removed(removed) => spans("-", removed, self.colors.removed, Color::Rgb(40, 0, 0), Color::Rgb(96, 0, 0)),
+            })
+            .collect();
+
+        let diff_paragraph = Paragraph::new(diff_lines)
+            .block(Block::default().borders(Borders::ALL).title("Word Diff"))
+            .wrap(Wrap { trim: false })
+            .scroll((self.scroll_offset, 0));
+        f.render_widget(diff_paragraph, area);
+    }
+
+    fn render_metadata(&self, f: &mut Frame, area: Rect) {
+        let (left, right) = &self.current_metadata;
+        let left_values = Self::metadata_values(left.as_ref());
+        let right_values = Self::metadata_values(right.as_ref());
+
+        // Fields that differ between the sides are highlighted
+        let rows = METADATA_FIELDS.into_iter().zip(left_values).zip(right_values).map(|((name, left_value), right_value)| {
+            let style = if left_value != right_value {
+                Style::default().fg(Color::Yellow)
+            } else {
+                Style::default()
+            };
+            Row::new(vec![Cell::from(name), Cell::from(left_value), Cell::from(right_value)]).style(style)
+        });
+
+        let table = Table::new(rows)
+            .header(Row::new(vec!["", "Left", "Right"]).style(Style::default().add_modifier(Modifier::BOLD)))
+            .widths(&[Constraint::Length(16), Constraint::Percentage(42), Constraint::Percentage(42)])
+            .block(Block::default().borders(Borders::ALL).title("Metadata"));
+        f.render_widget(table, area);
+    }
+
+    /// Values for each of `METADATA_FIELDS`
+    fn metadata_values(metadata: Option<&FileMetadata>) -> [String; 5] {
+        let Some(metadata) = metadata else {
+            return std::array::from_fn(|_| "(missing)".to_string());
+        };
+        [
+            metadata.modified.map_or_else(|| "-".to_string(), |time| humantime::format_rfc3339_seconds(time).to_string()),
+            format!("{} bytes", metadata.size),
+            metadata.permissions_string().unwrap_or_else(|| "-".to_string()),
+            if metadata.is_symlink { "yes" } else { "no" }.to_string(),
+            metadata.symlink_target.as_ref().map_or_else(|| "-".to_string(), |target| target.display().to_string()),
+        ]
+    }
+
     fn render_side_by_side_diff(&self, f: &mut Frame, area: Rect, diff: &FileDiff) {
         let chunks = Layout::default()
             .direction(Direction::Horizontal)
@@ -476,7 +2041,7 @@ impl TuiApp {
             .split(area);
 
         // Render left side
-        let left_content = diff.left_content.as_deref().unwrap_or("File not found");
+        let left_content = diff.left_content.as_deref().map(String::as_str).unwrap_or("File not found");
         let left_paragraph = Paragraph::new(left_content)
             .block(Block::default().borders(Borders::ALL).title("Left (Original)"))
             .wrap(Wrap { trim: false })
@@ -484,11 +2049,28 @@ impl TuiApp {
         f.render_widget(left_paragraph, chunks[0]);
 
         // Render right side
-        let right_content = diff.right_content.as_deref().unwrap_or("File not found");
+        let right_content = diff.right_content.as_deref().map(String::as_str).unwrap_or("File not found");
         let right_paragraph = Paragraph::new(right_content)
             .block(Block::default().borders(Borders::ALL).title("Right (Modified)"))
             .wrap(Wrap { trim: false })
             .scroll((self.scroll_offset, 0));
         f.render_widget(right_paragraph, chunks[1]);
     }
-}
\ No newline at end of file
+}
+
+/// Standard base64 with padding, as expected by OSC 52
+fn base64_encode(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut encoded = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let group = chunk.iter().enumerate().fold(0u32, |group, (i, byte)| group | (*byte as u32) << (16 - 8 * i));
+        for i in 0..4 {
+            if i <= chunk.len() {
+                encoded.push(ALPHABET[(group >> (18 - 6 * i) & 0x3f) as usize] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+    encoded
+}
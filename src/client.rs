@@ -0,0 +1,147 @@
+//! Async client for the JSON API served by `diffy --web`.
+//!
+//! ```no_run
+//! use diffy::client::DiffyClient;
+//! use diffy::core::export::ExportFormat;
+//!
+//! # async fn run() -> anyhow::Result<()> {
+//! let client = DiffyClient::new("http://127.0.0.1:3000")?;
+//!
+//! let result = client.get_diff().await?;
+//! println!("{} files changed", result.added_count + result.removed_count + result.modified_count);
+//!
+//! let patch = client.export(ExportFormat::Unified).await?;
+//! std::fs::write("changes.patch", patch)?;
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::core::export::ExportFormat;
+use crate::core::types::{BlameLine, DiffResult, DiffStatistics, FileDiff, FileDiffBatch, SearchResult};
+use crate::web::history::HistoryEntry;
+use anyhow::{anyhow, Context, Result};
+use reqwest::Url;
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use serde_json::json;
+use std::path::Path;
+
+/// Mirrors the server's `ApiResponse` envelope
+#[derive(Deserialize)]
+struct ApiResponse<T> {
+    success: bool,
+    data: Option<T>,
+    error: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct DiffyClient {
+    pub base_url: Url,
+    http: reqwest::Client,
+}
+
+impl DiffyClient {
+    /// Create a client for the server at `base_url`, e.g. `http://127.0.0.1:3000`
+    /// or `https://example.com/diffy` behind a reverse proxy
+    pub fn new(base_url: &str) -> Result<Self> {
+        let base_url = Url::parse(base_url).with_context(|| format!("Invalid server URL '{}'", base_url))?;
+        Ok(Self::with_client(base_url, reqwest::Client::new()))
+    }
+
+    /// Use an existing `reqwest::Client`, e.g. one configured with timeouts
+    pub fn with_client(mut base_url: Url, http: reqwest::Client) -> Self {
+        // Endpoints are joined relative to the base, which keeps a path prefix
+        // only when the base ends with a slash
+        if !base_url.path().ends_with('/') {
+            let path = format!("{}/", base_url.path());
+            base_url.set_path(&path);
+        }
+        Self { base_url, http }
+    }
+
+    /// Fetch the full directory comparison
+    ///
+    /// ```no_run
+    /// # async fn run() -> anyhow::Result<()> {
+    /// let client = diffy::client::DiffyClient::new("http://127.0.0.1:3000")?;
+    /// let result = client.get_diff().await?;
+    /// println!("{}", result.to_tree_string());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn get_diff(&self) -> Result<DiffResult> {
+        self.get("api/diff", &[]).await
+    }
+
+    /// Fetch the line diff for one file, relative to the compared roots
+    ///
+    /// ```no_run
+    /// # async fn run() -> anyhow::Result<()> {
+    /// let client = diffy::client::DiffyClient::new("http://127.0.0.1:3000")?;
+    /// let diff = client.get_file_diff(std::path::Path::new("src/main.rs")).await?;
+    /// println!("+{} -{}", diff.total_additions(), diff.total_deletions());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn get_file_diff(&self, path: &Path) -> Result<FileDiff> {
+        let path = path.to_string_lossy();
+        self.get("api/file", &[("path", path.as_ref())]).await
+    }
+
+    /// Fetch the line diffs for up to 20 files in one request
+    pub async fn get_file_diffs(&self, paths: &[&Path]) -> Result<FileDiffBatch> {
+        let paths: Vec<_> = paths.iter().map(|path| path.to_string_lossy()).collect();
+        let url = self.base_url.join("api/files/batch")?;
+        self.send(self.http.post(url).json(&json!({ "paths": paths }))).await
+    }
+
+    /// Fetch each line of a file with the commit that last changed it
+    pub async fn get_blame(&self, path: &Path) -> Result<Vec<BlameLine>> {
+        let path = path.to_string_lossy();
+        self.get("api/blame", &[("path", path.as_ref())]).await
+    }
+
+    /// Find tree entries whose path contains `query`, case-insensitively
+    pub async fn search(&self, query: &str) -> Result<Vec<SearchResult>> {
+        self.get("api/search", &[("q", query)]).await
+    }
+
+    /// Fetch file counts and line totals for the whole comparison
+    pub async fn get_stats(&self) -> Result<DiffStatistics> {
+        self.get("api/stats", &[]).await
+    }
+
+    /// Fetch the most recently compared directory pairs, newest first
+    pub async fn get_history(&self) -> Result<Vec<HistoryEntry>> {
+        self.get("api/history", &[]).await
+    }
+
+    /// Render every changed file in `format`
+    pub async fn export(&self, format: ExportFormat) -> Result<String> {
+        self.get("api/export", &[("format", format.name())]).await
+    }
+
+    async fn get<T: DeserializeOwned>(&self, endpoint: &str, query: &[(&str, &str)]) -> Result<T> {
+        let url = self.base_url.join(endpoint)?;
+        self.send(self.http.get(url).query(query)).await
+    }
+
+    async fn send<T: DeserializeOwned>(&self, request: reqwest::RequestBuilder) -> Result<T> {
+        let (client, request) = request.build_split();
+        let request = request?;
+        let url = request.url().clone();
+        let response: ApiResponse<T> = client
+            .execute(request)
+            .await
+            .with_context(|| format!("Request to {} failed", url))?
+            .error_for_status()?
+            .json()
+            .await
+            .with_context(|| format!("Invalid response from {}", url))?;
+
+        match response {
+            ApiResponse { success: true, data: Some(data), .. } => Ok(data),
+            ApiResponse { error, .. } => Err(anyhow!(error.unwrap_or_else(|| "Request failed".to_string()))),
+        }
+    }
+}
@@ -0,0 +1,287 @@
+use crate::core::types::DiffOptions;
+use anyhow::{Context, Result};
+use config::{Config, File, FileFormat, Map, Value, ValueKind};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// Name of the per-project configuration file looked up in the current directory
+pub const LOCAL_CONFIG_FILE: &str = ".diffy.toml";
+
+/// Default output path for `diffy config generate`
+pub const GENERATED_CONFIG_FILE: &str = "./diffy.toml";
+
+/// One-line descriptions for every key, used when generating a commented template
+const KEY_DESCRIPTIONS: &[(&str, &str)] = &[
+    ("diff.algorithm", "Line diff algorithm: \"myers\", \"patience\" or \"lcs\""),
+    ("diff.context_lines", "Number of unchanged lines shown around each change"),
+    ("diff.parallel_hunk_threshold", "Files with more lines than this are diffed in parallel segments"),
+    ("diff.compute_line_stats", "Count added/removed lines for every changed file during analysis"),
+    ("diff.ignore_blank_lines", "Skip lines containing only whitespace when comparing files"),
+    ("diff.max_file_size", "Files larger than this many bytes are shown as too large instead of diffed"),
+    ("tree.include_ignored", "Include files normally ignored by .gitignore"),
+    ("tree.show_empty_dirs", "List directories that contain no files on either side"),
+    ("tree.case_insensitive", "Match left and right paths regardless of case"),
+    ("web.port", "Port for the web server"),
+    ("web.open", "Open the browser automatically when the server starts"),
+    ("web.auto_reload_interval", "Re-analyze the directories every this many seconds (0 disables)"),
+    ("tui.added_color", "Color of added files and lines: a name such as \"green\", a 0-255 index or \"#rrggbb\""),
+    ("tui.removed_color", "Color of removed files and lines"),
+    ("tui.modified_color", "Color of modified files"),
+    ("tui.key_unified", "Key that switches to the unified diff view"),
+    ("tui.key_side_by_side", "Key that switches to the side-by-side view"),
+    ("tui.key_word_diff", "Key that switches to the word diff view"),
+    ("tui.key_metadata", "Key that switches to the metadata view"),
+    ("tui.key_blame", "Key that toggles the blame column"),
+    ("tui.key_next_change", "Key that jumps to the next change"),
+    ("tui.key_previous_change", "Key that jumps to the previous change"),
+    ("tui.key_expand_all", "Key that expands every directory"),
+    ("tui.key_collapse_all", "Key that collapses every directory"),
+    ("tui.key_sort", "Key that cycles the sort order of the file tree"),
+    ("tui.key_summary", "Key that summarizes all changes"),
+    ("tui.key_quit", "Key that quits diffy"),
+];
+
+/// Prefix for environment variable overrides, e.g. `DIFFY_CONTEXT_LINES=5`
+const ENV_PREFIX: &str = "DIFFY";
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DiffConfig {
+    pub diff: DiffOptions,
+    pub tree: TreeConfig,
+    pub web: WebConfig,
+    pub tui: TuiConfig,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TreeConfig {
+    /// Include files normally ignored by .gitignore
+    pub include_ignored: bool,
+    /// List directories that contain no files on either side
+    pub show_empty_dirs: bool,
+    /// Match left and right paths regardless of case
+    pub case_insensitive: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WebConfig {
+    /// Port for the web server
+    pub port: u16,
+    /// Open the browser automatically when the server starts
+    pub open: bool,
+    /// Re-analyze the directories every this many seconds; 0 disables it
+    pub auto_reload_interval: u64,
+}
+
+impl Default for WebConfig {
+    fn default() -> Self {
+        Self { port: 3000, open: false, auto_reload_interval: 0 }
+    }
+}
+
+/// Colors and single-key commands of the terminal UI
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TuiConfig {
+    /// Color of added files and lines: a name such as "green", a 0-255 index or "#rrggbb"
+    pub added_color: String,
+    /// Color of removed files and lines
+    pub removed_color: String,
+    /// Color of modified files
+    pub modified_color: String,
+    pub key_unified: String,
+    pub key_side_by_side: String,
+    pub key_word_diff: String,
+    pub key_metadata: String,
+    pub key_blame: String,
+    pub key_next_change: String,
+    pub key_previous_change: String,
+    pub key_expand_all: String,
+    pub key_collapse_all: String,
+    pub key_sort: String,
+    pub key_summary: String,
+    pub key_quit: String,
+}
+
+impl Default for TuiConfig {
+    fn default() -> Self {
+        let key = |key: char| key.to_string();
+        Self {
+            added_color: "green".to_string(),
+            removed_color: "red".to_string(),
+            modified_color: "yellow".to_string(),
+            key_unified: key('u'),
+            key_side_by_side: key('s'),
+            key_word_diff: key('w'),
+            key_metadata: key('m'),
+            key_blame: key('b'),
+            key_next_change: key('n'),
+            key_previous_change: key('N'),
+            key_expand_all: key('a'),
+            key_collapse_all: key('z'),
+            key_sort: key('S'),
+            key_summary: key('?'),
+            key_quit: key('q'),
+        }
+    }
+}
+
+impl TuiConfig {
+    /// Every configurable key as set, with the name of its setting and its default
+    pub fn key_bindings(&self) -> [(&str, &'static str, char); 12] {
+        [
+            (&self.key_unified, "key_unified", 'u'),
+            (&self.key_side_by_side, "key_side_by_side", 's'),
+            (&self.key_word_diff, "key_word_diff", 'w'),
+            (&self.key_metadata, "key_metadata", 'm'),
+            (&self.key_blame, "key_blame", 'b'),
+            (&self.key_next_change, "key_next_change", 'n'),
+            (&self.key_previous_change, "key_previous_change", 'N'),
+            (&self.key_expand_all, "key_expand_all", 'a'),
+            (&self.key_collapse_all, "key_collapse_all", 'z'),
+            (&self.key_sort, "key_sort", 'S'),
+            (&self.key_summary, "key_summary", '?'),
+            (&self.key_quit, "key_quit", 'q'),
+        ]
+    }
+}
+
+impl DiffConfig {
+    /// Load configuration from the global and local config files plus environment
+    /// variables, falling back to defaults for anything unset.
+    pub fn load() -> Result<DiffConfig> {
+        Self::load_from(None)
+    }
+
+    /// Like [`DiffConfig::load`], with an explicit `--config` file taking priority
+    /// over the global and local files. Environment variables still win over files.
+    pub fn load_from(explicit_path: Option<&Path>) -> Result<DiffConfig> {
+        let defaults = Config::try_from(&DiffConfig::default())?;
+        let mut builder = Config::builder().add_source(defaults);
+
+        for path in Self::file_sources(explicit_path) {
+            let required = explicit_path == Some(path.as_path());
+            builder = builder.add_source(
+                File::from(path.as_path()).format(FileFormat::Toml).required(required)
+            );
+        }
+
+        // Environment variables use the bare key name (DIFFY_CONTEXT_LINES), so
+        // map each one onto its section explicitly
+        for key in Self::known_keys() {
+            let leaf = key.rsplit('.').next().unwrap_or(&key);
+            let var = format!("{}_{}", ENV_PREFIX, leaf.to_uppercase());
+            if let Ok(value) = std::env::var(&var) {
+                builder = builder.set_override(key.as_str(), value)?;
+            }
+        }
+
+        builder
+            .build()
+            .context("Failed to load configuration")?
+            .try_deserialize()
+            .context("Invalid configuration")
+    }
+
+    /// Config files in increasing priority: global, local `.diffy.toml`, then `--config`
+    pub fn file_sources(explicit_path: Option<&Path>) -> Vec<PathBuf> {
+        let mut sources = Vec::new();
+        if let Some(global) = Self::global_config_path() {
+            sources.push(global);
+        }
+        sources.push(PathBuf::from(LOCAL_CONFIG_FILE));
+        if let Some(path) = explicit_path {
+            sources.push(path.to_path_buf());
+        }
+        sources
+    }
+
+    /// `$XDG_CONFIG_HOME/diffy/config.toml`, defaulting to `~/.config/diffy/config.toml`
+    pub fn global_config_path() -> Option<PathBuf> {
+        let config_home = std::env::var_os("XDG_CONFIG_HOME")
+            .map(PathBuf::from)
+            .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+        Some(config_home.join("diffy").join("config.toml"))
+    }
+
+    /// Report keys in `path` that do not correspond to any configuration field
+    pub fn unknown_keys(path: &Path) -> Result<Vec<String>> {
+        let file_config = Config::builder()
+            .add_source(File::from(path).format(FileFormat::Toml))
+            .build()
+            .with_context(|| format!("Failed to parse {}", path.display()))?;
+
+        let known = Self::known_keys();
+        let mut found = Vec::new();
+        Self::collect_keys(&file_config.try_deserialize()?, "", &mut found);
+
+        Ok(found.into_iter().filter(|key| !known.contains(key)).collect())
+    }
+
+    /// Dotted names of every configuration field, e.g. `diff.context_lines`
+    pub fn known_keys() -> Vec<String> {
+        let mut keys = Vec::new();
+        if let Ok(map) = Config::try_from(&DiffConfig::default()).and_then(|c| c.try_deserialize::<Map<String, Value>>()) {
+            Self::collect_keys(&map, "", &mut keys);
+        }
+        keys
+    }
+
+    fn toml_literal(value: &Value) -> String {
+        match &value.kind {
+            ValueKind::String(text) => format!("{:?}", text),
+            _ => value.to_string(),
+        }
+    }
+
+    fn collect_keys(map: &Map<String, Value>, prefix: &str, keys: &mut Vec<String>) {
+        for (name, value) in map {
+            let key = if prefix.is_empty() { name.clone() } else { format!("{}.{}", prefix, name) };
+            match &value.kind {
+                ValueKind::Table(table) => Self::collect_keys(table, &key, keys),
+                _ => keys.push(key),
+            }
+        }
+        keys.sort();
+    }
+
+    /// Render a `.diffy.toml` template with every key commented out, alongside
+    /// its description and default value.
+    pub fn generate_template() -> String {
+        let defaults: Map<String, Value> = Config::try_from(&DiffConfig::default())
+            .and_then(|c| c.try_deserialize())
+            .unwrap_or_default();
+
+        let mut output = String::from(
+            "# Diffy configuration\n\
+             # Uncomment and edit any setting to override its default.\n\
+             # Priority: CLI flags > DIFFY_* environment variables > --config > .diffy.toml > global config\n"
+        );
+
+        let mut current_section = String::new();
+        for key in Self::known_keys() {
+            let (section, name) = key.split_once('.').unwrap_or(("", key.as_str()));
+            if section != current_section {
+                output.push_str(&format!("\n[{}]\n", section));
+                current_section = section.to_string();
+            }
+
+            let description = KEY_DESCRIPTIONS
+                .iter()
+                .find(|(known, _)| *known == key)
+                .map(|(_, description)| *description)
+                .unwrap_or("");
+            let default = defaults
+                .get(section)
+                .and_then(|table| table.clone().into_table().ok())
+                .and_then(|table| table.get(name).map(Self::toml_literal))
+                .unwrap_or_default();
+
+            output.push_str(&format!("# {} (default: {})\n# {} = {}\n", description, default, name, default));
+        }
+
+        output
+    }
+}
@@ -0,0 +1,60 @@
+//! Shares the text of files with identical content between diffs.
+
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::{Arc, Mutex, Weak};
+
+/// Freed entries are only swept once the map has at least this many
+const PRUNE_MIN_ENTRIES: usize = 64;
+
+/// File contents keyed by their SHA-256 hash.
+///
+/// Entries are weak references, so text is only shared while some diff still
+/// holds it; the store never keeps memory alive by itself. This pays off when
+/// many copies of the same file (vendored dependencies, copied assets) are
+/// diffed at once, e.g. during an export, where every copy is held in memory
+/// once. Files are always read, so an edited file is never mistaken for the
+/// text it had before.
+#[derive(Debug, Default)]
+pub struct ContentStore {
+    entries: Mutex<HashMap<[u8; 32], Weak<String>>>,
+}
+
+impl ContentStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn hash(content: &[u8]) -> [u8; 32] {
+        Sha256::digest(content).into()
+    }
+
+    /// The text of `path`, shared with any live copy of the same content
+    pub fn load(&self, path: &Path) -> std::io::Result<Arc<String>> {
+        let content = std::fs::read_to_string(path)?;
+        let hash = Self::hash(content.as_bytes());
+
+        let mut entries = self.entries.lock().unwrap();
+        if let Some(existing) = entries.get(&hash).and_then(Weak::upgrade) {
+            return Ok(existing);
+        }
+        // Drop entries whose text has been freed, each time the map doubles
+        if entries.len() >= PRUNE_MIN_ENTRIES && entries.len().is_power_of_two() {
+            entries.retain(|_, content| content.strong_count() > 0);
+        }
+        let content = Arc::new(content);
+        entries.insert(hash, Arc::downgrade(&content));
+        Ok(content)
+    }
+
+    /// Number of distinct contents currently held by at least one diff
+    pub fn live_entries(&self) -> usize {
+        self.entries
+            .lock()
+            .unwrap()
+            .values()
+            .filter(|content| content.strong_count() > 0)
+            .count()
+    }
+}
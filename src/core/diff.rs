@@ -1,14 +1,40 @@
-use crate::core::types::{DiffHunk, DiffLine, DiffLineKind, FileDiff};
+use crate::core::content::ContentStore;
+use crate::error::{DiffyError, ParseError};
+use crate::core::types::{
+    BinaryFileDiff, DiffAlgorithm, DiffHunk, DiffLine, DiffLineKind, DiffOptions, FileDiff, ParsedFileDiff, WordDiffLine, WordSpan,
+};
 use anyhow::{Context, Result};
 use rayon::prelude::*;
-use similar::{ChangeTag, TextDiff};
-use std::path::Path;
+use similar::{Algorithm, ChangeTag, TextDiff};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 
-pub struct DiffEngine;
+pub struct DiffEngine {
+    options: DiffOptions,
+    content_store: Option<Arc<ContentStore>>,
+}
+
+impl Default for DiffEngine {
+    fn default() -> Self {
+        Self::new()
+    }
+}
 
 impl DiffEngine {
     pub fn new() -> Self {
-        Self
+        Self::with_options(DiffOptions::default())
+    }
+
+    pub fn with_options(options: DiffOptions) -> Self {
+        Self { options, content_store: None }
+    }
+
+    /// Read file contents through `store`, sharing text with other diffs of
+    /// identical files
+    pub fn with_content_store(mut self, store: Arc<ContentStore>) -> Self {
+        self.content_store = Some(store);
+        self
     }
 
     pub fn diff_files(&self, left_path: &Path, right_path: &Path) -> Result<FileDiff> {
@@ -17,37 +43,31 @@ impl DiffEngine {
         let right_is_binary = if right_path.exists() { Self::is_binary_file(right_path)? } else { false };
 
         if left_is_binary || right_is_binary {
-            return Ok(FileDiff {
-                left_content: Some("[Binary file]".to_string()),
-                right_content: Some("[Binary file]".to_string()),
-                hunks: Vec::new(),
-            });
+            return Ok(Self::binary_diff(left_path, right_path));
         }
 
-        // Read both files in parallel
-        let (left_result, right_result) = rayon::join(
-            || {
-                if left_path.exists() {
-                    std::fs::read_to_string(left_path)
-                        .with_context(|| format!("Failed to read left file: {}", left_path.display()))
-                        .map(Some)
-                } else {
-                    Ok(None)
-                }
-            },
-            || {
-                if right_path.exists() {
-                    std::fs::read_to_string(right_path)
-                        .with_context(|| format!("Failed to read right file: {}", right_path.display()))
-                        .map(Some)
-                } else {
-                    Ok(None)
+        if let Some(limit) = self.options.max_file_size {
+            for path in [left_path, right_path] {
+                if let Some(size) = Self::file_size(path).filter(|size| *size > limit) {
+                    return Err(DiffyError::FileTooLarge { path: path.to_path_buf(), size, limit }.into());
                 }
-            },
-        );
+            }
+        }
+
+        // Read both files in parallel
+        let (left_result, right_result) = rayon::join(|| self.read_text(left_path), || self.read_text(right_path));
+
+        // Text in another encoding, such as Latin-1, has no NUL bytes but can't
+        // be diffed line by line either
+        let undecodable = |result: &std::io::Result<_>| {
+            matches!(result, Err(e) if e.kind() == std::io::ErrorKind::InvalidData)
+        };
+        if undecodable(&left_result) || undecodable(&right_result) {
+            return Ok(Self::binary_diff(left_path, right_path));
+        }
 
-        let left_content = left_result?;
-        let right_content = right_result?;
+        let left_content = left_result.with_context(|| format!("Failed to read left file: {}", left_path.display()))?;
+        let right_content = right_result.with_context(|| format!("Failed to read right file: {}", right_path.display()))?;
 
         let hunks = match (&left_content, &right_content) {
             (Some(left), Some(right)) => self.compute_diff_hunks(left, right),
@@ -55,14 +75,154 @@ impl DiffEngine {
             (None, Some(right)) => self.create_addition_hunks(right),
             (None, None) => Vec::new(),
         };
+        let similarity = self.similarity(left_content.as_deref().map(String::as_str), right_content.as_deref().map(String::as_str), &hunks);
 
         Ok(FileDiff {
             left_content,
             right_content,
             hunks,
+            is_binary: false,
+            similarity,
+            binary: None,
         })
     }
 
+    /// A diff without line changes for files that aren't UTF-8 text
+    fn binary_diff(left_path: &Path, right_path: &Path) -> FileDiff {
+        FileDiff {
+            left_content: Some(Arc::new("[Binary file]".to_string())),
+            right_content: Some(Arc::new("[Binary file]".to_string())),
+            hunks: Vec::new(),
+            is_binary: true,
+            similarity: 0.0,
+            binary: Some(BinaryFileDiff {
+                left_size: Self::file_size(left_path),
+                right_size: Self::file_size(right_path),
+                too_large: false,
+            }),
+        }
+    }
+
+    /// Contents of `path`, or `None` if it does not exist
+    fn read_text(&self, path: &Path) -> std::io::Result<Option<Arc<String>>> {
+        if !path.exists() {
+            return Ok(None);
+        }
+        match &self.content_store {
+            Some(store) => store.load(path).map(Some),
+            None => std::fs::read_to_string(path).map(|content| Some(Arc::new(content))),
+        }
+    }
+
+    /// Diff two in-memory texts without touching the filesystem
+    pub fn diff_strings(&self, left: &str, right: &str) -> FileDiff {
+        let hunks = self.compute_diff_hunks(left, right);
+        FileDiff {
+            left_content: Some(Arc::new(left.to_string())),
+            right_content: Some(Arc::new(right.to_string())),
+            similarity: self.similarity(Some(left), Some(right), &hunks),
+            hunks,
+            is_binary: false,
+            binary: None,
+        }
+    }
+
+    /// One minus the changed lines over the compared lines on both sides;
+    /// 1.0 when both sides are empty
+    fn similarity(&self, left: Option<&str>, right: Option<&str>, hunks: &[DiffHunk]) -> f32 {
+        let compared = |text: Option<&str>| {
+            let lines = text.unwrap_or_default().split_inclusive('\n');
+            if self.options.ignore_blank_lines {
+                lines.filter(|line| !line.trim().is_empty()).count()
+            } else {
+                lines.count()
+            }
+        };
+        let total = compared(left) + compared(right);
+        if total == 0 {
+            return 1.0;
+        }
+        let changed = hunks
+            .iter()
+            .flat_map(|hunk| &hunk.lines)
+            .filter(|line| line.kind != DiffLineKind::Context)
+            .count();
+        1.0 - changed as f32 / total as f32
+    }
+
+    /// Diff two texts line by line, then word by word within changed lines. Each
+    /// run of removed lines is paired in order with the added lines that replace
+    /// it; only the words that differ within a pair are marked as changed.
+    pub fn word_diff(&self, left: &str, right: &str) -> Vec<WordDiffLine> {
+        let left_lines: Vec<&str> = left.split_inclusive('\n').collect();
+        let right_lines: Vec<&str> = right.split_inclusive('\n').collect();
+        let changes = self.collect_line_changes(&left_lines, &right_lines);
+
+        let mut output = Vec::new();
+        let mut removed: Vec<&str> = Vec::new();
+        let mut added: Vec<&str> = Vec::new();
+        for (tag, value) in changes {
+            let value = value.trim_end_matches('\n');
+            match tag {
+                ChangeTag::Delete => removed.push(value),
+                ChangeTag::Insert => added.push(value),
+                ChangeTag::Equal => {
+                    Self::flush_word_changes(&mut removed, &mut added, &mut output);
+                    output.push(WordDiffLine::Context(value.to_string()));
+                }
+            }
+        }
+        Self::flush_word_changes(&mut removed, &mut added, &mut output);
+        output
+    }
+
+    fn flush_word_changes<'a>(removed: &mut Vec<&'a str>, added: &mut Vec<&'a str>, output: &mut Vec<WordDiffLine>) {
+        let mut removed_lines = Vec::with_capacity(removed.len());
+        let mut added_lines = Vec::with_capacity(added.len());
+
+        for index in 0..removed.len().max(added.len()) {
+            match (removed.get(index), added.get(index)) {
+                (Some(old), Some(new)) => {
+                    let mut old_spans = Vec::new();
+                    let mut new_spans = Vec::new();
+                    for change in TextDiff::from_words(*old, *new).iter_all_changes() {
+                        let text = change.value();
+                        match change.tag() {
+                            ChangeTag::Equal => {
+                                Self::push_span(&mut old_spans, false, text);
+                                Self::push_span(&mut new_spans, false, text);
+                            }
+                            ChangeTag::Delete => Self::push_span(&mut old_spans, true, text),
+                            ChangeTag::Insert => Self::push_span(&mut new_spans, true, text),
+                        }
+                    }
+                    removed_lines.push(WordDiffLine::Removed(old_spans));
+                    added_lines.push(WordDiffLine::Added(new_spans));
+                }
+                (Some(old), None) => {
+                    removed_lines.push(WordDiffLine::Removed(vec![WordSpan { changed: true, text: old.to_string() }]));
+                }
+                (None, Some(new)) => {
+                    added_lines.push(WordDiffLine::Added(vec![WordSpan { changed: true, text: new.to_string() }]));
+                }
+                (None, None) => {}
+            }
+        }
+
+        output.append(&mut removed_lines);
+        output.append(&mut added_lines);
+        removed.clear();
+        added.clear();
+    }
+
+    /// Append `text`, merging it into the previous span when the flag matches
+    fn push_span(spans: &mut Vec<WordSpan>, changed: bool, text: &str) {
+        match spans.last_mut() {
+            Some(last) if last.changed == changed => last.text.push_str(text),
+            _ => spans.push(WordSpan { changed, text: text.to_string() }),
+        }
+    }
+
     /// Process multiple file diffs in parallel
     pub fn diff_files_batch(&self, file_pairs: Vec<(&Path, &Path)>) -> Vec<Result<FileDiff>> {
         file_pairs
@@ -72,18 +232,230 @@ impl DiffEngine {
     }
 
     fn compute_diff_hunks(&self, left: &str, right: &str) -> Vec<DiffHunk> {
-        let diff = TextDiff::from_lines(left, right);
-        let context_lines = 3; // Number of context lines to show around changes
+        if self.options.ignore_blank_lines {
+            return self.compute_diff_hunks_ignoring_blank_lines(left, right);
+        }
+
+        let left_lines: Vec<&str> = left.split_inclusive('\n').collect();
+        let right_lines: Vec<&str> = right.split_inclusive('\n').collect();
+
+        let changes = self.collect_line_changes(&left_lines, &right_lines);
+        self.build_hunks(&changes)
+    }
+
+    /// Diff with whitespace-only lines removed from both sides, then map the
+    /// hunks back onto the original files. Blank lines inside a hunk are put
+    /// back, so each hunk still applies to the original text; only hunks that
+    /// change nothing but blank lines are left out.
+    fn compute_diff_hunks_ignoring_blank_lines(&self, left: &str, right: &str) -> Vec<DiffHunk> {
+        let (left_lines, left_numbers) = Self::non_blank_lines(left);
+        let (right_lines, right_numbers) = Self::non_blank_lines(right);
+
+        let changes = self.collect_line_changes(&left_lines, &right_lines);
+        let mut hunks = self.build_hunks(&changes);
+
+        // Filtered line n is original line numbers[n - 1]; 0 means "before the first line"
+        let original = |numbers: &[u32], line: u32| if line == 0 { 0 } else { numbers[line as usize - 1] };
+        for hunk in &mut hunks {
+            hunk.old_start = original(&left_numbers, hunk.old_start);
+            hunk.new_start = original(&right_numbers, hunk.new_start);
+            for line in &mut hunk.lines {
+                line.old_line_number = line.old_line_number.map(|number| original(&left_numbers, number));
+                line.new_line_number = line.new_line_number.map(|number| original(&right_numbers, number));
+            }
+        }
+
+        let left_lines: Vec<&str> = left.split_inclusive('\n').collect();
+        let right_lines: Vec<&str> = right.split_inclusive('\n').collect();
+        for hunk in &mut hunks {
+            Self::restore_blank_lines(hunk, &left_lines, &right_lines);
+        }
+        hunks
+    }
+
+    /// Put back the blank lines skipped between the lines of `hunk` and recount
+    /// its header. A blank line on both sides with the same text is context;
+    /// any other is removed or added.
+    fn restore_blank_lines(hunk: &mut DiffHunk, left: &[&str], right: &[&str]) {
+        // Original lines strictly between `previous` and `next`, with their numbers
+        let skipped = |lines: &[&str], previous: Option<u32>, next: Option<u32>| -> Vec<(u32, String)> {
+            match (previous, next) {
+                (Some(previous), Some(next)) => (previous + 1..next)
+                    .map(|number| (number, lines[number as usize - 1].trim_end_matches('\n').to_string()))
+                    .collect(),
+                _ => Vec::new(),
+            }
+        };
+
+        let mut lines = Vec::with_capacity(hunk.lines.len());
+        let (mut previous_old, mut previous_new) = (None, None);
+        for line in std::mem::take(&mut hunk.lines) {
+            let mut removed = skipped(left, previous_old, line.old_line_number).into_iter();
+            let mut added = skipped(right, previous_new, line.new_line_number).into_iter();
+            loop {
+                match (removed.next(), added.next()) {
+                    (None, None) => break,
+                    (Some((old, content)), Some((new, other))) if content == other => lines.push(DiffLine {
+                        kind: DiffLineKind::Context,
+                        content,
+                        old_line_number: Some(old),
+                        new_line_number: Some(new),
+                    }),
+                    (old, new) => {
+                        lines.extend(old.map(|(number, content)| DiffLine {
+                            kind: DiffLineKind::Deletion,
+                            content,
+                            old_line_number: Some(number),
+                            new_line_number: None,
+                        }));
+                        lines.extend(new.map(|(number, content)| DiffLine {
+                            kind: DiffLineKind::Addition,
+                            content,
+                            old_line_number: None,
+                            new_line_number: Some(number),
+                        }));
+                    }
+                }
+            }
+            previous_old = line.old_line_number.or(previous_old);
+            previous_new = line.new_line_number.or(previous_new);
+            lines.push(line);
+        }
+        hunk.lines = lines;
+        Self::recount_hunk(hunk);
+    }
+
+    /// Lines that contain something other than whitespace, with their one-based
+    /// line numbers in `text`
+    fn non_blank_lines(text: &str) -> (Vec<&str>, Vec<u32>) {
+        text.split_inclusive('\n')
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty())
+            .map(|(index, line)| (line, index as u32 + 1))
+            .unzip()
+    }
+
+    /// Line-level changes, diffed in parallel segments for long inputs
+    fn collect_line_changes<'a>(&self, left: &[&'a str], right: &[&'a str]) -> Vec<(ChangeTag, &'a str)> {
+        if left.len().max(right.len()) >= self.options.parallel_hunk_threshold {
+            self.collect_changes_parallel(left, right)
+        } else {
+            self.collect_changes(left, right)
+        }
+    }
+
+    fn collect_changes<'a>(&self, left: &[&'a str], right: &[&'a str]) -> Vec<(ChangeTag, &'a str)> {
+        let algorithm = match self.options.algorithm {
+            DiffAlgorithm::Myers => Algorithm::Myers,
+            DiffAlgorithm::Patience => Algorithm::Patience,
+            DiffAlgorithm::Lcs => Algorithm::Lcs,
+        };
+
+        TextDiff::configure()
+            .algorithm(algorithm)
+            .diff_slices(left, right)
+            .iter_all_changes()
+            .map(|change| (change.tag(), change.value()))
+            .collect()
+    }
+
+    /// Split both inputs at sync points (lines unique to both sides) and diff the
+    /// segments in between in parallel. Hunks are assembled afterwards over the
+    /// merged change stream, so segment boundaries never split a hunk.
+    fn collect_changes_parallel<'a>(&self, left: &[&'a str], right: &[&'a str]) -> Vec<(ChangeTag, &'a str)> {
+        let sync_points = self.find_sync_points(left, right);
+        if sync_points.is_empty() {
+            return self.collect_changes(left, right);
+        }
+
+        // Ranges between consecutive sync points; the sync lines themselves are equal
+        let mut segments = Vec::with_capacity(sync_points.len() + 1);
+        let (mut left_start, mut right_start) = (0, 0);
+        for &(left_index, right_index) in &sync_points {
+            segments.push((left_start..left_index, right_start..right_index));
+            left_start = left_index + 1;
+            right_start = right_index + 1;
+        }
+        segments.push((left_start..left.len(), right_start..right.len()));
+
+        let segment_changes: Vec<Vec<(ChangeTag, &'a str)>> = segments
+            .into_par_iter()
+            .map(|(left_range, right_range)| self.collect_changes(&left[left_range], &right[right_range]))
+            .collect();
+
+        let mut changes = Vec::with_capacity(left.len().max(right.len()));
+        for (i, segment) in segment_changes.into_iter().enumerate() {
+            changes.extend(segment);
+            if let Some(&(left_index, _)) = sync_points.get(i) {
+                changes.push((ChangeTag::Equal, left[left_index]));
+            }
+        }
+        changes
+    }
+
+    /// Pick one sync point near every segment boundary. A sync point is a line that
+    /// occurs exactly once on each side; boundaries are aligned to `context_lines`
+    /// and right-side indices are kept strictly increasing.
+    fn find_sync_points(&self, left: &[&str], right: &[&str]) -> Vec<(usize, usize)> {
+        let context_lines = self.options.context_lines.max(1);
+        // At least one segment per thread, and none longer than the parallel threshold
+        // so heavily changed files stay tractable even on few cores
+        let segment_count = rayon::current_num_threads().max(2);
+        let segment_len = (left.len() / segment_count)
+            .min(self.options.parallel_hunk_threshold)
+            .div_ceil(context_lines) * context_lines;
+        if segment_len == 0 {
+            return Vec::new();
+        }
+
+        // line -> (occurrences on left, occurrences on right, last right index)
+        let mut occurrences: HashMap<&str, (usize, usize, usize)> = HashMap::new();
+        for line in left {
+            occurrences.entry(line).or_default().0 += 1;
+        }
+        for (index, line) in right.iter().enumerate() {
+            let entry = occurrences.entry(line).or_default();
+            entry.1 += 1;
+            entry.2 = index;
+        }
+
+        let mut sync_points = Vec::new();
+        let mut next_left = 0;
+        let mut next_right = 0;
+        let mut boundary = segment_len;
+
+        while boundary < left.len() {
+            let search_end = (boundary + segment_len).min(left.len());
+            let found = (boundary.max(next_left)..search_end).find_map(|left_index| {
+                match occurrences.get(left[left_index]) {
+                    Some(&(1, 1, right_index)) if right_index >= next_right => Some((left_index, right_index)),
+                    _ => None,
+                }
+            });
+
+            if let Some((left_index, right_index)) = found {
+                sync_points.push((left_index, right_index));
+                next_left = left_index + 1;
+                next_right = right_index + 1;
+            }
+            boundary += segment_len;
+        }
+
+        sync_points
+    }
+
+    fn build_hunks(&self, changes: &[(ChangeTag, &str)]) -> Vec<DiffHunk> {
+        let context_lines = self.options.context_lines;
         let mut hunks = Vec::new();
         let mut current_hunk: Option<DiffHunk> = None;
         let mut old_line_no = 1u32;
         let mut new_line_no = 1u32;
         let mut context_buffer = Vec::new();
 
-        for change in diff.iter_all_changes() {
-            let line_content = change.value().trim_end_matches('\n').to_string();
+        for &(tag, value) in changes {
+            let line_content = value.trim_end_matches('\n').to_string();
             
-            match change.tag() {
+            match tag {
                 ChangeTag::Equal => {
                     if let Some(ref mut hunk) = current_hunk {
                         // Add this context line to the current hunk
@@ -95,9 +467,7 @@ impl DiffEngine {
                         });
                         
                         // If we've collected enough context after changes, close the hunk
-                        let context_after_changes = hunk.lines.iter().rev()
-                            .take_while(|line| line.kind == DiffLineKind::Context)
-                            .count();
+                        let context_after_changes = hunk.after_context().len();
                         
                         if context_after_changes >= context_lines {
                             // Keep only the required context lines
@@ -105,7 +475,7 @@ impl DiffEngine {
                             let keep_context = std::cmp::min(context_lines, context_after_changes);
                             hunk.lines.truncate(changes_end + keep_context);
                             
-                            hunks.push(current_hunk.take().unwrap());
+                            hunks.push(Self::finalize_hunk(current_hunk.take().unwrap()));
                             context_buffer.clear();
                         }
                     } else {
@@ -161,7 +531,6 @@ impl DiffEngine {
                             old_line_number: Some(old_line_no),
                             new_line_number: None,
                         });
-                        hunk.old_lines += 1;
                     }
                     old_line_no += 1;
                 }
@@ -208,7 +577,6 @@ impl DiffEngine {
                             old_line_number: None,
                             new_line_number: Some(new_line_no),
                         });
-                        hunk.new_lines += 1;
                     }
                     new_line_no += 1;
                 }
@@ -216,12 +584,323 @@ impl DiffEngine {
         }
 
         if let Some(hunk) = current_hunk {
-            hunks.push(hunk);
+            hunks.push(Self::finalize_hunk(hunk));
         }
 
         hunks
     }
 
+    /// Apply `hunks` to `original`, producing the modified text. The inverse of
+    /// [`DiffEngine::diff_strings`]. Fails with [`DiffyError::PatchConflict`] if a
+    /// hunk's context or removed lines are not found at its `old_start`.
+    ///
+    /// Hunk lines do not record a missing final newline, so the result ends with a
+    /// newline whenever `original` does (or is empty).
+    pub fn apply_patch(original: &str, hunks: &[DiffHunk]) -> Result<String> {
+        Self::apply_hunks(original, hunks, false)
+    }
+
+    /// Undo `hunks` on `modified`, recovering the original text
+    pub fn apply_patch_reversed(modified: &str, hunks: &[DiffHunk]) -> Result<String> {
+        Self::apply_hunks(modified, hunks, true)
+    }
+
+    /// Hunks whose old or new line range overlaps `[line - radius, line + radius]`
+    pub fn get_hunks_near_line(hunks: &[DiffHunk], line: u32, radius: u32) -> Vec<&DiffHunk> {
+        let window_start = line.saturating_sub(radius);
+        let window_end = line.saturating_add(radius);
+        // An empty side occupies the position just after its start line
+        let overlaps = |start: u32, count: u32| {
+            let (first, last) = if count == 0 { (start + 1, start + 1) } else { (start, start + count - 1) };
+            first <= window_end && last >= window_start
+        };
+
+        hunks
+            .iter()
+            .filter(|hunk| overlaps(hunk.old_start, hunk.old_lines) || overlaps(hunk.new_start, hunk.new_lines))
+            .collect()
+    }
+
+    /// Join neighbouring hunks separated by fewer than `merge_threshold` unchanged
+    /// lines, filling the gap with context lines taken from `original`, the text
+    /// the hunks apply to. The lines between two hunks are not part of either
+    /// hunk, so they can't be recovered from `hunks` alone. Hunks whose range
+    /// doesn't fit `original`, such as `old_start == 0` with removed lines, are
+    /// left as they are.
+    pub fn merge_close_hunks(hunks: Vec<DiffHunk>, merge_threshold: u32, original: &str) -> Vec<DiffHunk> {
+        let original_lines: Vec<&str> = original
+            .split_inclusive('\n')
+            .map(|line| line.strip_suffix('\n').unwrap_or(line))
+            .collect();
+        // First line after a range; an empty side sits just after its start line
+        let end_of = |start: u32, count: u32| start.saturating_add(count.max(1));
+        let first_of = |start: u32, count: u32| if count == 0 { start.saturating_add(1) } else { start };
+        // Line numbers start at 1; only an empty side can start at line 0
+        let is_valid = |hunk: &DiffHunk| hunk.old_start > 0 || hunk.old_lines == 0;
+
+        let mut merged: Vec<DiffHunk> = Vec::with_capacity(hunks.len());
+        for hunk in hunks {
+            let Some(previous) = merged.last_mut() else {
+                merged.push(hunk);
+                continue;
+            };
+
+            let gap_start = end_of(previous.old_start, previous.old_lines);
+            let gap_end = first_of(hunk.old_start, hunk.old_lines);
+            let gap = gap_end.saturating_sub(gap_start);
+            let gap_lines = if is_valid(previous) && is_valid(&hunk) {
+                original_lines.get(gap_start as usize - 1..gap_end as usize - 1)
+            } else {
+                None
+            };
+
+            match gap_lines {
+                Some(gap_lines) if gap < merge_threshold => {
+                    let new_offset = end_of(previous.new_start, previous.new_lines) as i64 - gap_start as i64;
+                    previous.lines.extend(gap_lines.iter().enumerate().map(|(index, content)| {
+                        let old_line = gap_start + index as u32;
+                        DiffLine {
+                            kind: DiffLineKind::Context,
+                            content: content.to_string(),
+                            old_line_number: Some(old_line),
+                            new_line_number: Some((old_line as i64 + new_offset) as u32),
+                        }
+                    }));
+                    previous.lines.extend(hunk.lines);
+                    Self::recount_hunk(previous);
+                }
+                _ => merged.push(hunk),
+            }
+        }
+        merged
+    }
+
+    /// Recompute a merged hunk's header from its lines
+    fn recount_hunk(hunk: &mut DiffHunk) {
+        let first_old = hunk.lines.iter().find_map(|line| line.old_line_number);
+        let first_new = hunk.lines.iter().find_map(|line| line.new_line_number);
+        hunk.old_lines = hunk.lines.iter().filter(|line| line.kind != DiffLineKind::Addition).count() as u32;
+        hunk.new_lines = hunk.lines.iter().filter(|line| line.kind != DiffLineKind::Deletion).count() as u32;
+        if hunk.old_lines > 0 {
+            hunk.old_start = first_old.unwrap_or(hunk.old_start);
+        }
+        if hunk.new_lines > 0 {
+            hunk.new_start = first_new.unwrap_or(hunk.new_start);
+        }
+    }
+
+    /// Parse `diff -u` / `git diff` output into per-file hunks that can be passed
+    /// to [`DiffEngine::apply_patch`]. Text outside file sections (such as
+    /// `diff --git` and `index` lines) is skipped, as are `\\ No newline at end
+    /// of file` markers. Malformed input fails with a [`ParseError`].
+    pub fn parse_unified_diff(input: &str) -> Result<Vec<ParsedFileDiff>> {
+        let lines: Vec<&str> = input
+            .split_inclusive('\n')
+            .map(|line| line.strip_suffix('\n').unwrap_or(line))
+            .collect();
+        let mut files: Vec<ParsedFileDiff> = Vec::new();
+        let mut index = 0;
+
+        while index < lines.len() {
+            let line = lines[index];
+            let line_number = index + 1;
+
+            if let Some(old_path) = line.strip_prefix("--- ") {
+                let new_path = lines
+                    .get(index + 1)
+                    .and_then(|next| next.strip_prefix("+++ "))
+                    .ok_or(ParseError::MissingNewHeader { line: line_number })?;
+                files.push(ParsedFileDiff {
+                    old_path: Self::header_path(old_path),
+                    new_path: Self::header_path(new_path),
+                    hunks: Vec::new(),
+                    is_binary: false,
+                });
+                index += 2;
+            } else if line.starts_with("@@") {
+                let file = files.last_mut().ok_or(ParseError::HunkWithoutFile { line: line_number })?;
+                let (hunk, consumed) = Self::parse_hunk(&lines[index..], line_number)?;
+                file.hunks.push(hunk);
+                index += consumed;
+            } else if let Some((old_path, new_path)) = line
+                .strip_prefix("Binary files ")
+                .and_then(|rest| rest.trim_end_matches('\r').strip_suffix(" differ"))
+                .and_then(|rest| rest.split_once(" and "))
+            {
+                files.push(ParsedFileDiff {
+                    old_path: PathBuf::from(old_path),
+                    new_path: PathBuf::from(new_path),
+                    hunks: Vec::new(),
+                    is_binary: true,
+                });
+                index += 1;
+            } else {
+                index += 1;
+            }
+        }
+
+        Ok(files)
+    }
+
+    /// Strip the optional tab-separated timestamp `diff -u` adds after the path
+    fn header_path(header: &str) -> PathBuf {
+        let header = header.trim_end_matches('\r');
+        PathBuf::from(header.split('\t').next().unwrap_or(header))
+    }
+
+    /// Parse one hunk starting at its `@@` header, returning it with the number of
+    /// input lines consumed
+    fn parse_hunk(lines: &[&str], line_number: usize) -> Result<(DiffHunk, usize), ParseError> {
+        let header = lines[0].trim_end_matches('\r');
+        let invalid = || ParseError::InvalidHunkHeader { line: line_number, text: header.to_string() };
+
+        // "@@ -old_start[,old_lines] +new_start[,new_lines] @@ optional context"
+        let ranges = header
+            .strip_prefix("@@ ")
+            .and_then(|rest| rest.split_once(" @@"))
+            .map(|(ranges, _context)| ranges)
+            .ok_or_else(invalid)?;
+        let (old_range, new_range) = ranges.split_once(' ').ok_or_else(invalid)?;
+        let parse_range = |range: Option<&str>| -> Option<(u32, u32)> {
+            let (start, count) = match range?.split_once(',') {
+                Some((start, count)) => (start, count.parse().ok()?),
+                None => (range?, 1),
+            };
+            Some((start.parse().ok()?, count))
+        };
+        let (old_start, old_lines) = parse_range(old_range.strip_prefix('-')).ok_or_else(invalid)?;
+        let (new_start, new_lines) = parse_range(new_range.strip_prefix('+')).ok_or_else(invalid)?;
+
+        let mut hunk = DiffHunk { old_start, old_lines, new_start, new_lines, lines: Vec::new() };
+        // Line numbers of an empty side start after old_start/new_start
+        let mut old_line_no = if old_lines == 0 { old_start + 1 } else { old_start };
+        let mut new_line_no = if new_lines == 0 { new_start + 1 } else { new_start };
+        let (mut old_seen, mut new_seen) = (0, 0);
+        let mut consumed = 1;
+
+        while old_seen < old_lines || new_seen < new_lines {
+            let Some(line) = lines.get(consumed) else {
+                return Err(ParseError::TruncatedHunk { line: line_number + consumed, old_lines, new_lines });
+            };
+            consumed += 1;
+
+            // Editors often strip the single space from empty context lines
+            let (prefix, content) = match line.chars().next() {
+                Some(prefix) => (prefix, &line[prefix.len_utf8()..]),
+                None => (' ', ""),
+            };
+            let kind = match prefix {
+                ' ' => DiffLineKind::Context,
+                '-' => DiffLineKind::Deletion,
+                '+' => DiffLineKind::Addition,
+                '\\' => continue,
+                _ if line.starts_with("@@") || line.starts_with("--- ") => {
+                    return Err(ParseError::TruncatedHunk { line: line_number + consumed - 1, old_lines, new_lines });
+                }
+                _ => return Err(ParseError::UnexpectedLine { line: line_number + consumed - 1, text: line.to_string() }),
+            };
+
+            let old_line_number = (kind != DiffLineKind::Addition).then(|| {
+                old_seen += 1;
+                old_line_no += 1;
+                old_line_no - 1
+            });
+            let new_line_number = (kind != DiffLineKind::Deletion).then(|| {
+                new_seen += 1;
+                new_line_no += 1;
+                new_line_no - 1
+            });
+            if old_seen > old_lines || new_seen > new_lines {
+                return Err(ParseError::UnexpectedLine { line: line_number + consumed - 1, text: line.to_string() });
+            }
+
+            hunk.lines.push(DiffLine { kind, content: content.to_string(), old_line_number, new_line_number });
+        }
+
+        // A trailing "\\ No newline at end of file" belongs to this hunk
+        if lines.get(consumed).is_some_and(|line| line.starts_with('\\')) {
+            consumed += 1;
+        }
+
+        Ok((hunk, consumed))
+    }
+
+    fn apply_hunks(source: &str, hunks: &[DiffHunk], reverse: bool) -> Result<String> {
+        let (removed_kind, added_kind) = if reverse {
+            (DiffLineKind::Addition, DiffLineKind::Deletion)
+        } else {
+            (DiffLineKind::Deletion, DiffLineKind::Addition)
+        };
+
+        let lines: Vec<&str> = source
+            .split_inclusive('\n')
+            .map(|line| line.strip_suffix('\n').unwrap_or(line))
+            .collect();
+        let mut output: Vec<&str> = Vec::with_capacity(lines.len());
+        let mut next = 0usize;
+
+        for (index, hunk) in hunks.iter().enumerate() {
+            let (start, count) = if reverse {
+                (hunk.new_start, hunk.new_lines)
+            } else {
+                (hunk.old_start, hunk.old_lines)
+            };
+            let expected: Vec<&str> = hunk.lines
+                .iter()
+                .filter(|line| line.kind != added_kind)
+                .map(|line| line.content.as_str())
+                .collect();
+            if expected.len() != count as usize {
+                anyhow::bail!(
+                    "Hunk {} header covers {} lines but the hunk contains {}",
+                    index + 1, count, expected.len()
+                );
+            }
+
+            // An empty side starts *after* its start line
+            let position = if count == 0 { start as usize } else { (start as usize).saturating_sub(1) };
+            let actual = &lines[position.min(lines.len())..(position + expected.len()).min(lines.len())];
+            // A hunk that starts past the end of the file (possible for an
+            // empty side, whose `actual` is empty too) doesn't apply either
+            if position < next || position > lines.len() || actual != expected.as_slice() {
+                return Err(DiffyError::PatchConflict {
+                    hunk: index,
+                    expected: expected.join("\n"),
+                    actual: actual.join("\n"),
+                }.into());
+            }
+
+            output.extend_from_slice(&lines[next..position]);
+            output.extend(hunk.lines
+                .iter()
+                .filter(|line| line.kind != removed_kind)
+                .map(|line| line.content.as_str()));
+            next = position + expected.len();
+        }
+        output.extend_from_slice(&lines[next..]);
+
+        let mut result = output.join("\n");
+        if !output.is_empty() && (source.is_empty() || source.ends_with('\n')) {
+            result.push('\n');
+        }
+        Ok(result)
+    }
+
+    /// Set the header counts the way unified diff expects: each side counts its
+    /// context lines plus its own changes, and an empty side starts at the line
+    /// before the change.
+    fn finalize_hunk(mut hunk: DiffHunk) -> DiffHunk {
+        hunk.old_lines = hunk.lines.iter().filter(|line| line.kind != DiffLineKind::Addition).count() as u32;
+        hunk.new_lines = hunk.lines.iter().filter(|line| line.kind != DiffLineKind::Deletion).count() as u32;
+        if hunk.old_lines == 0 {
+            hunk.old_start = hunk.old_start.saturating_sub(1);
+        }
+        if hunk.new_lines == 0 {
+            hunk.new_start = hunk.new_start.saturating_sub(1);
+        }
+        hunk
+    }
+
     fn create_deletion_hunks(&self, content: &str) -> Vec<DiffHunk> {
         let lines: Vec<&str> = content.lines().collect();
         if lines.is_empty() {
@@ -241,7 +920,7 @@ impl DiffEngine {
         vec![DiffHunk {
             old_start: 1,
             old_lines: lines.len() as u32,
-            new_start: 1,
+            new_start: 0,
             new_lines: 0,
             lines: diff_lines,
         }]
@@ -264,7 +943,7 @@ impl DiffEngine {
         }
 
         vec![DiffHunk {
-            old_start: 1,
+            old_start: 0,
             old_lines: 0,
             new_start: 1,
             new_lines: lines.len() as u32,
@@ -272,6 +951,26 @@ impl DiffEngine {
         }]
     }
 
+    /// Placeholder diff for a pair with a side over `max_file_size`
+    pub fn too_large_diff(left_path: &Path, right_path: &Path) -> FileDiff {
+        FileDiff {
+            left_content: None,
+            right_content: None,
+            hunks: Vec::new(),
+            is_binary: true,
+            similarity: 0.0,
+            binary: Some(BinaryFileDiff {
+                left_size: Self::file_size(left_path),
+                right_size: Self::file_size(right_path),
+                too_large: true,
+            }),
+        }
+    }
+
+    fn file_size(path: &Path) -> Option<u64> {
+        std::fs::metadata(path).ok().map(|metadata| metadata.len())
+    }
+
     pub fn is_binary_file(path: &Path) -> Result<bool> {
         if !path.exists() {
             return Ok(false);
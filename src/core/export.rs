@@ -0,0 +1,358 @@
+use crate::core::types::{DiffLineKind, DiffResult, DiffStatus, FileDiff, FileEntry, WordDiffLine, WordSpan};
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// Page written at the top of an `--output-dir` report
+pub const REPORT_INDEX: &str = "index.html";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    Unified,
+    Markdown,
+    Html,
+    Json,
+}
+
+/// A changed file together with its line-level diff
+#[derive(Debug, Clone, Serialize)]
+pub struct ExportedFile {
+    pub path: PathBuf,
+    pub status: DiffStatus,
+    pub diff: FileDiff,
+}
+
+#[derive(Serialize)]
+struct JsonExport<'a> {
+    result: &'a DiffResult,
+    files: &'a [ExportedFile],
+}
+
+impl ExportFormat {
+    pub const ALL: [ExportFormat; 4] = [
+        ExportFormat::Unified,
+        ExportFormat::Markdown,
+        ExportFormat::Html,
+        ExportFormat::Json,
+    ];
+
+    /// Name used in the `format` query parameter of `/api/export`
+    pub fn name(&self) -> &'static str {
+        match self {
+            ExportFormat::Unified => "unified",
+            ExportFormat::Markdown => "markdown",
+            ExportFormat::Html => "html",
+            ExportFormat::Json => "json",
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ExportFormat::Unified => "patch",
+            ExportFormat::Markdown => "md",
+            ExportFormat::Html => "html",
+            ExportFormat::Json => "json",
+        }
+    }
+
+    pub fn render(&self, result: &DiffResult, files: &[ExportedFile]) -> Result<String> {
+        Ok(match self {
+            ExportFormat::Unified => render_unified(files),
+            ExportFormat::Markdown => render_markdown(result, files),
+            ExportFormat::Html => render_html(result, files),
+            ExportFormat::Json => serde_json::to_string_pretty(&JsonExport { result, files })?,
+        })
+    }
+}
+
+/// Paths of all non-directory entries that differ, in tree order
+pub fn changed_files(tree: &FileEntry) -> Vec<(PathBuf, DiffStatus)> {
+    fn walk(entry: &FileEntry, files: &mut Vec<(PathBuf, DiffStatus)>) {
+        if !entry.is_directory && entry.status != DiffStatus::Unchanged {
+            files.push((entry.relative_path.clone(), entry.status.clone()));
+        }
+        for child in &entry.children {
+            walk(child, files);
+        }
+    }
+
+    let mut files = Vec::new();
+    walk(tree, &mut files);
+    files
+}
+
+/// Render one file as a unified diff with `---`/`+++` headers
+pub fn unified_file(path: &Path, status: &DiffStatus, diff: &FileDiff) -> String {
+    let path = path.to_string_lossy().replace('\\', "/");
+    let old_name = if *status == DiffStatus::Added { "/dev/null".to_string() } else { format!("a/{}", path) };
+    let new_name = if *status == DiffStatus::Removed { "/dev/null".to_string() } else { format!("b/{}", path) };
+
+    unified_with_names(&old_name, &new_name, diff)
+}
+
+/// Render a unified diff using `old_name` and `new_name` verbatim in the headers
+pub fn unified_with_names(old_name: &str, new_name: &str, diff: &FileDiff) -> String {
+    if diff.binary.as_ref().is_some_and(|binary| binary.too_large) {
+        return format!("Files {} and {} differ (too large to diff)\n", old_name, new_name);
+    }
+    if diff.is_binary {
+        return format!("Binary files {} and {} differ\n", old_name, new_name);
+    }
+
+    let mut output = format!("--- {}\n+++ {}\n", old_name, new_name);
+    for hunk in &diff.hunks {
+        output.push_str(&format!(
+            "@@ -{},{} +{},{} @@\n",
+            hunk.old_start, hunk.old_lines, hunk.new_start, hunk.new_lines
+        ));
+        for line in &hunk.lines {
+            output.push_str(line_prefix(&line.kind));
+            output.push_str(&line.content);
+            output.push('\n');
+        }
+    }
+    output
+}
+
+/// Render word-diff output as text, marking changed words `[-removed-]` and
+/// `{+added+}` the way `git diff --word-diff=plain` does
+pub fn word_diff_text(lines: &[WordDiffLine]) -> String {
+    fn spans(prefix: char, spans: &[WordSpan], open: &str, close: &str) -> String {
+        let mut line = prefix.to_string();
+        for span in spans {
+            if span.changed {
+                line.push_str(open);
+                line.push_str(&span.text);
+                line.push_str(close);
+            } else {
+                line.push_str(&span.text);
+            }
+        }
+        line
+    }
+
+    let mut output = String::new();
+    for line in lines {
+        let rendered = match line {
+            WordDiffLine::Context(text) => format!(" {}", text),
+            WordDiffLine::Removed(removed) => spans('-', removed, "[-", "-]"),
+            WordDiffLine::Added(added) => spans('+', added, "{+", "+}"),
+        };
+        output.push_str(&rendered);
+        output.push('\n');
+    }
+    output
+}
+
+fn line_prefix(kind: &DiffLineKind) -> &'static str {
+    match kind {
+        DiffLineKind::Addition => "+",
+        DiffLineKind::Deletion => "-",
+        DiffLineKind::Context => " ",
+    }
+}
+
+fn render_unified(files: &[ExportedFile]) -> String {
+    files
+        .iter()
+        .map(|file| unified_file(&file.path, &file.status, &file.diff))
+        .collect()
+}
+
+fn render_markdown(result: &DiffResult, files: &[ExportedFile]) -> String {
+    let mut output = format!(
+        "# Diff: `{}` → `{}`\n\n| Added | Removed | Modified | Total files |\n|---|---|---|---|\n| {} | {} | {} | {} |\n",
+        result.left_path.display(),
+        result.right_path.display(),
+        result.added_count,
+        result.removed_count,
+        result.modified_count,
+        result.total_files,
+    );
+
+    for file in files {
+        output.push_str(&format!("\n## {} `{}` ({:?})\n\n", file.status.icon(), file.path.display(), file.status));
+        output.push_str("```diff\n");
+        output.push_str(&unified_file(&file.path, &file.status, &file.diff));
+        output.push_str("```\n");
+    }
+    output
+}
+
+fn render_html(result: &DiffResult, files: &[ExportedFile]) -> String {
+    let mut body = String::new();
+    for file in files {
+        body.push_str(&format!(
+            "<section class=\"file\">\n<h2 style=\"color: {}\">{} {}</h2>\n<pre>",
+            file.status.color_code(),
+            file.status.icon(),
+            escape_html(&file.path.display().to_string()),
+        ));
+
+        if file.diff.binary.as_ref().is_some_and(|binary| binary.too_large) {
+            body.push_str("File too large to diff");
+        } else if file.diff.is_binary {
+            body.push_str("Binary file differs");
+        }
+        for hunk in &file.diff.hunks {
+            body.push_str(&format!(
+                "<span class=\"hunk\">@@ -{},{} +{},{} @@</span>\n",
+                hunk.old_start, hunk.old_lines, hunk.new_start, hunk.new_lines
+            ));
+            for line in &hunk.lines {
+                let class = match line.kind {
+                    DiffLineKind::Addition => "add",
+                    DiffLineKind::Deletion => "del",
+                    DiffLineKind::Context => "ctx",
+                };
+                body.push_str(&format!(
+                    "<span class=\"{}\">{}{}</span>\n",
+                    class,
+                    line_prefix(&line.kind),
+                    escape_html(&line.content)
+                ));
+            }
+        }
+        body.push_str("</pre>\n</section>\n");
+    }
+
+    format!(
+        r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+<meta charset="UTF-8">
+<title>Diffy report</title>
+<style>
+body {{ background: #000000; color: #ffffff; font-family: 'Segoe UI', sans-serif; }}
+pre {{ font-family: monospace; font-size: 13px; }}
+.hunk {{ color: #00bcd4; }}
+.add {{ color: #4caf50; background: #9ccc2c22; }}
+.del {{ color: #f44336; background: #ff000022; }}
+.ctx {{ color: #9e9e9e; }}
+</style>
+</head>
+<body>
+<h1>{} → {}</h1>
+<p>+{} -{} ~{} (total: {})</p>
+{}</body>
+</html>
+"#,
+        escape_html(&result.left_path.display().to_string()),
+        escape_html(&result.right_path.display().to_string()),
+        result.added_count,
+        result.removed_count,
+        result.modified_count,
+        result.total_files,
+        body
+    )
+}
+
+/// Write an exploded report into `dir`: one `<path>.diff` unified diff per
+/// changed file, laid out like the compared directories, and an `index.html`
+/// linking to them. Everything is inline, so the directory works offline.
+/// `dir` must be empty or not exist yet. Returns the path of `index.html`.
+pub fn write_report(dir: &Path, result: &DiffResult, files: &[ExportedFile]) -> Result<PathBuf> {
+    if dir.exists() && std::fs::read_dir(dir)?.next().is_some() {
+        anyhow::bail!("Report directory '{}' is not empty", dir.display());
+    }
+    std::fs::create_dir_all(dir).with_context(|| format!("Failed to create '{}'", dir.display()))?;
+
+    let mut rows = String::new();
+    for file in files {
+        let diff_path = report_diff_path(&file.path);
+        let target = dir.join(&diff_path);
+        if let Some(parent) = target.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(&target, unified_file(&file.path, &file.status, &file.diff))
+            .with_context(|| format!("Failed to write '{}'", target.display()))?;
+
+        let lines = if file.diff.is_binary {
+            "<td colspan=\"2\">binary</td>".to_string()
+        } else {
+            format!(
+                "<td class=\"add\">+{}</td><td class=\"del\">-{}</td>",
+                file.diff.total_additions(),
+                file.diff.total_deletions()
+            )
+        };
+        rows.push_str(&format!(
+            "<tr><td style=\"color: {}\">{} {:?}</td><td><a href=\"{}\">{}</a></td>{}</tr>\n",
+            file.status.color_code(),
+            file.status.icon(),
+            file.status,
+            escape_html(&report_href(&diff_path)),
+            escape_html(&file.path.display().to_string()),
+            lines
+        ));
+    }
+
+    let index = format!(
+        r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+<meta charset="UTF-8">
+<title>Diffy report</title>
+<style>
+body {{ background: #000000; color: #ffffff; font-family: 'Segoe UI', sans-serif; }}
+a {{ color: #00bcd4; }}
+table {{ border-collapse: collapse; font-family: monospace; font-size: 13px; }}
+td, th {{ padding: 2px 12px; text-align: left; }}
+.add {{ color: #4caf50; }}
+.del {{ color: #f44336; }}
+</style>
+</head>
+<body>
+<h1>{} → {}</h1>
+<p>+{} -{} ~{} (total: {})</p>
+<table>
+<tr><th>Status</th><th>File</th><th colspan="2">Lines</th></tr>
+{}</table>
+</body>
+</html>
+"#,
+        escape_html(&result.left_path.display().to_string()),
+        escape_html(&result.right_path.display().to_string()),
+        result.added_count,
+        result.removed_count,
+        result.modified_count,
+        result.total_files,
+        rows
+    );
+    let index_path = dir.join(REPORT_INDEX);
+    std::fs::write(&index_path, index).with_context(|| format!("Failed to write '{}'", index_path.display()))?;
+    Ok(index_path)
+}
+
+/// `src/main.rs` → `src/main.rs.diff`
+fn report_diff_path(path: &Path) -> PathBuf {
+    let mut diff_path = path.as_os_str().to_owned();
+    diff_path.push(".diff");
+    PathBuf::from(diff_path)
+}
+
+/// A relative URL for `path`, with `/` separators and unsafe bytes percent-encoded
+fn report_href(path: &Path) -> String {
+    let mut href = String::new();
+    for (index, component) in path.components().enumerate() {
+        if index > 0 {
+            href.push('/');
+        }
+        for byte in component.as_os_str().to_string_lossy().bytes() {
+            if byte.is_ascii_alphanumeric() || b"-._~".contains(&byte) {
+                href.push(byte as char);
+            } else {
+                href.push_str(&format!("%{:02X}", byte));
+            }
+        }
+    }
+    href
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
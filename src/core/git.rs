@@ -0,0 +1,174 @@
+//! Writes git trees to plain directories so they can be compared like any
+//! other pair of paths.
+
+use anyhow::{Context, Result};
+use git2::{FileMode, ObjectType, Oid, Repository, Tree, TreeWalkMode, TreeWalkResult};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Mode bits git uses for symlinks and executable files
+const SYMLINK_MODE: i32 = 0o120000;
+const EXECUTABLE_MODE: i32 = 0o100755;
+
+/// A directory under the system temp dir, removed when dropped
+#[derive(Debug)]
+pub(crate) struct TempCheckout {
+    path: PathBuf,
+}
+
+impl TempCheckout {
+    pub(crate) fn create(label: &str) -> Result<Self> {
+        static NEXT_ID: AtomicUsize = AtomicUsize::new(0);
+        let name = format!("diffy-{}-{}-{}", label, std::process::id(), NEXT_ID.fetch_add(1, Ordering::Relaxed));
+        let path = std::env::temp_dir().join(name);
+        std::fs::create_dir_all(&path).with_context(|| format!("Failed to create {}", path.display()))?;
+        Ok(Self { path })
+    }
+
+    pub(crate) fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for TempCheckout {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.path);
+    }
+}
+
+/// The commit of `stash@{index}`, where 0 is the most recent stash
+pub(crate) fn stash_commit(repo: &mut Repository, index: usize) -> Result<Oid> {
+    let mut found = None;
+    repo.stash_foreach(|stash_index, _message, oid| {
+        if stash_index == index {
+            found = Some(*oid);
+        }
+        found.is_none()
+    })?;
+    found.ok_or_else(|| anyhow::anyhow!("No stash@{{{}}} in this repository", index))
+}
+
+/// Commit details for a blamed line
+pub(crate) struct LineCommit {
+    pub hash: String,
+    pub author: Option<String>,
+    pub date: String,
+}
+
+/// The commit that last changed each line of `content`, the current text of
+/// `file`, indexed from 0. Lines not yet committed are `None`. Returns `None`
+/// when `file` is not tracked in a repository; `repo` is where to look for
+/// one, defaulting to the file's directory.
+pub(crate) fn blame(file: &Path, content: &str, repo: Option<&Path>) -> Option<Vec<Option<LineCommit>>> {
+    let file = file.canonicalize().ok()?;
+    let repository = Repository::discover(repo.or(file.parent())?).ok()?;
+    let relative = file.strip_prefix(repository.workdir()?.canonicalize().ok()?).ok()?;
+
+    // Blaming the buffer attributes uncommitted edits to no commit
+    let committed = repository.blame_file(relative, None).ok()?;
+    let blame = committed.blame_buffer(content.as_bytes()).ok()?;
+
+    let commits = (1..=content.lines().count())
+        .map(|line| {
+            let hunk = blame.get_line(line)?;
+            let id = hunk.final_commit_id();
+            if id.is_zero() {
+                return None;
+            }
+            let signature = hunk.final_signature();
+            let seconds = u64::try_from(signature.when().seconds()).unwrap_or(0);
+            let time = std::time::UNIX_EPOCH + std::time::Duration::from_secs(seconds);
+            Some(LineCommit {
+                hash: id.to_string(),
+                author: signature.name().map(str::to_string),
+                date: humantime::format_rfc3339_seconds(time).to_string(),
+            })
+        })
+        .collect();
+    Some(commits)
+}
+
+/// The tree of the commit `branch` points at
+pub(crate) fn branch_tree<'r>(repo: &'r Repository, branch: &str) -> Result<Tree<'r>> {
+    let commit = repo
+        .revparse_single(branch)
+        .and_then(|object| object.peel_to_commit())
+        .map_err(|e| anyhow::anyhow!("Unknown branch '{}': {}", branch, e.message()))?;
+    Ok(commit.tree()?)
+}
+
+/// Write every file in `tree` below `destination`, keeping symlinks and the
+/// executable bit. Submodules are skipped.
+pub(crate) fn write_tree(repo: &Repository, tree: &Tree, destination: &Path) -> Result<()> {
+    let mut failure = None;
+    tree.walk(TreeWalkMode::PreOrder, |root, entry| {
+        let Some(name) = entry.name() else { return TreeWalkResult::Skip };
+        let path = destination.join(root).join(name);
+        let written = match entry.kind() {
+            Some(ObjectType::Tree) => std::fs::create_dir_all(&path).map_err(anyhow::Error::from),
+            Some(ObjectType::Blob) => repo
+                .find_blob(entry.id())
+                .map_err(anyhow::Error::from)
+                .and_then(|blob| write_blob(&path, blob.content(), entry.filemode())),
+            _ => Ok(()),
+        };
+
+        match written {
+            Ok(()) => TreeWalkResult::Ok,
+            Err(e) => {
+                failure = Some(e.context(format!("Failed to write {}", path.display())));
+                TreeWalkResult::Abort
+            }
+        }
+    })?;
+
+    failure.map_or(Ok(()), Err)
+}
+
+/// Write only the files that differ between `tree` (nothing before the first
+/// commit) and the index: the committed version of each below `old_destination`
+/// and the staged version below `new_destination`. Unchanged files are never
+/// read, so this stays fast in large repositories. Submodules are skipped.
+pub(crate) fn write_staged_changes(
+    repo: &Repository,
+    tree: Option<&Tree>,
+    old_destination: &Path,
+    new_destination: &Path,
+) -> Result<()> {
+    let index = repo.index()?;
+    let diff = repo.diff_tree_to_index(tree, Some(&index), None)?;
+    for delta in diff.deltas() {
+        for (file, destination) in [(delta.old_file(), old_destination), (delta.new_file(), new_destination)] {
+            let Some(relative) = file.path().filter(|_| file.exists() && file.mode() != FileMode::Commit) else {
+                continue;
+            };
+            let path = destination.join(relative);
+            if let Some(parent) = path.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+            let blob = repo.find_blob(file.id())?;
+            write_blob(&path, blob.content(), i32::from(file.mode()))
+                .with_context(|| format!("Failed to write {}", path.display()))?;
+        }
+    }
+    Ok(())
+}
+
+fn write_blob(path: &Path, content: &[u8], mode: i32) -> Result<()> {
+    #[cfg(unix)]
+    if mode == SYMLINK_MODE {
+        use std::os::unix::ffi::OsStrExt;
+        std::os::unix::fs::symlink(std::ffi::OsStr::from_bytes(content), path)?;
+        return Ok(());
+    }
+
+    std::fs::write(path, content)?;
+
+    #[cfg(unix)]
+    if mode == EXECUTABLE_MODE {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o755))?;
+    }
+
+    Ok(())
+}
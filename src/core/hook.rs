@@ -0,0 +1,147 @@
+//! The git pre-commit hook written by `diffy hook install`, which rejects
+//! commits that add sensitive files such as `.env` or private keys.
+
+use anyhow::{Context, Result};
+use std::path::{Path, PathBuf};
+
+/// Bumped whenever the generated script changes, so `diffy hook check` can
+/// tell an installed hook is out of date
+pub const HOOK_VERSION: u32 = 1;
+
+/// Files rejected unless other patterns are given; gitignore syntax
+pub const DEFAULT_GUARD_PATTERNS: &[&str] = &[".env", ".env.*", "*.pem", "*.key", "*.p12", "*.pfx", "id_rsa", "id_ed25519", "*.keystore"];
+
+const MARKER: &str = "# diffy-hook-version: ";
+
+/// What the hook compares and which added files it rejects
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HookOptions {
+    /// Paths to compare instead of the last commit and the staged files
+    pub left: Option<PathBuf>,
+    pub right: Option<PathBuf>,
+    pub guard_patterns: Vec<String>,
+}
+
+impl Default for HookOptions {
+    fn default() -> Self {
+        Self {
+            left: None,
+            right: None,
+            guard_patterns: DEFAULT_GUARD_PATTERNS.iter().map(|pattern| pattern.to_string()).collect(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HookStatus {
+    Missing,
+    /// Written by this version of diffy
+    Current,
+    /// Written by an older or newer version of diffy
+    Outdated,
+    /// A pre-commit hook diffy did not write
+    Foreign,
+}
+
+/// The pre-commit script for `options`. It is POSIX sh, which Git for
+/// Windows also runs hooks with, and it skips the check when `diffy` is not
+/// on the PATH rather than blocking every commit.
+pub fn script(options: &HookOptions) -> String {
+    let compared = match (&options.left, &options.right) {
+        (None, None) => "--git-staged --repo .".to_string(),
+        (left, right) => format!(
+            "--left {} --right {}",
+            quote(&left.as_deref().unwrap_or(Path::new(".")).display().to_string()),
+            quote(&right.as_deref().unwrap_or(Path::new(".")).display().to_string()),
+        ),
+    };
+
+    format!(
+        "#!/bin/sh\n\
+         # Installed by `diffy hook install`; `diffy hook uninstall` removes it.\n\
+         {MARKER}{HOOK_VERSION}\n\
+         #\n\
+         # Rejects the commit when a newly added file matches a guard pattern.\n\
+         \n\
+         if ! command -v diffy >/dev/null 2>&1; then\n\
+         \x20   echo \"diffy pre-commit hook: diffy is not on the PATH, skipping the check\" >&2\n\
+         \x20   exit 0\n\
+         fi\n\
+         \n\
+         exec diffy {compared} --format stats --guard-patterns {patterns}\n",
+        patterns = quote(&options.guard_patterns.join(",")),
+    )
+}
+
+/// Where git looks for hooks in the repository containing `repo_path`:
+/// `core.hooksPath` when set, otherwise `.git/hooks`
+pub fn hooks_dir(repo_path: &Path) -> Result<PathBuf> {
+    let repo = git2::Repository::discover(repo_path)
+        .map_err(|e| anyhow::anyhow!("{} is not in a git repository: {}", repo_path.display(), e.message()))?;
+    if let Ok(hooks_path) = repo.config().and_then(|config| config.get_path("core.hooksPath")) {
+        return Ok(match repo.workdir() {
+            Some(workdir) if hooks_path.is_relative() => workdir.join(hooks_path),
+            _ => hooks_path,
+        });
+    }
+    Ok(repo.commondir().join("hooks"))
+}
+
+/// Write the pre-commit hook, replacing an earlier one written by diffy. A
+/// hook from elsewhere is only replaced with `force`. Returns the hook's path.
+pub fn install(repo_path: &Path, options: &HookOptions, force: bool) -> Result<PathBuf> {
+    let path = hooks_dir(repo_path)?.join("pre-commit");
+    if status_of(&path)? == HookStatus::Foreign && !force {
+        anyhow::bail!("{} was not written by diffy; use --force to replace it", path.display());
+    }
+
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).with_context(|| format!("Failed to create {}", parent.display()))?;
+    }
+    std::fs::write(&path, script(options)).with_context(|| format!("Failed to write {}", path.display()))?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755))?;
+    }
+    Ok(path)
+}
+
+/// Remove the pre-commit hook if diffy wrote it. Returns whether a hook was
+/// removed.
+pub fn uninstall(repo_path: &Path) -> Result<bool> {
+    let path = hooks_dir(repo_path)?.join("pre-commit");
+    match status_of(&path)? {
+        HookStatus::Missing => Ok(false),
+        HookStatus::Foreign => anyhow::bail!("{} was not written by diffy; leaving it in place", path.display()),
+        HookStatus::Current | HookStatus::Outdated => {
+            std::fs::remove_file(&path).with_context(|| format!("Failed to remove {}", path.display()))?;
+            Ok(true)
+        }
+    }
+}
+
+/// Whether the repository's pre-commit hook is diffy's current one
+pub fn check(repo_path: &Path) -> Result<HookStatus> {
+    status_of(&hooks_dir(repo_path)?.join("pre-commit"))
+}
+
+fn status_of(path: &Path) -> Result<HookStatus> {
+    let script = match std::fs::read_to_string(path) {
+        Ok(script) => script,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(HookStatus::Missing),
+        Err(e) => return Err(e).with_context(|| format!("Failed to read {}", path.display())),
+    };
+
+    let version = script.lines().find_map(|line| line.strip_prefix(MARKER));
+    Ok(match version {
+        None => HookStatus::Foreign,
+        Some(version) if version.trim() == HOOK_VERSION.to_string() => HookStatus::Current,
+        Some(_) => HookStatus::Outdated,
+    })
+}
+
+/// `text` as a single-quoted shell word
+fn quote(text: &str) -> String {
+    format!("'{}'", text.replace('\'', r"'\''"))
+}
@@ -1,155 +1,1086 @@
+pub mod content;
 pub mod diff;
+pub mod export;
+mod git;
+pub mod hook;
+pub mod patch;
+mod snapshot;
 pub mod tree;
 pub mod types;
 
+use crate::core::content::ContentStore;
 use crate::core::diff::DiffEngine;
+use crate::core::export::{ExportFormat, ExportedFile};
+use crate::core::git::TempCheckout;
 use crate::core::tree::FileTreeBuilder;
-use crate::core::types::{DiffResult, DiffStatus, FileEntry};
+use crate::config::DiffConfig;
+use crate::error::DiffyError;
+use crate::core::types::{BlameLine, DiffFilter, DiffOptions, DiffResult, DiffStatistics, DiffStatus, FileEntry, FileMetadata, LineSummary, RenamedFile, SnapshotInfo, DEFAULT_RENAME_THRESHOLD};
 use anyhow::Result;
 use rayon::prelude::*;
-use std::path::PathBuf;
-use std::time::Instant;
+use std::collections::{HashMap, HashSet};
+use std::io::BufRead;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Instant, SystemTime};
 
 #[derive(Clone)]
 pub struct DiffyCore {
     pub left_path: PathBuf,
     pub right_path: PathBuf,
     pub include_ignored: bool,
+    pub show_empty_dirs: bool,
+    pub case_insensitive: bool,
+    pub diff_options: DiffOptions,
+    /// Extra gitignore-syntax lines applied to both sides, relative to the roots
+    pub ignore_patterns: Vec<String>,
+    /// Describes the comparison in UI headers instead of the two paths,
+    /// e.g. "Stash@{0} vs Working Tree" or "main..feature/my-feature"
+    pub description: Option<String>,
+    /// Limits what [`DiffyCore::analyze`] and the other analyses return, like `--diff-filter`
+    pub diff_filter: Option<DiffFilter>,
+    /// Keeps temporary checkouts behind `left_path`/`right_path` alive
+    checkouts: Vec<Arc<TempCheckout>>,
+    /// Shared with clones, so every diff of this comparison can reuse text
+    content_store: Arc<ContentStore>,
+}
+
+#[derive(Default)]
+pub struct DiffyCoreBuilder {
+    left_path: Option<PathBuf>,
+    right_path: Option<PathBuf>,
+    include_ignored: bool,
+    show_empty_dirs: bool,
+    case_insensitive: bool,
+    diff_options: DiffOptions,
+}
+
+impl DiffyCoreBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn from_config(cfg: &DiffConfig) -> Self {
+        Self {
+            left_path: None,
+            right_path: None,
+            include_ignored: cfg.tree.include_ignored,
+            show_empty_dirs: cfg.tree.show_empty_dirs,
+            case_insensitive: cfg.tree.case_insensitive,
+            diff_options: cfg.diff.clone(),
+        }
+    }
+
+    pub fn left(mut self, path: PathBuf) -> Self {
+        self.left_path = Some(path);
+        self
+    }
+
+    pub fn right(mut self, path: PathBuf) -> Self {
+        self.right_path = Some(path);
+        self
+    }
+
+    pub fn include_ignored(mut self, include_ignored: bool) -> Self {
+        self.include_ignored = include_ignored;
+        self
+    }
+
+    pub fn show_empty_dirs(mut self, show_empty_dirs: bool) -> Self {
+        self.show_empty_dirs = show_empty_dirs;
+        self
+    }
+
+    pub fn case_insensitive(mut self, case_insensitive: bool) -> Self {
+        self.case_insensitive = case_insensitive;
+        self
+    }
+
+    pub fn diff_options(mut self, diff_options: DiffOptions) -> Self {
+        self.diff_options = diff_options;
+        self
+    }
+
+    pub fn build(self) -> Result<DiffyCore> {
+        Ok(DiffyCore {
+            left_path: self.left_path.ok_or_else(|| anyhow::anyhow!("Left path is required"))?,
+            right_path: self.right_path.ok_or_else(|| anyhow::anyhow!("Right path is required"))?,
+            include_ignored: self.include_ignored,
+            show_empty_dirs: self.show_empty_dirs,
+            case_insensitive: self.case_insensitive,
+            diff_options: self.diff_options,
+            ignore_patterns: Vec::new(),
+            description: None,
+            diff_filter: None,
+            checkouts: Vec::new(),
+            content_store: Arc::default(),
+        })
+    }
+}
+
+/// File totals for a tree. Binary files are only counted as binary, so every
+/// file falls into exactly one of the other fields.
+#[derive(Clone, Copy, Default)]
+struct FileCounts {
+    total: usize,
+    added: usize,
+    removed: usize,
+    modified: usize,
+    conflicted: usize,
+    unchanged: usize,
+    binary: usize,
+}
+
+impl FileCounts {
+    fn add(self, other: FileCounts) -> FileCounts {
+        FileCounts {
+            total: self.total + other.total,
+            added: self.added + other.added,
+            removed: self.removed + other.removed,
+            modified: self.modified + other.modified,
+            conflicted: self.conflicted + other.conflicted,
+            unchanged: self.unchanged + other.unchanged,
+            binary: self.binary + other.binary,
+        }
+    }
 }
 
 impl DiffyCore {
     pub fn new(left_path: PathBuf, right_path: PathBuf) -> Self {
-        Self { left_path, right_path, include_ignored: false }
+        Self::new_with_options(left_path, right_path, false)
     }
 
     pub fn new_with_options(left_path: PathBuf, right_path: PathBuf, include_ignored: bool) -> Self {
-        Self { left_path, right_path, include_ignored }
+        Self {
+            left_path,
+            right_path,
+            include_ignored,
+            show_empty_dirs: false,
+            case_insensitive: false,
+            diff_options: DiffOptions::default(),
+            ignore_patterns: Vec::new(),
+            description: None,
+            diff_filter: None,
+            checkouts: Vec::new(),
+            content_store: Arc::default(),
+        }
+    }
+
+    /// Compare the working tree of `repo` (left) with the contents of
+    /// `stash@{stash_index}` (right), written to a temporary directory that is
+    /// removed once the last clone of the core is dropped
+    pub fn from_git_stash(repo: PathBuf, stash_index: usize) -> Result<Self> {
+        let mut repository = git2::Repository::open(&repo)
+            .map_err(|e| anyhow::anyhow!("{} is not a git repository: {}", repo.display(), e.message()))?;
+        let working_tree = repository
+            .workdir()
+            .ok_or_else(|| anyhow::anyhow!("{} is a bare repository", repo.display()))?
+            .to_path_buf();
+
+        let stash = git::stash_commit(&mut repository, stash_index)?;
+        let tree = repository.find_commit(stash)?.tree()?;
+        let checkout = TempCheckout::create("stash")?;
+        git::write_tree(&repository, &tree, checkout.path())?;
+
+        let mut core = Self::new(working_tree, checkout.path().to_path_buf());
+        core.description = Some(format!("Stash@{{{}}} vs Working Tree", stash_index));
+        core.checkouts = vec![Arc::new(checkout)];
+        Ok(core)
+    }
+
+    /// Compare the last commit of the repository containing `repo_path` (left)
+    /// with its staged files (right), as `git diff --cached` would. Only the
+    /// files that differ are written to temporary directories, so unchanged
+    /// files are not part of the result; before the first commit every staged
+    /// file is added.
+    pub fn from_git_staged(repo_path: PathBuf) -> Result<Self> {
+        let repository = git2::Repository::discover(&repo_path)
+            .map_err(|e| anyhow::anyhow!("{} is not in a git repository: {}", repo_path.display(), e.message()))?;
+
+        let head_tree = repository.head().and_then(|head| head.peel_to_tree()).ok();
+        let head = TempCheckout::create("head")?;
+        let staged = TempCheckout::create("staged")?;
+        git::write_staged_changes(&repository, head_tree.as_ref(), head.path(), staged.path())?;
+
+        let mut core = Self::new(head.path().to_path_buf(), staged.path().to_path_buf());
+        core.description = Some("HEAD vs Staged".to_string());
+        core.checkouts = vec![Arc::new(head), Arc::new(staged)];
+        Ok(core)
+    }
+
+    /// Compare the tip of `base_branch` (left) with the tip of `head_branch`
+    /// (right) in the repository containing `repo_path`. Both trees are written
+    /// to temporary directories that are removed once the last clone of the
+    /// core is dropped. Anything git can resolve to a commit works as a branch
+    /// name, including tags and remote branches like `origin/main`.
+    pub fn from_git_branches(repo_path: PathBuf, base_branch: &str, head_branch: &str) -> Result<Self> {
+        let repository = git2::Repository::discover(&repo_path)
+            .map_err(|e| anyhow::anyhow!("{} is not in a git repository: {}", repo_path.display(), e.message()))?;
+
+        let mut checkouts = Vec::new();
+        for branch in [base_branch, head_branch] {
+            let tree = git::branch_tree(&repository, branch)?;
+            let checkout = TempCheckout::create("branch")?;
+            git::write_tree(&repository, &tree, checkout.path())?;
+            checkouts.push(Arc::new(checkout));
+        }
+
+        let mut core = Self::new(checkouts[0].path().to_path_buf(), checkouts[1].path().to_path_buf());
+        core.description = Some(format!("{}..{}", base_branch, head_branch));
+        core.checkouts = checkouts;
+        Ok(core)
+    }
+
+    /// Compare two snapshots in `snapshots_dir`, such as `20240101-120000/`
+    /// and `20240115-094500/`. Each name can be a unique prefix of the
+    /// snapshot's directory name; see [`DiffyCore::list_snapshots`].
+    pub fn from_snapshots(snapshots_dir: PathBuf, old_snapshot: &str, new_snapshot: &str) -> Result<Self> {
+        let snapshots = snapshot::list(&snapshots_dir)?;
+        let old = snapshot::find(&snapshots, old_snapshot)?;
+        let new = snapshot::find(&snapshots, new_snapshot)?;
+
+        let mut core = Self::new(snapshots_dir.join(&old.name), snapshots_dir.join(&new.name));
+        core.description = Some(format!("Snapshot {} vs {}", old.name, new.name));
+        Ok(core)
+    }
+
+    /// The subdirectories of `snapshots_dir`, oldest first
+    pub fn list_snapshots(snapshots_dir: &Path) -> Result<Vec<SnapshotInfo>> {
+        snapshot::list(snapshots_dir)
     }
 
+    /// Compare the snapshot of `path` taken at `since` (see
+    /// [`DiffyCore::parse_since`]) with its current state. Snapshots are read
+    /// from `snapshots_dir`, or from [`DiffyCore::default_snapshots_dir`].
+    /// When every snapshot is newer than `since` the oldest one is used and a
+    /// warning is logged.
+    pub fn from_snapshot_since(path: PathBuf, snapshots_dir: Option<PathBuf>, since: &str) -> Result<Self> {
+        let time = snapshot::parse_since(since)?;
+        let snapshots_dir = match snapshots_dir {
+            Some(dir) => dir,
+            None => Self::default_snapshots_dir(&path)?,
+        };
+        let snapshots = snapshot::list(&snapshots_dir)?;
+        let (old, taken_by_then) = snapshot::at(&snapshots, time)?;
+        if !taken_by_then {
+            tracing::warn!("No snapshot was taken by {}; comparing with the oldest, {}", since, old.name);
+        }
+
+        let mut core = Self::new(snapshots_dir.join(&old.name), path);
+        core.description = Some(format!("Snapshot {} vs now", old.name));
+        Ok(core)
+    }
+
+    /// Save a snapshot of the directory `path` called `name` in
+    /// [`DiffyCore::default_snapshots_dir`]; see [`DiffyCore::snapshot_into`]
+    pub fn snapshot(path: PathBuf, name: &str) -> Result<()> {
+        let snapshots_dir = Self::default_snapshots_dir(&path)?;
+        Self::snapshot_into(&path, &snapshots_dir, name)
+    }
+
+    /// Save a snapshot of the directory `path` as `snapshots_dir/name`. Files
+    /// are hard-linked like `cp -al`, so the snapshot takes almost no space,
+    /// but a file changed in place rather than replaced (as most editors
+    /// save) changes in the snapshot too.
+    pub fn snapshot_into(path: &Path, snapshots_dir: &Path, name: &str) -> Result<()> {
+        if !path.is_dir() {
+            anyhow::bail!("{} is not a directory", path.display());
+        }
+        snapshot::save(path, &snapshots_dir.join(name))
+    }
+
+    /// `~/.cache/diffy/snapshots/<directory name>`, or the same under
+    /// `$XDG_CACHE_HOME`
+    pub fn default_snapshots_dir(path: &Path) -> Result<PathBuf> {
+        snapshot::default_dir(path)
+    }
+
+    /// A snapshot name for `time` that `--since` can date, e.g. `20240115-094500`
+    pub fn snapshot_name(time: SystemTime) -> String {
+        snapshot::name_for(time)
+    }
+
+    /// Whether the compared paths are temporary checkouts, such as those made
+    /// for git branches or stashes, which are removed when the core is dropped
+    pub fn uses_checkouts(&self) -> bool {
+        !self.checkouts.is_empty()
+    }
+
+    /// Split a `<base>..<head>` branch range, as accepted by `--git-branch`
+    /// style APIs, into its two branch names
+    pub fn parse_branch_range(range: &str) -> Result<(&str, &str)> {
+        match range.split_once("..") {
+            Some((base, head)) if !base.is_empty() && !head.is_empty() => Ok((base, head)),
+            _ => anyhow::bail!("Expected a branch range like 'main..feature', got '{}'", range),
+        }
+    }
+
+    /// A core comparing `left_path` and `right_path` with the same settings
+    pub fn with_paths(&self, left_path: PathBuf, right_path: PathBuf) -> Self {
+        Self::new(left_path, right_path).with_settings_from(self)
+    }
+
+    /// Use the tree, ignore and diff settings of `other`, keeping this core's
+    /// paths and description
+    pub fn with_settings_from(mut self, other: &DiffyCore) -> Self {
+        self.include_ignored = other.include_ignored;
+        self.show_empty_dirs = other.show_empty_dirs;
+        self.case_insensitive = other.case_insensitive;
+        self.diff_options = other.diff_options.clone();
+        self.ignore_patterns = other.ignore_patterns.clone();
+        self.diff_filter = other.diff_filter.clone();
+        self
+    }
+
+    /// Keep only the files `filter` allows in every analysis of this core
+    pub fn with_diff_filter(mut self, filter: DiffFilter) -> Self {
+        self.diff_filter = Some(filter);
+        self
+    }
+
+    /// Also skip paths matching the gitignore-syntax patterns in `ignore_path`.
+    /// Patterns are relative to the compared roots and apply to both sides;
+    /// calls can be chained to combine several files.
+    pub fn with_ignore_file(self, ignore_path: PathBuf) -> Result<Self> {
+        let patterns = std::fs::read_to_string(&ignore_path)
+            .map_err(|e| anyhow::anyhow!("Failed to read ignore file {}: {}", ignore_path.display(), e))?;
+        Ok(self.with_ignore_string(&patterns))
+    }
+
+    /// Also skip paths matching `patterns`, gitignore-syntax lines such as
+    /// `"target/\n*.log"`. Invalid lines are logged and skipped.
+    pub fn with_ignore_string(mut self, patterns: &str) -> Self {
+        self.ignore_patterns.extend(patterns.lines().map(str::to_string));
+        self
+    }
+
+    /// Apply the tree and diff settings from `cfg`, keeping the paths
+    pub fn with_config(mut self, cfg: &DiffConfig) -> Self {
+        self.include_ignored = cfg.tree.include_ignored;
+        self.show_empty_dirs = cfg.tree.show_empty_dirs;
+        self.case_insensitive = cfg.tree.case_insensitive;
+        self.diff_options = cfg.diff.clone();
+        self
+    }
+
+    /// Read the left and right paths from the first two lines of stdin
+    pub fn from_stdin_paths() -> Result<Self> {
+        let (left, right) = Self::read_path_pair(std::io::stdin().lock())?;
+        Ok(Self::new(left, right))
+    }
+
+    /// Read two non-empty lines from `reader` as a left/right path pair
+    pub fn read_path_pair<R: BufRead>(reader: R) -> Result<(PathBuf, PathBuf)> {
+        let mut lines = reader.lines();
+        let mut next_path = |side: &str| -> Result<PathBuf> {
+            let line = lines
+                .next()
+                .ok_or_else(|| anyhow::anyhow!("Expected the {} path on stdin", side))??;
+            let line = line.trim_end_matches('\r');
+            if line.is_empty() {
+                anyhow::bail!("The {} path on stdin is empty", side);
+            }
+            Ok(PathBuf::from(line))
+        };
+
+        let left = next_path("left")?;
+        let right = next_path("right")?;
+        Ok((left, right))
+    }
+
+    /// Parse one `--batch` line of the form `<left_path>\t<right_path>`
+    pub fn parse_batch_line(line: &str) -> Result<(PathBuf, PathBuf)> {
+        match line.trim_end_matches('\r').split_once('\t') {
+            Some((left, right)) if !left.is_empty() && !right.is_empty() => {
+                Ok((PathBuf::from(left), PathBuf::from(right)))
+            }
+            _ => anyhow::bail!("Expected '<left_path>\\t<right_path>', got '{}'", line),
+        }
+    }
+
+    fn tree_builder(&self) -> FileTreeBuilder {
+        FileTreeBuilder::new_with_options(self.left_path.clone(), self.right_path.clone(), self.include_ignored)
+            .with_show_empty_dirs(self.show_empty_dirs)
+            .with_case_insensitive(self.case_insensitive)
+            .with_ignore_patterns(&self.ignore_patterns)
+    }
+
+    /// Contents shared between diffs of identical files
+    pub fn content_store(&self) -> &ContentStore {
+        &self.content_store
+    }
+
+    /// Compare the two sides, keeping only the files [`DiffyCore::diff_filter`] allows
     pub fn analyze(&self) -> Result<DiffResult> {
+        self.apply_diff_filter(self.analyze_unfiltered()?)
+    }
+
+    fn analyze_unfiltered(&self) -> Result<DiffResult> {
         let start_time = Instant::now();
-        println!("🔍 Analyzing directories...");
+        eprintln!("🔍 Analyzing directories...");
         
-        let tree_builder = FileTreeBuilder::new_with_options(
-            self.left_path.clone(), 
-            self.right_path.clone(),
-            self.include_ignored
-        );
-        let tree = tree_builder.build()?;
+        let tree_builder = self.tree_builder();
+        let mut tree = tree_builder.build()?;
+        self.fill_line_summaries(&mut tree);
         
-        let (total_files, added_count, removed_count, modified_count) = 
-            Self::count_file_stats(&tree);
+        let result = self.result_for(tree);
 
         let duration = start_time.elapsed();
-        println!("✅ Analysis complete! {} files processed in {:.2}s", 
-                total_files, duration.as_secs_f64());
-        println!("   📊 {} added, {} removed, {} modified", 
-                added_count, removed_count, modified_count);
+        eprintln!("✅ Analysis complete! {} files processed in {:.2}s", 
+                result.total_files, duration.as_secs_f64());
+        eprintln!("   📊 {} added, {} removed, {} modified, {} unchanged, {} binary", 
+                result.added_count, result.removed_count, result.modified_count,
+                result.unchanged_count, result.binary_count);
 
-        Ok(DiffResult {
-            left_path: self.left_path.clone(),
-            right_path: self.right_path.clone(),
-            tree,
-            total_files,
-            added_count,
-            removed_count,
-            modified_count,
-        })
+        Ok(result)
     }
 
-    pub fn analyze_with_progress<F>(&self, mut progress_callback: F) -> Result<DiffResult>
+    pub fn analyze_with_progress<F>(&self, progress_callback: F) -> Result<DiffResult>
     where
         F: FnMut(usize, usize) + Send + Sync,
     {
         let start_time = Instant::now();
-        println!("🔍 Analyzing directories with progress tracking...");
+        eprintln!("🔍 Analyzing directories with progress tracking...");
         
-        // Use a custom tree builder that reports progress
-        let tree_builder = FileTreeBuilder::new_with_options(
-            self.left_path.clone(), 
-            self.right_path.clone(),
-            self.include_ignored
-        );
-        let tree = tree_builder.build()?;
+        // The tree builder reports (compared, total) while file statuses are computed
+        let tree_builder = self.tree_builder();
+        let mut tree = tree_builder.build_with_progress(progress_callback)?;
+        self.fill_line_summaries(&mut tree);
         
-        let (total_files, added_count, removed_count, modified_count) = 
-            Self::count_file_stats(&tree);
+        let result = self.result_for(tree);
+
+        let duration = start_time.elapsed();
+        eprintln!("✅ Analysis complete! {} files processed in {:.2}s", 
+                result.total_files, duration.as_secs_f64());
+
+        self.apply_diff_filter(result)
+    }
+
+    /// Like [`DiffyCore::analyze`], but compare files `batch_size` at a time
+    /// (see [`tree::DEFAULT_BATCH_SIZE`]) and merge the partial trees, so the
+    /// details gathered while comparing never cover more than one batch. Meant
+    /// for repositories with millions of files.
+    pub fn analyze_batched(&self, batch_size: usize) -> Result<DiffResult> {
+        let start_time = Instant::now();
+        eprintln!("🔍 Analyzing directories in batches of {}...", batch_size);
+
+        let mut tree: Option<FileEntry> = None;
+        let mut batches = 0;
+        self.tree_builder().build_batched(batch_size, |partial| {
+            batches += 1;
+            match &mut tree {
+                Some(tree) => Self::merge_batch(tree, partial.tree),
+                None => tree = Some(partial.tree),
+            }
+        })?;
+        let mut tree = tree.expect("build_batched reports at least one batch");
+        self.fill_line_summaries(&mut tree);
 
-        progress_callback(total_files, total_files);
+        let result = self.result_for(tree);
 
         let duration = start_time.elapsed();
-        println!("✅ Analysis complete! {} files processed in {:.2}s", 
-                total_files, duration.as_secs_f64());
+        eprintln!("✅ Analysis complete! {} files processed in {} batch(es) in {:.2}s",
+                result.total_files, batches, duration.as_secs_f64());
+
+        self.apply_diff_filter(result)
+    }
+
+    /// Add the entries of `from` to `into`, descending into directories both
+    /// trees contain
+    fn merge_batch(into: &mut FileEntry, from: FileEntry) {
+        let dirs: HashMap<PathBuf, usize> = into.children
+            .iter()
+            .enumerate()
+            .filter(|(_, child)| child.is_directory)
+            .map(|(index, child)| (child.relative_path.clone(), index))
+            .collect();
+
+        for child in from.children {
+            match dirs.get(&child.relative_path) {
+                Some(&index) if child.is_directory => Self::merge_batch(&mut into.children[index], child),
+                _ => into.children.push(child),
+            }
+        }
+        into.children.sort_by(FileEntry::sort_by_name());
+    }
+
+    /// Analyze, then keep only the files `filter` allows, with their parent
+    /// directories. Counts cover the kept files only. Renames are detected
+    /// with [`DEFAULT_RENAME_THRESHOLD`] when the filter treats them
+    /// differently from added or deleted files.
+    pub fn analyze_filtered(&self, filter: &DiffFilter) -> Result<DiffResult> {
+        self.filter_result(self.analyze_unfiltered()?, filter)
+    }
+
+    fn apply_diff_filter(&self, result: DiffResult) -> Result<DiffResult> {
+        match &self.diff_filter {
+            Some(filter) => self.filter_result(result, filter),
+            None => Ok(result),
+        }
+    }
+
+    fn filter_result(&self, result: DiffResult, filter: &DiffFilter) -> Result<DiffResult> {
+        let mut renamed = HashSet::new();
+        if filter.needs_renames() {
+            for rename in self.detect_renames(&result, DEFAULT_RENAME_THRESHOLD)? {
+                renamed.insert(rename.from);
+                renamed.insert(rename.to);
+            }
+        }
+
+        let mut kept = Vec::new();
+        result.tree.visit(&mut |entry| {
+            let code = DiffFilter::code_of(&entry.status, renamed.contains(&entry.relative_path));
+            if !entry.is_directory && filter.allows(code) {
+                kept.push(entry.relative_path.clone());
+            }
+        });
+        Self::analyze_file_range(&result, &kept)
+    }
+
+    /// Re-analyze after a filesystem change, rescanning only the subtree at the
+    /// nearest common parent of `changed_paths` and reusing the rest of `previous`.
+    pub fn incremental_analyze(&self, previous: &DiffResult, changed_paths: &[PathBuf]) -> Result<DiffResult> {
+        if changed_paths.is_empty() {
+            return Ok(previous.clone());
+        }
+
+        let relative_dirs: Vec<PathBuf> = changed_paths
+            .iter()
+            .map(|path| self.changed_dir_for(path))
+            .collect();
+
+        let mut subtree_path = Self::common_parent(&relative_dirs);
+
+        // Walk up until we reach a directory the previous tree already knows about
+        while !subtree_path.as_os_str().is_empty()
+            && !Self::find_entry(&previous.tree, &subtree_path).is_some_and(|entry| entry.is_directory)
+        {
+            subtree_path = subtree_path.parent().map(Path::to_path_buf).unwrap_or_default();
+        }
+
+        let tree_builder = self.tree_builder();
+        let mut tree = tree_builder.rebuild_subtree(&previous.tree, &subtree_path)?;
+        self.fill_line_summaries(&mut tree);
+
+        self.apply_diff_filter(self.result_for(tree))
+    }
+
+    /// Scope an existing result to `subdir` without rescanning. Paths in the new
+    /// result are relative to the subdirectory on each side.
+    pub fn analyze_subdirectory(result: &DiffResult, subdir: &Path) -> Result<DiffResult> {
+        let entry = Self::find_entry(&result.tree, subdir)
+            .ok_or_else(|| anyhow::anyhow!("'{}' is not in the diff result", subdir.display()))?;
+        if !entry.is_directory {
+            anyhow::bail!("'{}' is not a directory", subdir.display());
+        }
+
+        let mut tree = entry.clone();
+        Self::strip_prefix_recursive(&mut tree, subdir);
 
         Ok(DiffResult {
-            left_path: self.left_path.clone(),
-            right_path: self.right_path.clone(),
-            tree,
-            total_files,
-            added_count,
-            removed_count,
-            modified_count,
+            description: result.description.clone(),
+            ..Self::result_for_tree(result.left_path.join(subdir), result.right_path.join(subdir), tree)
+        })
+    }
+
+    /// Restrict an existing result to `paths` (files, or directories whose whole
+    /// subtree is kept) without rescanning. Ancestor directories are retained so
+    /// the tree shape is preserved.
+    pub fn analyze_file_range(result: &DiffResult, paths: &[PathBuf]) -> Result<DiffResult> {
+        let wanted: HashSet<&Path> = paths.iter().map(PathBuf::as_path).collect();
+        let mut found = HashSet::new();
+
+        let tree = Self::filter_entry(&result.tree, &wanted, &mut found)
+            .unwrap_or_else(|| Self::with_children(&result.tree, Vec::new()));
+
+        let missing: Vec<String> = paths
+            .iter()
+            .filter(|path| !found.contains(path.as_path()))
+            .map(|path| path.display().to_string())
+            .collect();
+        if !missing.is_empty() {
+            anyhow::bail!("Paths not in the diff result: {}", missing.join(", "));
+        }
+
+        Ok(DiffResult {
+            description: result.description.clone(),
+            ..Self::result_for_tree(result.left_path.clone(), result.right_path.clone(), tree)
         })
     }
 
+    /// A result for `tree` under this core's roots, counted and described
+    fn result_for(&self, tree: FileEntry) -> DiffResult {
+        DiffResult {
+            description: self.description.clone(),
+            ..Self::result_for_tree(self.left_path.clone(), self.right_path.clone(), tree)
+        }
+    }
+
+    fn result_for_tree(left_path: PathBuf, right_path: PathBuf, tree: FileEntry) -> DiffResult {
+        let counts = Self::count_file_stats(&tree);
+
+        DiffResult {
+            left_path,
+            right_path,
+            tree,
+            total_files: counts.total,
+            added_count: counts.added,
+            removed_count: counts.removed,
+            modified_count: counts.modified,
+            conflict_count: counts.conflicted,
+            unchanged_count: counts.unchanged,
+            binary_count: counts.binary,
+            description: None,
+        }
+    }
+
+    fn find_entry<'a>(entry: &'a FileEntry, relative_path: &Path) -> Option<&'a FileEntry> {
+        if entry.relative_path == relative_path {
+            return Some(entry);
+        }
+
+        entry.children
+            .iter()
+            .filter(|child| relative_path.starts_with(&child.relative_path))
+            .find_map(|child| Self::find_entry(child, relative_path))
+    }
+
+    fn strip_prefix_recursive(entry: &mut FileEntry, prefix: &Path) {
+        entry.relative_path = entry.relative_path
+            .strip_prefix(prefix)
+            .map(Path::to_path_buf)
+            .unwrap_or_default();
+        entry.path = entry.relative_path.clone();
+
+        for child in &mut entry.children {
+            Self::strip_prefix_recursive(child, prefix);
+        }
+    }
+
+    fn filter_entry<'a>(
+        entry: &'a FileEntry,
+        wanted: &HashSet<&Path>,
+        found: &mut HashSet<&'a Path>,
+    ) -> Option<FileEntry> {
+        if wanted.contains(entry.relative_path.as_path()) {
+            found.insert(entry.relative_path.as_path());
+            return Some(entry.clone());
+        }
+
+        let children: Vec<FileEntry> = entry.children
+            .iter()
+            .filter_map(|child| Self::filter_entry(child, wanted, found))
+            .collect();
+
+        if children.is_empty() {
+            None
+        } else {
+            Some(Self::with_children(entry, children))
+        }
+    }
+
+    /// Diff every changed file that has no line summary yet, when enabled in
+    /// `diff_options`. Entries reused by incremental analysis keep their summary.
+    /// Files that can't be diffed, e.g. because they are unreadable, are left
+    /// without one rather than failing the analysis.
+    fn fill_line_summaries(&self, entry: &mut FileEntry) {
+        if !self.diff_options.compute_line_stats {
+            return;
+        }
+
+        if !entry.is_directory {
+            if entry.status != DiffStatus::Unchanged && entry.line_summary.is_none() {
+                let Ok(diff) = self.get_file_diff(&entry.relative_path) else {
+                    return;
+                };
+                if !diff.is_binary {
+                    entry.line_summary = Some(LineSummary {
+                        additions: diff.total_additions() as u32,
+                        deletions: diff.total_deletions() as u32,
+                    });
+                    if entry.status == DiffStatus::Modified {
+                        entry.similarity = Some(diff.similarity);
+                    }
+                }
+            }
+            return;
+        }
+
+        entry.children
+            .par_iter_mut()
+            .for_each(|child| self.fill_line_summaries(child));
+    }
+
+    /// Copy an entry's own fields without cloning its existing children
+    fn with_children(entry: &FileEntry, children: Vec<FileEntry>) -> FileEntry {
+        FileEntry {
+            path: entry.path.clone(),
+            relative_path: entry.relative_path.clone(),
+            is_directory: entry.is_directory,
+            status: entry.status.clone(),
+            size: entry.size,
+            children,
+            line_summary: entry.line_summary,
+            similarity: entry.similarity,
+            is_binary: entry.is_binary,
+            left_metadata: entry.left_metadata.clone(),
+            right_metadata: entry.right_metadata.clone(),
+        }
+    }
+
+    /// Map a changed path (absolute under either root, or already relative) to the
+    /// relative directory that needs rescanning.
+    fn changed_dir_for(&self, path: &Path) -> PathBuf {
+        let relative = path
+            .strip_prefix(&self.left_path)
+            .or_else(|_| path.strip_prefix(&self.right_path))
+            .unwrap_or(path)
+            .to_path_buf();
+
+        let is_dir = self.left_path.join(&relative).is_dir() || self.right_path.join(&relative).is_dir();
+        if is_dir {
+            relative
+        } else {
+            relative.parent().map(Path::to_path_buf).unwrap_or_default()
+        }
+    }
+
+    fn common_parent(paths: &[PathBuf]) -> PathBuf {
+        let mut common = paths[0].clone();
+        for path in &paths[1..] {
+            while !path.starts_with(&common) {
+                if !common.pop() {
+                    return PathBuf::new();
+                }
+            }
+        }
+        common
+    }
+
+
     pub fn get_file_diff(&self, relative_path: &std::path::Path) -> Result<crate::core::types::FileDiff> {
-        let diff_engine = DiffEngine::new();
-        let left_file = self.left_path.join(relative_path);
-        let right_file = self.right_path.join(relative_path);
+        let diff_engine = DiffEngine::with_options(self.diff_options.clone())
+            .with_content_store(self.content_store.clone());
+        let (left_file, right_file) = self.side_paths(relative_path);
         
-        diff_engine.diff_files(&left_file, &right_file)
+        // Oversized files get a placeholder diff instead of an error
+        match diff_engine.diff_files(&left_file, &right_file) {
+            Err(e) if matches!(e.downcast_ref(), Some(DiffyError::FileTooLarge { .. })) => {
+                Ok(DiffEngine::too_large_diff(&left_file, &right_file))
+            }
+            result => result,
+        }
     }
 
-    fn count_file_stats(entry: &FileEntry) -> (usize, usize, usize, usize) {
+    /// Pair removed files with added files whose text is more than `threshold`
+    /// similar (see `FileDiff::similarity`), most similar pairs first. Each
+    /// file is used in at most one pair. Binary files and files over
+    /// `DiffOptions::max_file_size` are never considered renamed.
+    pub fn detect_renames(&self, result: &DiffResult, threshold: f32) -> Result<Vec<RenamedFile>> {
+        let mut removed = Vec::new();
+        let mut added = Vec::new();
+        result.tree.visit(&mut |entry| {
+            if entry.is_directory || entry.is_binary {
+                return;
+            }
+            match entry.status {
+                DiffStatus::Removed => removed.push(entry.relative_path.clone()),
+                DiffStatus::Added => added.push(entry.relative_path.clone()),
+                _ => {}
+            }
+        });
+        if removed.is_empty() || added.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let limit = self.diff_options.max_file_size.unwrap_or(u64::MAX);
+        let ignore_blank_lines = self.diff_options.ignore_blank_lines;
+        // The text and compared line count of each candidate
+        let load = |relative_path: PathBuf, path: PathBuf| -> Result<Option<(PathBuf, Arc<String>, usize)>> {
+            if std::fs::metadata(&path)?.len() > limit || DiffEngine::is_binary_file(&path)? {
+                return Ok(None);
+            }
+            let content = self.content_store.load(&path)?;
+            let lines = content.lines().filter(|line| !ignore_blank_lines || !line.trim().is_empty()).count();
+            Ok(Some((relative_path, content, lines)))
+        };
+        let removed: Vec<_> = removed
+            .into_par_iter()
+            .filter_map(|path| load(path.clone(), self.side_paths(&path).0).transpose())
+            .collect::<Result<_>>()?;
+        let added: Vec<_> = added
+            .into_par_iter()
+            .filter_map(|path| load(path.clone(), self.side_paths(&path).1).transpose())
+            .collect::<Result<_>>()?;
+
+        let engine = DiffEngine::with_options(self.diff_options.clone());
+        let mut candidates: Vec<RenamedFile> = added
+            .par_iter()
+            .flat_map_iter(|(to, new_content, new_lines)| {
+                let engine = &engine;
+                removed.iter().filter_map(move |(from, old_content, old_lines)| {
+                    // At least the difference in length has to change, so skip
+                    // pairs that cannot reach the threshold without diffing them
+                    let best_case = 1.0 - old_lines.abs_diff(*new_lines) as f32 / (old_lines + new_lines).max(1) as f32;
+                    if best_case <= threshold {
+                        return None;
+                    }
+                    let similarity = engine.diff_strings(old_content, new_content).similarity;
+                    (similarity > threshold).then(|| RenamedFile { from: from.clone(), to: to.clone(), similarity })
+                })
+            })
+            .collect();
+        candidates.sort_by(|a, b| b.similarity.total_cmp(&a.similarity).then_with(|| a.to.cmp(&b.to)));
+
+        let mut used_from = HashSet::new();
+        let mut used_to = HashSet::new();
+        candidates.retain(|rename| {
+            if used_from.contains(&rename.from) || used_to.contains(&rename.to) {
+                return false;
+            }
+            used_from.insert(rename.from.clone());
+            used_to.insert(rename.to.clone());
+            true
+        });
+        Ok(candidates)
+    }
+
+    /// Filesystem metadata of `relative_path` on each side, `None` where it is missing
+    pub fn get_file_metadata(&self, relative_path: &Path) -> (Option<FileMetadata>, Option<FileMetadata>) {
+        let (left_file, right_file) = self.side_paths(relative_path);
+        (FileMetadata::read(&left_file).ok(), FileMetadata::read(&right_file).ok())
+    }
+
+    /// Each line of `relative_path` with the commit that last changed it. The
+    /// right side is used, or the left if the file was removed. Commit details
+    /// come from the git repository at `git_repo`, or the one containing the
+    /// file; outside a repository only the content is filled in.
+    pub fn get_file_blame(&self, relative_path: &Path, git_repo: Option<&Path>) -> Result<Vec<BlameLine>> {
+        let (left_file, right_file) = self.side_paths(relative_path);
+        let file = if right_file.is_file() { right_file } else { left_file };
+        let content = std::fs::read_to_string(&file)
+            .map_err(|e| anyhow::anyhow!("Failed to read {}: {}", file.display(), e))?;
+
+        let mut commits = git::blame(&file, &content, git_repo).unwrap_or_default().into_iter();
+        Ok(content
+            .lines()
+            .enumerate()
+            .map(|(index, line)| {
+                let commit = commits.next().flatten();
+                BlameLine {
+                    line_number: index as u32 + 1,
+                    commit_hash: commit.as_ref().map(|commit| commit.hash.clone()),
+                    author: commit.as_ref().and_then(|commit| commit.author.clone()),
+                    date: commit.map(|commit| commit.date),
+                    content: line.to_string(),
+                }
+            })
+            .collect())
+    }
+
+    /// Full paths of `relative_path` under both roots
+    fn side_paths(&self, relative_path: &Path) -> (PathBuf, PathBuf) {
+        if self.case_insensitive {
+            (
+                tree::resolve_case_insensitive(&self.left_path, relative_path),
+                tree::resolve_case_insensitive(&self.right_path, relative_path),
+            )
+        } else {
+            (self.left_path.join(relative_path), self.right_path.join(relative_path))
+        }
+    }
+
+    /// Diff two individual files directly, without building a tree
+    pub fn diff_two_files(left: PathBuf, right: PathBuf) -> Result<crate::core::types::FileDiff> {
+        DiffEngine::new().diff_files(&left, &right)
+    }
+
+    /// Render `result` in the given format, diffing every changed file
+    pub fn export(&self, result: &DiffResult, format: ExportFormat) -> Result<String> {
+        format.render(result, &self.exported_files(result)?)
+    }
+
+    /// Write a static report of `result` into `dir` (see `export::write_report`),
+    /// returning the path of its `index.html`
+    pub fn export_report(&self, result: &DiffResult, dir: &Path) -> Result<PathBuf> {
+        export::write_report(dir, result, &self.exported_files(result)?)
+    }
+
+    fn exported_files(&self, result: &DiffResult) -> Result<Vec<ExportedFile>> {
+        export::changed_files(&result.tree)
+            .into_par_iter()
+            .map(|(path, status)| {
+                let diff = self.get_file_diff(&path)?;
+                Ok(ExportedFile { path, status, diff })
+            })
+            .collect()
+    }
+
+    /// Added files in `result` matching any of the gitignore-syntax
+    /// `patterns`, such as `.env` or `*.pem`, used to stop sensitive files
+    /// from being committed
+    pub fn guarded_files(&self, result: &DiffResult, patterns: &[String]) -> Result<Vec<PathBuf>> {
+        let mut builder = ignore::gitignore::GitignoreBuilder::new("");
+        for pattern in patterns {
+            builder
+                .add_line(None, pattern)
+                .map_err(|e| anyhow::anyhow!("Invalid guard pattern '{}': {}", pattern, e))?;
+        }
+        let guard = builder.build()?;
+
+        let mut guarded = Vec::new();
+        result.tree.visit(&mut |entry| {
+            if !entry.is_directory
+                && entry.status == DiffStatus::Added
+                && guard.matched_path_or_any_parents(&entry.relative_path, false).is_ignore()
+            {
+                guarded.push(entry.relative_path.clone());
+            }
+        });
+        Ok(guarded)
+    }
+
+    /// File counts from `result` plus line totals across every changed file
+    pub fn statistics(&self, result: &DiffResult) -> Result<DiffStatistics> {
+        let (lines_added, lines_removed) = export::changed_files(&result.tree)
+            .into_par_iter()
+            .map(|(path, _)| {
+                let diff = self.get_file_diff(&path)?;
+                Ok((diff.total_additions(), diff.total_deletions()))
+            })
+            .collect::<Result<Vec<_>>>()?
+            .into_iter()
+            .fold((0, 0), |(added, removed), (a, r)| (added + a, removed + r));
+
+        Ok(DiffStatistics {
+            total_files: result.total_files,
+            added_count: result.added_count,
+            removed_count: result.removed_count,
+            modified_count: result.modified_count,
+            unchanged_count: result.unchanged_count,
+            binary_count: result.binary_count,
+            lines_added,
+            lines_removed,
+        })
+    }
+
+    /// A short plain-English summary of `result`, such as "47 files were
+    /// compared. 5 files were added (total 312 lines), 2 files were deleted,
+    /// and 12 files were modified. The largest change was in `src/lib.rs`
+    /// (+89/-34 lines)." Diffs every changed file, and mentions renames found
+    /// with [`DEFAULT_RENAME_THRESHOLD`]. Files that can no longer be diffed
+    /// are left out of the line counts instead of failing the summary.
+    pub fn explain(&self, result: &DiffResult) -> String {
+        let changed: Vec<_> = export::changed_files(&result.tree)
+            .into_par_iter()
+            .filter_map(|(path, status)| {
+                let diff = self.get_file_diff(&path).ok()?;
+                Some((path, status, diff.total_additions(), diff.total_deletions()))
+            })
+            .collect();
+
+        let mut sentences = vec![format!("{} compared.", files_were(result.total_files))];
+
+        let added_lines: usize = changed
+            .iter()
+            .filter(|(_, status, _, _)| *status == DiffStatus::Added)
+            .map(|(_, _, additions, _)| additions)
+            .sum();
+        let mut changes = Vec::new();
+        if result.added_count > 0 {
+            changes.push(format!("{} added (total {})", files_were(result.added_count), plural(added_lines, "line")));
+        }
+        if result.removed_count > 0 {
+            changes.push(format!("{} deleted", files_were(result.removed_count)));
+        }
+        if result.modified_count > 0 {
+            changes.push(format!("{} modified", files_were(result.modified_count)));
+        }
+        if result.conflict_count > 0 {
+            changes.push(format!("{} left with merge conflicts", files_were(result.conflict_count)));
+        }
+        sentences.push(match changes.as_slice() {
+            [] => "No files changed.".to_string(),
+            [only] => format!("{}.", only),
+            [first, second] => format!("{} and {}.", first, second),
+            [rest @ .., last] => format!("{}, and {}.", rest.join(", "), last),
+        });
+
+        // Earlier paths win ties, so the summary is stable
+        let largest = changed
+            .iter()
+            .filter(|(_, status, additions, deletions)| {
+                matches!(status, DiffStatus::Modified | DiffStatus::Conflicted) && additions + deletions > 0
+            })
+            .max_by(|a, b| (a.2 + a.3).cmp(&(b.2 + b.3)).then_with(|| b.0.cmp(&a.0)));
+        if let Some((path, _, additions, deletions)) = largest {
+            sentences.push(format!(
+                "The largest change was in `{}` (+{}/-{} lines).",
+                path.display(), additions, deletions
+            ));
+        }
+
+        let renames = self.detect_renames(result, DEFAULT_RENAME_THRESHOLD).unwrap_or_default();
+        if let Some(lowest) = renames.iter().map(|rename| rename.similarity).reduce(f32::min) {
+            let percent = (lowest * 100.0).floor();
+            sentences.push(if renames.len() == 1 {
+                format!("1 file was renamed with {}% similarity.", percent)
+            } else {
+                format!("{} files were renamed with at least {}% similarity.", renames.len(), percent)
+            });
+        }
+
+        sentences.join(" ")
+    }
+
+    fn count_file_stats(entry: &FileEntry) -> FileCounts {
         // Use parallel counting for large trees
-        let (total_files, added_count, removed_count, modified_count) = 
-            Self::count_recursive_parallel(entry);
-        
-        (total_files, added_count, removed_count, modified_count)
+        Self::count_recursive_parallel(entry)
     }
 
-    fn count_recursive_parallel(entry: &FileEntry) -> (usize, usize, usize, usize) {
-        let mut total_files = 0;
-        let mut added_count = 0;
-        let mut removed_count = 0;
-        let mut modified_count = 0;
+    fn count_recursive_parallel(entry: &FileEntry) -> FileCounts {
+        let mut counts = FileCounts::default();
 
         if !entry.is_directory {
-            total_files = 1;
-            match entry.status {
-                DiffStatus::Added => added_count = 1,
-                DiffStatus::Removed => removed_count = 1,
-                DiffStatus::Modified => modified_count = 1,
-                _ => {}
+            counts.total = 1;
+            if entry.is_binary {
+                counts.binary = 1;
+            } else {
+                match entry.status {
+                    DiffStatus::Added => counts.added = 1,
+                    DiffStatus::Removed => counts.removed = 1,
+                    DiffStatus::Modified => counts.modified = 1,
+                    DiffStatus::Conflicted => counts.conflicted = 1,
+                    DiffStatus::Unchanged => counts.unchanged = 1,
+                }
             }
         }
 
         if !entry.children.is_empty() {
             // For directories with many children, use parallel processing
             if entry.children.len() > 10 {
-                let results: Vec<(usize, usize, usize, usize)> = entry.children
+                counts = entry.children
                     .par_iter()
-                    .map(|child| Self::count_recursive_parallel(child))
-                    .collect();
-
-                for (t, a, r, m) in results {
-                    total_files += t;
-                    added_count += a;
-                    removed_count += r;
-                    modified_count += m;
-                }
+                    .map(Self::count_recursive_parallel)
+                    .reduce(|| counts, FileCounts::add);
             } else {
                 // For small directories, use sequential processing to avoid overhead
                 for child in &entry.children {
-                    let (t, a, r, m) = Self::count_recursive_parallel(child);
-                    total_files += t;
-                    added_count += a;
-                    removed_count += r;
-                    modified_count += m;
+                    counts = counts.add(Self::count_recursive_parallel(child));
                 }
             }
         }
 
-        (total_files, added_count, removed_count, modified_count)
+        counts
     }
-}
\ No newline at end of file
+}
+
+/// "1 file was" or "N files were"
+fn files_were(count: usize) -> String {
+    if count == 1 { "1 file was".to_string() } else { format!("{} files were", count) }
+}
+
+fn plural(count: usize, noun: &str) -> String {
+    if count == 1 { format!("1 {}", noun) } else { format!("{} {}s", count, noun) }
+}
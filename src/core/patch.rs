@@ -0,0 +1,172 @@
+//! Applies a unified diff to a directory, as done by `diffy apply`.
+
+use crate::core::diff::DiffEngine;
+use crate::core::types::{DiffLineKind, ParsedFileDiff};
+use anyhow::{Context, Result};
+use std::path::{Component, Path, PathBuf};
+
+/// What a patch does to one file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PatchAction {
+    Create(String),
+    Modify(String),
+    Delete,
+}
+
+/// One file changed by a patch, relative to the target directory
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PatchedFile {
+    pub path: PathBuf,
+    pub action: PatchAction,
+    pub additions: usize,
+    pub deletions: usize,
+}
+
+/// The result of applying a patch in memory. Nothing is written until
+/// [`PatchPlan::write`] is called, so a patch with conflicts can be rejected
+/// as a whole.
+#[derive(Debug, Default)]
+pub struct PatchPlan {
+    pub files: Vec<PatchedFile>,
+    /// Files the patch does not apply to, with the reason
+    pub conflicts: Vec<(PathBuf, String)>,
+}
+
+impl PatchPlan {
+    /// Work out the new contents of every file `patch` touches below
+    /// `target_dir`. With `reverse` the patch is undone instead. Fails only if
+    /// the patch cannot be parsed; hunks that do not apply are collected in
+    /// `conflicts`.
+    pub fn prepare(patch: &str, target_dir: &Path, reverse: bool) -> Result<Self> {
+        let mut plan = PatchPlan::default();
+        for file in DiffEngine::parse_unified_diff(patch)? {
+            let (from, to) = if reverse {
+                (&file.new_path, &file.old_path)
+            } else {
+                (&file.old_path, &file.new_path)
+            };
+            let (from, to) = match (patch_path(from), patch_path(to)) {
+                (Ok(from), Ok(to)) => (from, to),
+                (Err(e), _) | (_, Err(e)) => {
+                    plan.conflicts.push((to.clone(), e.to_string()));
+                    continue;
+                }
+            };
+            let Some(path) = to.clone().or_else(|| from.clone()) else { continue };
+
+            if file.is_binary {
+                plan.conflicts.push((path, "binary changes cannot be applied".to_string()));
+                continue;
+            }
+
+            match plan_file(&file, from.as_deref(), to.as_deref(), target_dir, reverse) {
+                Ok(patched) => plan.files.extend(patched),
+                Err(e) => plan.conflicts.push((path, e.to_string())),
+            }
+        }
+        Ok(plan)
+    }
+
+    pub fn is_clean(&self) -> bool {
+        self.conflicts.is_empty()
+    }
+
+    /// Write the planned changes below `target_dir`, creating directories for
+    /// new files as needed
+    pub fn write(&self, target_dir: &Path) -> Result<()> {
+        for file in &self.files {
+            let path = target_dir.join(&file.path);
+            match &file.action {
+                PatchAction::Create(content) | PatchAction::Modify(content) => {
+                    if let Some(parent) = path.parent() {
+                        std::fs::create_dir_all(parent)?;
+                    }
+                    std::fs::write(&path, content)
+                }
+                PatchAction::Delete => std::fs::remove_file(&path),
+            }
+            .with_context(|| format!("Failed to update {}", path.display()))?;
+        }
+        Ok(())
+    }
+}
+
+/// The changes for one file section, going from the `from` path to `to`
+/// (either may be `None` for `/dev/null`)
+fn plan_file(
+    file: &ParsedFileDiff,
+    from: Option<&Path>,
+    to: Option<&Path>,
+    target_dir: &Path,
+    reverse: bool,
+) -> Result<Vec<PatchedFile>> {
+    let apply = |source: &str| {
+        if reverse {
+            DiffEngine::apply_patch_reversed(source, &file.hunks)
+        } else {
+            DiffEngine::apply_patch(source, &file.hunks)
+        }
+    };
+    let count = |kind: DiffLineKind| {
+        file.hunks.iter().flat_map(|hunk| &hunk.lines).filter(|line| line.kind == kind).count()
+    };
+    let (additions, deletions) = if reverse {
+        (count(DiffLineKind::Deletion), count(DiffLineKind::Addition))
+    } else {
+        (count(DiffLineKind::Addition), count(DiffLineKind::Deletion))
+    };
+    let patched = |path: &Path, action| PatchedFile { path: path.to_path_buf(), action, additions, deletions };
+
+    let original = match from {
+        Some(from) => {
+            let path = target_dir.join(from);
+            if !path.is_file() {
+                anyhow::bail!("{} does not exist", path.display());
+            }
+            std::fs::read_to_string(&path).with_context(|| format!("Failed to read {}", path.display()))?
+        }
+        None => String::new(),
+    };
+    let content = apply(&original)?;
+
+    match (from, to) {
+        (None, None) => Ok(Vec::new()),
+        (Some(from), None) => {
+            if !content.is_empty() {
+                anyhow::bail!("The file is not empty after removing the patched lines");
+            }
+            Ok(vec![patched(from, PatchAction::Delete)])
+        }
+        (None, Some(to)) => {
+            if target_dir.join(to).exists() {
+                anyhow::bail!("{} already exists", target_dir.join(to).display());
+            }
+            Ok(vec![patched(to, PatchAction::Create(content))])
+        }
+        (Some(from), Some(to)) if from != to => Ok(vec![
+            patched(to, PatchAction::Create(content)),
+            PatchedFile { path: from.to_path_buf(), action: PatchAction::Delete, additions: 0, deletions: 0 },
+        ]),
+        (Some(_), Some(to)) => Ok(vec![patched(to, PatchAction::Modify(content))]),
+    }
+}
+
+/// A header path relative to the target directory, without the `a/` or `b/`
+/// prefix; `None` for `/dev/null`. Paths that could escape the target
+/// directory are rejected.
+fn patch_path(header: &Path) -> Result<Option<PathBuf>> {
+    if header == Path::new("/dev/null") {
+        return Ok(None);
+    }
+
+    let stripped = header
+        .strip_prefix("a")
+        .or_else(|_| header.strip_prefix("b"))
+        .unwrap_or(header);
+    if stripped.as_os_str().is_empty()
+        || stripped.components().any(|component| !matches!(component, Component::Normal(_)))
+    {
+        anyhow::bail!("{} is outside the target directory", header.display());
+    }
+    Ok(Some(stripped.to_path_buf()))
+}
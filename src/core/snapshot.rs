@@ -0,0 +1,178 @@
+//! Finds snapshots, the timestamped or numbered copies of a directory kept
+//! side by side in one parent directory.
+
+use crate::core::types::SnapshotInfo;
+use anyhow::{Context, Result};
+use std::cmp::Ordering;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+/// Every subdirectory of `snapshots_dir`, oldest first by name: numbered
+/// snapshots compare by their number, others alphabetically, which orders
+/// timestamps like `20240101-120000` correctly
+pub(crate) fn list(snapshots_dir: &Path) -> Result<Vec<SnapshotInfo>> {
+    let entries = std::fs::read_dir(snapshots_dir)
+        .with_context(|| format!("Failed to read snapshot directory {}", snapshots_dir.display()))?;
+
+    let mut snapshots = Vec::new();
+    for entry in entries {
+        let entry = entry?;
+        if !entry.file_type()?.is_dir() {
+            continue;
+        }
+        let Some(name) = entry.file_name().to_str().map(str::to_string) else { continue };
+        let metadata = entry.metadata()?;
+        snapshots.push(SnapshotInfo {
+            name,
+            created: metadata.created().or_else(|_| metadata.modified()).ok(),
+            file_count: count_files(&entry.path()),
+        });
+    }
+    snapshots.sort_by(|a, b| compare_names(&a.name, &b.name));
+    Ok(snapshots)
+}
+
+/// The snapshot called `name`, or else the only one whose name starts with
+/// it. Separators are ignored when matching prefixes, so `2024-01-15` finds
+/// `20240115-094500`.
+pub(crate) fn find<'s>(snapshots: &'s [SnapshotInfo], name: &str) -> Result<&'s SnapshotInfo> {
+    if let Some(exact) = snapshots.iter().find(|snapshot| snapshot.name == name) {
+        return Ok(exact);
+    }
+
+    let prefix = normalize(name);
+    let matches: Vec<_> = snapshots
+        .iter()
+        .filter(|snapshot| !prefix.is_empty() && normalize(&snapshot.name).starts_with(&prefix))
+        .collect();
+    match matches.as_slice() {
+        [snapshot] => Ok(snapshot),
+        [] => anyhow::bail!("No snapshot matches '{}'", name),
+        _ => {
+            let names: Vec<_> = matches.iter().map(|snapshot| snapshot.name.as_str()).collect();
+            anyhow::bail!("'{}' matches several snapshots: {}", name, names.join(", "))
+        }
+    }
+}
+
+/// Where snapshots of `path` are kept unless another directory is given:
+/// `$XDG_CACHE_HOME/diffy/snapshots/<directory name>`, falling back to
+/// `~/.cache/diffy/snapshots/<directory name>`
+pub(crate) fn default_dir(path: &Path) -> Result<PathBuf> {
+    let cache_home = std::env::var_os("XDG_CACHE_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".cache")))
+        .context("Neither XDG_CACHE_HOME nor HOME is set")?;
+    let path = path.canonicalize().with_context(|| format!("Failed to resolve {}", path.display()))?;
+    let name = path.file_name().context("Cannot snapshot the root directory")?;
+    Ok(cache_home.join("diffy").join("snapshots").join(name))
+}
+
+/// A snapshot name for `time`, such as `20240115-094500` (UTC)
+pub(crate) fn name_for(time: SystemTime) -> String {
+    let digits: String = humantime::format_rfc3339_seconds(time)
+        .to_string()
+        .chars()
+        .filter(char::is_ascii_digit)
+        .collect();
+    format!("{}-{}", &digits[..8], &digits[8..])
+}
+
+/// When the snapshot was taken: the time in its name when it was named by
+/// [`name_for`], otherwise its creation time
+pub(crate) fn taken_at(snapshot: &SnapshotInfo) -> Option<SystemTime> {
+    let name = snapshot.name.as_bytes();
+    let from_name = (name.len() == 15 && name[8] == b'-')
+        .then(|| {
+            let n = &snapshot.name;
+            format!("{}-{}-{}T{}:{}:{}Z", &n[..4], &n[4..6], &n[6..8], &n[9..11], &n[11..13], &n[13..15])
+        })
+        .and_then(|timestamp| humantime::parse_rfc3339(&timestamp).ok());
+    from_name.or(snapshot.created)
+}
+
+/// The latest snapshot taken at or before `time`, which shows the directory
+/// as it was then. When every snapshot is newer the earliest one is used
+/// instead, and the second value is `false`.
+pub(crate) fn at(snapshots: &[SnapshotInfo], time: SystemTime) -> Result<(&SnapshotInfo, bool)> {
+    let dated: Vec<_> = snapshots
+        .iter()
+        .filter_map(|snapshot| taken_at(snapshot).map(|taken| (snapshot, taken)))
+        .collect();
+    if let Some((snapshot, _)) = dated.iter().filter(|(_, taken)| *taken <= time).max_by_key(|(_, taken)| *taken) {
+        return Ok((snapshot, true));
+    }
+    match dated.iter().min_by_key(|(_, taken)| *taken) {
+        Some((snapshot, _)) => Ok((snapshot, false)),
+        None => anyhow::bail!("There are no snapshots to compare against"),
+    }
+}
+
+/// A point in time given as a duration before now (`24h`, `7d`) or as a
+/// date or date and time (`2023-01-15`, `2023-01-15 08:00:00`, UTC)
+pub(crate) fn parse_since(since: &str) -> Result<SystemTime> {
+    if let Ok(ago) = humantime::parse_duration(since) {
+        return SystemTime::now().checked_sub(ago).context("--since is too far in the past");
+    }
+    humantime::parse_rfc3339_weak(since)
+        .or_else(|_| humantime::parse_rfc3339_weak(&format!("{} 00:00:00", since)))
+        .map_err(|_| anyhow::anyhow!("Expected a duration like '24h' or '7d', or a date like '2023-01-15', got '{}'", since))
+}
+
+/// Copy `source` to `destination` the way `cp -al` does: directories are
+/// recreated and files hard-linked, falling back to a copy where linking
+/// fails, e.g. across filesystems
+pub(crate) fn save(source: &Path, destination: &Path) -> Result<()> {
+    if destination.exists() {
+        anyhow::bail!("Snapshot {} already exists", destination.display());
+    }
+
+    for entry in walkdir::WalkDir::new(source) {
+        let entry = entry?;
+        let target = destination.join(entry.path().strip_prefix(source)?);
+        let file_type = entry.file_type();
+        if file_type.is_dir() {
+            std::fs::create_dir_all(&target).with_context(|| format!("Failed to create {}", target.display()))?;
+        } else if file_type.is_symlink() {
+            copy_symlink(entry.path(), &target)?;
+        } else if std::fs::hard_link(entry.path(), &target).is_err() {
+            std::fs::copy(entry.path(), &target)
+                .with_context(|| format!("Failed to copy {} to {}", entry.path().display(), target.display()))?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(unix)]
+fn copy_symlink(link: &Path, target: &Path) -> Result<()> {
+    std::os::unix::fs::symlink(std::fs::read_link(link)?, target)
+        .with_context(|| format!("Failed to link {}", target.display()))
+}
+
+#[cfg(not(unix))]
+fn copy_symlink(link: &Path, target: &Path) -> Result<()> {
+    if link.is_file() {
+        std::fs::copy(link, target).with_context(|| format!("Failed to copy {}", link.display()))?;
+    }
+    Ok(())
+}
+
+fn normalize(name: &str) -> String {
+    name.chars().filter(|c| c.is_alphanumeric()).flat_map(char::to_lowercase).collect()
+}
+
+fn compare_names(a: &str, b: &str) -> Ordering {
+    match (a.parse::<u64>(), b.parse::<u64>()) {
+        (Ok(a), Ok(b)) => a.cmp(&b),
+        _ => a.cmp(b),
+    }
+}
+
+fn count_files(dir: &Path) -> usize {
+    ignore::WalkBuilder::new(dir)
+        .standard_filters(false)
+        .build()
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_type().is_some_and(|file_type| file_type.is_file()))
+        .count()
+}
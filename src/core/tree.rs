@@ -1,33 +1,185 @@
-use crate::core::types::{DiffStatus, FileEntry};
+use crate::core::diff::DiffEngine;
+use crate::core::types::{DiffResult, DiffStatus, FileEntry, FileMetadata};
+use crate::core::DiffyCore;
 use anyhow::Result;
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
 use rayon::prelude::*;
-use std::collections::{BTreeSet, HashMap};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::io::Read;
 use std::path::{Path, PathBuf};
-use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{mpsc, Arc, Mutex};
+use std::time::Duration;
+
+/// Per-directory ignore file, same syntax as .gitignore
+pub const DIFFYIGNORE_FILENAME: &str = ".diffyignore";
+
+/// How much of a file is scanned for merge conflict markers
+const CONFLICT_SCAN_BYTES: u64 = 64 * 1024;
+
+/// Directories with this name hold git metadata and are never walked
+const GIT_DIR_NAME: &str = ".git";
+
+/// Paths compared per batch by `DiffyCore::analyze_batched` unless another
+/// size is given
+pub const DEFAULT_BATCH_SIZE: usize = 10_000;
+
+/// Resolve `relative_path` under `root`, matching each component that does not
+/// exist as spelled against the directory's entries regardless of case
+pub fn resolve_case_insensitive(root: &Path, relative_path: &Path) -> PathBuf {
+    let mut resolved = root.to_path_buf();
+    for component in relative_path.components() {
+        let exact = resolved.join(component);
+        if exact.exists() {
+            resolved = exact;
+            continue;
+        }
+
+        let wanted = case_fold_key(Path::new(component.as_os_str()));
+        let matched = std::fs::read_dir(&resolved).ok().and_then(|entries| {
+            entries
+                .flatten()
+                .map(|entry| entry.file_name())
+                .find(|name| case_fold_key(Path::new(name)) == wanted)
+        });
+        resolved.push(matched.unwrap_or_else(|| component.as_os_str().to_os_string()));
+    }
+    resolved
+}
+
+/// Metadata for a file with more than one hard link, or `None` for a file
+/// that cannot share its inode with anything else
+fn hardlink_metadata(path: &Path) -> Option<FileMetadata> {
+    FileMetadata::read(path)
+        .ok()
+        .filter(|metadata| metadata.nlinks.is_some_and(|nlinks| nlinks > 1))
+}
+
+fn case_fold_key(path: &Path) -> PathBuf {
+    PathBuf::from(path.to_string_lossy().to_lowercase())
+}
 
+/// How often `build_with_progress` reports the number of compared files
+const PROGRESS_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+#[derive(Clone)]
 pub struct FileTreeBuilder {
     left_path: PathBuf,
     right_path: PathBuf,
     include_ignored: bool,
+    show_empty_dirs: bool,
+    case_insensitive: bool,
+    /// Rules from the `.diffyignore` at either root, applied to both sides
+    root_ignore: Option<Arc<Gitignore>>,
+    /// Where `left_path` and `right_path` sit below the roots `root_ignore` was
+    /// loaded from; only set for subtree rebuilds
+    ignore_prefix: PathBuf,
+}
+
+/// Paths found under one root, relative to it
+#[derive(Debug, Default)]
+struct WalkedPaths {
+    files: BTreeSet<PathBuf>,
+    dirs: BTreeSet<PathBuf>,
 }
 
 #[derive(Debug, Clone)]
 struct FileInfo {
     path: PathBuf,
     relative_path: PathBuf,
+    /// Names on disk under each root, which only differ from `relative_path`
+    /// in case-insensitive mode
+    left_relative: PathBuf,
+    right_relative: PathBuf,
     is_directory: bool,
     size: Option<u64>,
     exists_left: bool,
     exists_right: bool,
+    /// Only read for files with more than one link, see `hardlink_metadata`
+    left_metadata: Option<FileMetadata>,
+    right_metadata: Option<FileMetadata>,
+    /// Set by `compute_file_statuses` when either side is binary
+    is_binary: bool,
 }
 
 impl FileTreeBuilder {
     pub fn new(left_path: PathBuf, right_path: PathBuf) -> Self {
-        Self { left_path, right_path, include_ignored: false }
+        Self::new_with_options(left_path, right_path, false)
     }
 
     pub fn new_with_options(left_path: PathBuf, right_path: PathBuf, include_ignored: bool) -> Self {
-        Self { left_path, right_path, include_ignored }
+        let root_ignore = Self::load_root_ignore(&left_path, &right_path, &[]);
+        Self {
+            left_path,
+            right_path,
+            include_ignored,
+            show_empty_dirs: false,
+            case_insensitive: false,
+            root_ignore,
+            ignore_prefix: PathBuf::new(),
+        }
+    }
+
+    /// Combine the `.diffyignore` files found directly under either root, so a
+    /// file present on only one side still filters both, with any `extra`
+    /// gitignore-syntax lines. Unreadable files and invalid patterns are logged
+    /// and skipped.
+    fn load_root_ignore(left_path: &Path, right_path: &Path, extra: &[String]) -> Option<Arc<Gitignore>> {
+        let files: Vec<PathBuf> = [left_path, right_path]
+            .iter()
+            .map(|root| root.join(DIFFYIGNORE_FILENAME))
+            .filter(|file| file.is_file())
+            .collect();
+        if files.is_empty() && extra.is_empty() {
+            return None;
+        }
+
+        // Patterns are matched against paths relative to the roots
+        let mut builder = GitignoreBuilder::new("");
+        for file in &files {
+            if let Some(err) = builder.add(file) {
+                tracing::warn!("Problem reading {}: {}", file.display(), err);
+            }
+        }
+        for line in extra {
+            if let Err(err) = builder.add_line(None, line) {
+                tracing::warn!("Skipping ignore pattern '{}': {}", line, err);
+            }
+        }
+
+        match builder.build() {
+            Ok(ignore) => Some(Arc::new(ignore)),
+            Err(err) => {
+                tracing::warn!("Ignoring root {} files: {}", DIFFYIGNORE_FILENAME, err);
+                None
+            }
+        }
+    }
+
+
+    /// Also list directories that contain no files on either side. A directory
+    /// that only exists on the left is reported as removed, and vice versa.
+    pub fn with_show_empty_dirs(mut self, show_empty_dirs: bool) -> Self {
+        self.show_empty_dirs = show_empty_dirs;
+        self
+    }
+
+    /// Match paths on the two sides regardless of case, for comparing a
+    /// case-sensitive filesystem against a case-insensitive one. Entries keep
+    /// the left side's spelling, and a file whose name only changed case is
+    /// reported as modified.
+    pub fn with_case_insensitive(mut self, case_insensitive: bool) -> Self {
+        self.case_insensitive = case_insensitive;
+        self
+    }
+
+    /// Skip paths matching these gitignore-syntax lines on both sides, in
+    /// addition to the root `.diffyignore` files
+    pub fn with_ignore_patterns(mut self, patterns: &[String]) -> Self {
+        if !patterns.is_empty() {
+            self.root_ignore = Self::load_root_ignore(&self.left_path, &self.right_path, patterns);
+        }
+        self
     }
 
     pub fn build(&self) -> Result<FileEntry> {
@@ -35,7 +187,7 @@ impl FileTreeBuilder {
         let all_files = self.discover_all_files()?;
         
         // Phase 2: Parallel status computation
-        let file_statuses = self.compute_file_statuses(all_files)?;
+        let file_statuses = self.compute_file_statuses(all_files, &AtomicUsize::new(0))?;
         
         // Phase 3: Build tree structure
         let root = self.build_tree_from_statuses(file_statuses)?;
@@ -43,91 +195,350 @@ impl FileTreeBuilder {
         Ok(root)
     }
 
+    /// Like [`FileTreeBuilder::build`], calling `progress(compared, total)` every
+    /// 50 ms while file statuses are computed, where `total` is the number of
+    /// paths discovered. A final `(total, total)` call is made once comparison ends.
+    pub fn build_with_progress<F>(&self, mut progress: F) -> Result<FileEntry>
+    where
+        F: FnMut(usize, usize) + Send,
+    {
+        let all_files = self.discover_all_files()?;
+        let total = all_files.len();
+        let compared = AtomicUsize::new(0);
+
+        let file_statuses = std::thread::scope(|scope| {
+            let (done_tx, done_rx) = mpsc::channel::<()>();
+            let progress = &mut progress;
+            let compared = &compared;
+
+            // Poll the shared counter until the comparison signals completion
+            scope.spawn(move || loop {
+                progress(compared.load(Ordering::Relaxed), total);
+                if !matches!(done_rx.recv_timeout(PROGRESS_POLL_INTERVAL), Err(mpsc::RecvTimeoutError::Timeout)) {
+                    break;
+                }
+            });
+
+            let statuses = self.compute_file_statuses(all_files, compared);
+            let _ = done_tx.send(());
+            statuses
+        })?;
+        progress(total, total);
+
+        self.build_tree_from_statuses(file_statuses)
+    }
+
+    /// Like [`FileTreeBuilder::build`], but compare the discovered paths
+    /// `batch_size` at a time, calling `callback` with a partial result for
+    /// each batch. Only one batch's file details are held at once; each
+    /// partial tree also contains the directories above its paths, so the
+    /// trees overlap at those directories. Hard links are only detected
+    /// within a batch. `callback` is called at least once.
+    pub fn build_batched(&self, batch_size: usize, mut callback: impl FnMut(DiffResult)) -> Result<()> {
+        if batch_size == 0 {
+            anyhow::bail!("The batch size must be at least 1");
+        }
+
+        let all_paths = self.discover_paths();
+        if all_paths.is_empty() {
+            let tree = self.build_tree_from_statuses(HashMap::new())?;
+            callback(DiffyCore::result_for_tree(self.left_path.clone(), self.right_path.clone(), tree));
+            return Ok(());
+        }
+
+        // Paths are sorted so everything below a directory directly follows
+        // it; the stack holds the path last seen and the directories above it
+        let mut open_dirs: Vec<&(PathBuf, PathBuf, PathBuf)> = Vec::new();
+        for batch in all_paths.chunks(batch_size) {
+            let first = &batch[0].0;
+            while open_dirs.last().is_some_and(|dir| !first.starts_with(&dir.0) || *first == dir.0) {
+                open_dirs.pop();
+            }
+
+            let file_infos: Vec<FileInfo> = open_dirs
+                .par_iter()
+                .copied()
+                .chain(batch.par_iter())
+                .map(|paths| self.file_info(paths.clone()))
+                .collect();
+            let statuses = self.compute_file_statuses(file_infos, &AtomicUsize::new(0))?;
+            let tree = self.build_tree_from_statuses(statuses)?;
+            callback(DiffyCore::result_for_tree(self.left_path.clone(), self.right_path.clone(), tree));
+
+            for paths in batch {
+                while open_dirs.last().is_some_and(|dir| !paths.0.starts_with(&dir.0)) {
+                    open_dirs.pop();
+                }
+                open_dirs.push(paths);
+            }
+        }
+        Ok(())
+    }
+
+    /// Rescan only the directory at `subtree_path` (relative to both roots) and
+    /// splice the fresh result into a copy of `root`. Everything outside the
+    /// subtree is reused from the previous tree.
+    pub fn rebuild_subtree(&self, root: &FileEntry, subtree_path: &Path) -> Result<FileEntry> {
+        if subtree_path.as_os_str().is_empty() {
+            return self.build();
+        }
+
+        let exists_left = self.left_path.join(subtree_path).exists();
+        let exists_right = self.right_path.join(subtree_path).exists();
+
+        let mut new_root = root.clone();
+
+        if !exists_left && !exists_right {
+            // The directory is gone from both sides, drop it from the tree
+            let parent = subtree_path.parent().unwrap_or_else(|| Path::new(""));
+            if let Some(parent_entry) = Self::find_entry_mut(&mut new_root, parent) {
+                parent_entry.children.retain(|child| child.relative_path != subtree_path);
+            }
+            return Ok(new_root);
+        }
+
+        let sub_builder = FileTreeBuilder {
+            left_path: self.left_path.join(subtree_path),
+            right_path: self.right_path.join(subtree_path),
+            ignore_prefix: self.ignore_prefix.join(subtree_path),
+            ..self.clone()
+        };
+        let mut subtree = sub_builder.build()?;
+        Self::prefix_relative_paths(&mut subtree, subtree_path);
+        subtree.status = match (exists_left, exists_right) {
+            (true, false) => DiffStatus::Removed,
+            (false, true) => DiffStatus::Added,
+            _ => DiffStatus::Unchanged,
+        };
+
+        match Self::find_entry_mut(&mut new_root, subtree_path) {
+            Some(existing) => *existing = subtree,
+            None => {
+                let parent = subtree_path.parent().unwrap_or_else(|| Path::new(""));
+                let parent_entry = Self::find_entry_mut(&mut new_root, parent)
+                    .ok_or_else(|| anyhow::anyhow!(
+                        "Parent of '{}' not found in previous tree", subtree_path.display()
+                    ))?;
+                parent_entry.children.push(subtree);
+                Self::sort_entries(&mut parent_entry.children);
+            }
+        }
+
+        Ok(new_root)
+    }
+
+    fn find_entry_mut<'a>(entry: &'a mut FileEntry, relative_path: &Path) -> Option<&'a mut FileEntry> {
+        if entry.relative_path == relative_path {
+            return Some(entry);
+        }
+
+        entry.children
+            .iter_mut()
+            .filter(|child| relative_path.starts_with(&child.relative_path))
+            .find_map(|child| Self::find_entry_mut(child, relative_path))
+    }
+
+    fn prefix_relative_paths(entry: &mut FileEntry, prefix: &Path) {
+        entry.relative_path = if entry.relative_path.as_os_str().is_empty() {
+            prefix.to_path_buf()
+        } else {
+            prefix.join(&entry.relative_path)
+        };
+        entry.path = entry.relative_path.clone();
+
+        for child in &mut entry.children {
+            Self::prefix_relative_paths(child, prefix);
+        }
+    }
+
+    fn sort_entries(entries: &mut [FileEntry]) {
+        // Same ordering as build_entry_recursive
+        entries.sort_by(FileEntry::sort_by_name());
+    }
+
     fn discover_all_files(&self) -> Result<Vec<FileInfo>> {
-        let left_files = Arc::new(Mutex::new(BTreeSet::new()));
-        let right_files = Arc::new(Mutex::new(BTreeSet::new()));
+        Ok(self.discover_paths().into_par_iter().map(|paths| self.file_info(paths)).collect())
+    }
+
+    /// Every path under either root as (display, left on disk, right on
+    /// disk), sorted so parents come before their children
+    fn discover_paths(&self) -> Vec<(PathBuf, PathBuf, PathBuf)> {
+        let left_files = Arc::new(Mutex::new(WalkedPaths::default()));
+        let right_files = Arc::new(Mutex::new(WalkedPaths::default()));
 
         // Discover files in parallel
-        let include_ignored = self.include_ignored;
         rayon::scope(|s| {
             let left_files = left_files.clone();
-            let left_path = self.left_path.clone();
             s.spawn(move |_| {
-                if let Ok(files) = Self::collect_files_parallel_static(&left_path, include_ignored) {
+                if let Ok(files) = self.collect_files_parallel(&self.left_path) {
                     *left_files.lock().unwrap() = files;
                 }
             });
 
             let right_files = right_files.clone();
-            let right_path = self.right_path.clone();
             s.spawn(move |_| {
-                if let Ok(files) = Self::collect_files_parallel_static(&right_path, include_ignored) {
+                if let Ok(files) = self.collect_files_parallel(&self.right_path) {
                     *right_files.lock().unwrap() = files;
                 }
             });
         });
 
-        let left_files = left_files.lock().unwrap().clone();
-        let right_files = right_files.lock().unwrap().clone();
+        let left_files = std::mem::take(&mut *left_files.lock().unwrap());
+        let right_files = std::mem::take(&mut *right_files.lock().unwrap());
 
-        // Combine all unique paths
-        let mut all_paths = BTreeSet::new();
-        all_paths.extend(left_files.iter().cloned());
-        all_paths.extend(right_files.iter().cloned());
+        let left_paths = self.tree_paths(left_files);
+        let right_paths = self.tree_paths(right_files);
 
-        // Create FileInfo structs
-        let file_infos: Vec<FileInfo> = all_paths
-            .into_par_iter()
-            .map(|relative_path| {
-                let left_full_path = self.left_path.join(&relative_path);
-                let right_full_path = self.right_path.join(&relative_path);
-                
-                let exists_left = left_full_path.exists();
-                let exists_right = right_full_path.exists();
-                
-                let is_directory = if exists_left {
-                    left_full_path.is_dir()
-                } else if exists_right {
-                    right_full_path.is_dir()
-                } else {
-                    false
-                };
+        // Combine all unique paths as (display, left on disk, right on disk)
+        if self.case_insensitive {
+            Self::fold_case(&left_paths, &right_paths)
+        } else {
+            left_paths
+                .union(&right_paths)
+                .map(|path| (path.clone(), path.clone(), path.clone()))
+                .collect()
+        }
+    }
 
-                let size = if !is_directory {
-                    if exists_left {
-                        std::fs::metadata(&left_full_path).ok().map(|m| m.len())
-                    } else {
-                        std::fs::metadata(&right_full_path).ok().map(|m| m.len())
-                    }
-                } else {
-                    None
-                };
+    fn file_info(&self, (relative_path, left_relative, right_relative): (PathBuf, PathBuf, PathBuf)) -> FileInfo {
+        let left_full_path = self.left_path.join(&left_relative);
+        let right_full_path = self.right_path.join(&right_relative);
 
-                FileInfo {
-                    path: relative_path.clone(),
-                    relative_path,
-                    is_directory,
-                    size,
-                    exists_left,
-                    exists_right,
-                }
-            })
-            .collect();
+        let exists_left = left_full_path.exists();
+        let exists_right = right_full_path.exists();
+
+        let is_directory = if exists_left {
+            left_full_path.is_dir()
+        } else if exists_right {
+            right_full_path.is_dir()
+        } else {
+            false
+        };
+
+        let size = if !is_directory {
+            if exists_left {
+                std::fs::metadata(&left_full_path).ok().map(|m| m.len())
+            } else {
+                std::fs::metadata(&right_full_path).ok().map(|m| m.len())
+            }
+        } else {
+            None
+        };
+
+        let left_metadata = (exists_left && !is_directory)
+            .then(|| hardlink_metadata(&left_full_path))
+            .flatten();
+        let right_metadata = (exists_right && !is_directory)
+            .then(|| hardlink_metadata(&right_full_path))
+            .flatten();
+
+        FileInfo {
+            path: relative_path.clone(),
+            relative_path,
+            left_relative,
+            right_relative,
+            is_directory,
+            size,
+            exists_left,
+            exists_right,
+            left_metadata,
+            right_metadata,
+            is_binary: false,
+        }
+    }
+
+    /// Every walked file plus the directories that hold them, and empty
+    /// directories too when those are shown
+    fn tree_paths(&self, walked: WalkedPaths) -> BTreeSet<PathBuf> {
+        let mut paths = BTreeSet::new();
+        for file in &walked.files {
+            paths.extend(
+                file.ancestors()
+                    .skip(1)
+                    .take_while(|dir| !dir.as_os_str().is_empty())
+                    .map(Path::to_path_buf),
+            );
+        }
+        paths.extend(walked.files);
+        if self.show_empty_dirs {
+            paths.extend(walked.dirs);
+        }
+        paths
+    }
 
-        Ok(file_infos)
+    /// Pair up left and right paths that only differ in case. Each display path
+    /// is built from its parent's display path so children stay under it.
+    fn fold_case(left: &BTreeSet<PathBuf>, right: &BTreeSet<PathBuf>) -> Vec<(PathBuf, PathBuf, PathBuf)> {
+        let by_key = |paths: &BTreeSet<PathBuf>| -> BTreeMap<PathBuf, PathBuf> {
+            paths.iter().map(|path| (case_fold_key(path), path.clone())).collect()
+        };
+        let left_by_key = by_key(left);
+        let right_by_key = by_key(right);
+
+        // Keys sort parents before their children, so a parent's display path
+        // is always known by the time its children are reached
+        let keys: BTreeSet<&PathBuf> = left_by_key.keys().chain(right_by_key.keys()).collect();
+        let mut display_by_key: HashMap<&Path, PathBuf> = HashMap::new();
+        let mut paths = Vec::with_capacity(keys.len());
+
+        for key in keys {
+            let left_actual = left_by_key.get(key);
+            let right_actual = right_by_key.get(key);
+            let actual = left_actual.or(right_actual).expect("key comes from one of the sides");
+
+            let display = match (key.parent(), actual.file_name()) {
+                (Some(parent), Some(name)) if !parent.as_os_str().is_empty() => display_by_key
+                    .get(parent)
+                    .map(|parent_display| parent_display.join(name))
+                    .unwrap_or_else(|| actual.clone()),
+                _ => actual.clone(),
+            };
+
+            paths.push((
+                display.clone(),
+                left_actual.cloned().unwrap_or_else(|| display.clone()),
+                right_actual.cloned().unwrap_or_else(|| display.clone()),
+            ));
+            display_by_key.insert(key.as_path(), display);
+        }
+
+        paths
     }
 
-    fn collect_files_parallel_static(root: &Path, include_ignored: bool) -> Result<BTreeSet<PathBuf>> {
+    fn collect_files_parallel(&self, root: &Path) -> Result<WalkedPaths> {
         if !root.exists() {
-            return Ok(BTreeSet::new());
+            return Ok(WalkedPaths::default());
         }
 
-        let files = Arc::new(Mutex::new(BTreeSet::new()));
-        let walker = ignore::WalkBuilder::new(root)
+        let files = Arc::new(Mutex::new(WalkedPaths::default()));
+        // .diffyignore files apply to their own directory and below, using
+        // .gitignore syntax, and are honoured even with --include-ignored
+        let mut builder = ignore::WalkBuilder::new(root);
+        builder
             .hidden(false)
-            .git_ignore(!include_ignored)
-            .threads(std::cmp::max(1, num_cpus::get() / 2))
-            .build_parallel();
+            .git_ignore(!self.include_ignored)
+            .add_custom_ignore_filename(DIFFYIGNORE_FILENAME)
+            .threads(std::cmp::max(1, num_cpus::get() / 2));
+
+        // Repository metadata is never compared, e.g. when a working tree is
+        // diffed against a stash. The root .diffyignore of either side also
+        // applies to the other.
+        let root_ignore = self.root_ignore.clone();
+        let walk_root = root.to_path_buf();
+        let prefix = self.ignore_prefix.clone();
+        builder.filter_entry(move |entry| {
+            let is_dir = entry.file_type().is_some_and(|file_type| file_type.is_dir());
+            if is_dir && entry.depth() > 0 && entry.file_name() == GIT_DIR_NAME {
+                return false;
+            }
+            let Some(root_ignore) = &root_ignore else { return true };
+            let Ok(relative_path) = entry.path().strip_prefix(&walk_root) else { return true };
+            if relative_path.as_os_str().is_empty() {
+                return true;
+            }
+            !root_ignore.matched(prefix.join(relative_path), is_dir).is_ignore()
+        });
+        let walker = builder.build_parallel();
 
         walker.run(|| {
             let files = files.clone();
@@ -136,7 +547,13 @@ impl FileTreeBuilder {
                 if let Ok(entry) = entry {
                     if let Ok(relative_path) = entry.path().strip_prefix(&root) {
                         if !relative_path.as_os_str().is_empty() {
-                            files.lock().unwrap().insert(relative_path.to_path_buf());
+                            let is_dir = entry.file_type().is_some_and(|file_type| file_type.is_dir());
+                            let mut walked = files.lock().unwrap();
+                            if is_dir {
+                                walked.dirs.insert(relative_path.to_path_buf());
+                            } else {
+                                walked.files.insert(relative_path.to_path_buf());
+                            }
                         }
                     }
                 }
@@ -147,14 +564,24 @@ impl FileTreeBuilder {
         Ok(Arc::try_unwrap(files).unwrap().into_inner().unwrap())
     }
 
-    fn compute_file_statuses(&self, file_infos: Vec<FileInfo>) -> Result<HashMap<PathBuf, (FileInfo, DiffStatus)>> {
-        let statuses: HashMap<PathBuf, (FileInfo, DiffStatus)> = file_infos
+    /// Compare every discovered path, incrementing `compared` as each one finishes
+    fn compute_file_statuses(
+        &self,
+        file_infos: Vec<FileInfo>,
+        compared: &AtomicUsize,
+    ) -> Result<HashMap<PathBuf, (FileInfo, DiffStatus)>> {
+        let mut statuses: HashMap<PathBuf, (FileInfo, DiffStatus)> = file_infos
             .into_par_iter()
-            .map(|info| {
+            .map(|mut info| {
+                if !info.is_directory {
+                    info.is_binary = [(info.exists_left, &self.left_path, &info.left_relative), (info.exists_right, &self.right_path, &info.right_relative)]
+                        .into_iter()
+                        .any(|(exists, root, relative)| exists && DiffEngine::is_binary_file(&root.join(relative)).unwrap_or(false));
+                }
+
                 let status = if info.exists_left && info.exists_right {
-                    if info.is_directory {
-                        DiffStatus::Unchanged
-                    } else if self.files_are_equal(&info.relative_path).unwrap_or(false) {
+                    // The two sides' names can differ in case; a rename alone isn't a change
+                    if info.is_directory || self.files_are_equal(&info.left_relative, &info.right_relative).unwrap_or(false) {
                         DiffStatus::Unchanged
                     } else {
                         DiffStatus::Modified
@@ -167,22 +594,90 @@ impl FileTreeBuilder {
                     DiffStatus::Unchanged // Shouldn't happen
                 };
 
+                compared.fetch_add(1, Ordering::Relaxed);
                 (info.relative_path.clone(), (info, status))
             })
             .collect();
 
+        Self::mark_hardlinks(&mut statuses);
+        self.mark_conflicts(&mut statuses);
         Ok(statuses)
     }
 
+    /// Report modified files whose right side still contains git merge
+    /// conflict markers as `Conflicted`
+    fn mark_conflicts(&self, statuses: &mut HashMap<PathBuf, (FileInfo, DiffStatus)>) {
+        statuses.par_iter_mut().for_each(|(_, (info, status))| {
+            if *status == DiffStatus::Modified
+                && !info.is_directory
+                && has_conflict_markers(&self.right_path.join(&info.right_relative))
+            {
+                *status = DiffStatus::Conflicted;
+            }
+        });
+    }
+
+    /// Record which scanned files share a `(device, inode)` pair, either with
+    /// other paths on the same side or with a differently named path on the
+    /// other side. Metadata of files linked to nothing else in the scan is dropped.
+    fn mark_hardlinks(statuses: &mut HashMap<PathBuf, (FileInfo, DiffStatus)>) {
+        let mut left_links: HashMap<(u64, u64), Vec<PathBuf>> = HashMap::new();
+        let mut right_links: HashMap<(u64, u64), Vec<PathBuf>> = HashMap::new();
+        for (path, (info, _)) in statuses.iter() {
+            if let Some(key) = info.left_metadata.as_ref().and_then(FileMetadata::inode_key) {
+                left_links.entry(key).or_default().push(path.clone());
+            }
+            if let Some(key) = info.right_metadata.as_ref().and_then(FileMetadata::inode_key) {
+                right_links.entry(key).or_default().push(path.clone());
+            }
+        }
+
+        for (info, _) in statuses.values_mut() {
+            let sides = [
+                (&mut info.left_metadata, &left_links, &right_links),
+                (&mut info.right_metadata, &right_links, &left_links),
+            ];
+            for (metadata, same_side, other_side) in sides {
+                let Some(key) = metadata.as_ref().and_then(FileMetadata::inode_key) else { continue };
+                let others = |links: &HashMap<(u64, u64), Vec<PathBuf>>| -> Vec<PathBuf> {
+                    let mut paths: Vec<PathBuf> = links
+                        .get(&key)
+                        .into_iter()
+                        .flatten()
+                        .filter(|path| **path != info.relative_path)
+                        .cloned()
+                        .collect();
+                    paths.sort();
+                    paths
+                };
+
+                let hardlinks = others(same_side);
+                let linked_as = others(other_side).into_iter().next();
+                match metadata {
+                    Some(metadata) if !hardlinks.is_empty() || linked_as.is_some() => {
+                        metadata.hardlinks = hardlinks;
+                        metadata.linked_as = linked_as;
+                    }
+                    _ => *metadata = None,
+                }
+            }
+        }
+    }
+
     fn build_tree_from_statuses(&self, statuses: HashMap<PathBuf, (FileInfo, DiffStatus)>) -> Result<FileEntry> {
         // Build the tree structure
         let root_info = FileInfo {
             path: PathBuf::from(""),
             relative_path: PathBuf::from(""),
+            left_relative: PathBuf::from(""),
+            right_relative: PathBuf::from(""),
             is_directory: true,
             size: None,
             exists_left: true,
             exists_right: true,
+            left_metadata: None,
+            right_metadata: None,
+            is_binary: false,
         };
 
         let root_entry = self.build_entry_recursive(root_info, DiffStatus::Unchanged, &statuses)?;
@@ -202,11 +697,16 @@ impl FileTreeBuilder {
             status,
             size: info.size,
             children: Vec::new(),
+            line_summary: None,
+            similarity: None,
+            is_binary: info.is_binary,
+            left_metadata: info.left_metadata.clone(),
+            right_metadata: info.right_metadata.clone(),
         };
 
         if info.is_directory {
             // Find all direct children
-            let mut children: Vec<(FileInfo, DiffStatus)> = all_statuses
+            let children: Vec<(FileInfo, DiffStatus)> = all_statuses
                 .values()
                 .filter_map(|(child_info, child_status)| {
                     if let Some(parent) = child_info.relative_path.parent() {
@@ -223,29 +723,21 @@ impl FileTreeBuilder {
                 })
                 .collect();
 
-            // Sort children: directories first, then files
-            children.sort_by(|(a_info, _), (b_info, _)| {
-                match (a_info.is_directory, b_info.is_directory) {
-                    (true, false) => std::cmp::Ordering::Less,
-                    (false, true) => std::cmp::Ordering::Greater,
-                    _ => a_info.relative_path.file_name().cmp(&b_info.relative_path.file_name()),
-                }
-            });
-
             // Build children recursively
             for (child_info, child_status) in children {
                 if let Ok(child_entry) = self.build_entry_recursive(child_info, child_status, all_statuses) {
                     entry.children.push(child_entry);
                 }
             }
+            entry.children.sort_by(FileEntry::sort_by_name());
         }
 
         Ok(entry)
     }
 
-    fn files_are_equal(&self, relative_path: &Path) -> Result<bool> {
-        let left_path = self.left_path.join(relative_path);
-        let right_path = self.right_path.join(relative_path);
+    fn files_are_equal(&self, left_relative: &Path, right_relative: &Path) -> Result<bool> {
+        let left_path = self.left_path.join(left_relative);
+        let right_path = self.right_path.join(right_relative);
 
         if !left_path.exists() || !right_path.exists() {
             return Ok(false);
@@ -336,4 +828,23 @@ impl FileTreeBuilder {
 
         Ok(true)
     }
-}
\ No newline at end of file
+}
+
+/// Whether the start of the file at `path` has a complete set of `<<<<<<<`,
+/// `=======` and `>>>>>>>` conflict marker lines
+fn has_conflict_markers(path: &Path) -> bool {
+    let mut head = Vec::new();
+    let Ok(file) = std::fs::File::open(path) else { return false };
+    if file.take(CONFLICT_SCAN_BYTES).read_to_end(&mut head).is_err() {
+        return false;
+    }
+
+    let (mut ours, mut separator, mut theirs) = (false, false, false);
+    for line in head.split(|&byte| byte == b'\n') {
+        let line = line.strip_suffix(b"\r").unwrap_or(line);
+        ours |= line.starts_with(b"<<<<<<< ");
+        separator |= line == b"=======";
+        theirs |= line.starts_with(b">>>>>>> ");
+    }
+    ours && separator && theirs
+}
@@ -1,5 +1,10 @@
+use crate::error::DiffyError;
 use serde::{Deserialize, Serialize};
-use std::path::PathBuf;
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::SystemTime;
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum DiffStatus {
@@ -10,6 +15,46 @@ pub enum DiffStatus {
     Conflicted,
 }
 
+/// Order of entries within each directory of a tree. Directories always come
+/// before files.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SortMode {
+    #[default]
+    Name,
+    /// Conflicted, modified, added, removed, then unchanged entries
+    Status,
+    /// Largest files first
+    Size,
+}
+
+impl SortMode {
+    pub fn name(&self) -> &'static str {
+        match self {
+            SortMode::Name => "name",
+            SortMode::Status => "status",
+            SortMode::Size => "size",
+        }
+    }
+
+    /// The mode after this one, wrapping around
+    pub fn next(&self) -> SortMode {
+        match self {
+            SortMode::Name => SortMode::Status,
+            SortMode::Status => SortMode::Size,
+            SortMode::Size => SortMode::Name,
+        }
+    }
+
+    pub fn comparator(&self) -> fn(&FileEntry, &FileEntry) -> Ordering {
+        match self {
+            SortMode::Name => FileEntry::sort_by_name(),
+            SortMode::Status => FileEntry::sort_by_status_then_name(),
+            SortMode::Size => FileEntry::sort_by_size(),
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct FileEntry {
     pub path: PathBuf,
@@ -18,6 +63,99 @@ pub struct FileEntry {
     pub status: DiffStatus,
     pub size: Option<u64>,
     pub children: Vec<FileEntry>,
+    /// Line counts for changed files, filled in when `DiffOptions::compute_line_stats` is set
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub line_summary: Option<LineSummary>,
+    /// `FileDiff::similarity` of a modified file, filled in with `line_summary`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub similarity: Option<f32>,
+    /// Either side looks binary (has a NUL byte near the start)
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub is_binary: bool,
+    /// Left side metadata, recorded when the file is hard linked to another scanned path
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub left_metadata: Option<FileMetadata>,
+    /// Right side metadata, recorded when the file is hard linked to another scanned path
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub right_metadata: Option<FileMetadata>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LineSummary {
+    pub additions: u32,
+    pub deletions: u32,
+}
+
+/// Filesystem details for one side of an entry
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FileMetadata {
+    pub size: u64,
+    pub modified: Option<SystemTime>,
+    /// Permission bits such as `0o644` (Unix only)
+    pub permissions: Option<u32>,
+    pub is_symlink: bool,
+    pub symlink_target: Option<PathBuf>,
+    /// Number of hard links to the inode (Unix only)
+    pub nlinks: Option<u64>,
+    /// Inode number (Unix only)
+    pub inode: Option<u64>,
+    /// Device holding the inode (Unix only)
+    pub device: Option<u64>,
+    /// Other scanned paths on the same side that are hard links to this file
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub hardlinks: Vec<PathBuf>,
+    /// Path on the other side that is the same inode under a different name
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub linked_as: Option<PathBuf>,
+}
+
+impl FileMetadata {
+    /// Read the metadata of `path` itself, without following a final symlink
+    pub fn read(path: &Path) -> std::io::Result<Self> {
+        let metadata = std::fs::symlink_metadata(path)?;
+        let is_symlink = metadata.file_type().is_symlink();
+
+        #[cfg(unix)]
+        let (permissions, nlinks, inode, device) = {
+            use std::os::unix::fs::MetadataExt;
+            (Some(metadata.mode() & 0o7777), Some(metadata.nlink()), Some(metadata.ino()), Some(metadata.dev()))
+        };
+        #[cfg(not(unix))]
+        let (permissions, nlinks, inode, device) = (None, None, None, None);
+
+        Ok(Self {
+            size: metadata.len(),
+            modified: metadata.modified().ok(),
+            permissions,
+            is_symlink,
+            symlink_target: if is_symlink { std::fs::read_link(path).ok() } else { None },
+            nlinks,
+            inode,
+            device,
+            hardlinks: Vec::new(),
+            linked_as: None,
+        })
+    }
+
+    /// Permission bits as `ls` shows them, e.g. `rwxr-xr-x`
+    pub fn permissions_string(&self) -> Option<String> {
+        let mode = self.permissions?;
+        Some(
+            (0..9)
+                .rev()
+                .map(|bit| if mode & (1 << bit) != 0 { b"xwr"[bit % 3] as char } else { '-' })
+                .collect(),
+        )
+    }
+
+    /// The `(device, inode)` pair identifying the file, where available
+    pub fn inode_key(&self) -> Option<(u64, u64)> {
+        Some((self.device?, self.inode?))
+    }
+
+    pub fn is_hardlinked(&self) -> bool {
+        !self.hardlinks.is_empty() || self.linked_as.is_some()
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -29,16 +167,203 @@ pub struct DiffResult {
     pub added_count: usize,
     pub removed_count: usize,
     pub modified_count: usize,
+    /// Files containing git merge conflict markers
+    #[serde(default)]
+    pub conflict_count: usize,
+    #[serde(default)]
+    pub unchanged_count: usize,
+    /// Binary files, which are not included in the other counts
+    #[serde(default)]
+    pub binary_count: usize,
+    /// Copied from `DiffyCore::description`, e.g. "main..feature"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+}
+
+/// One line of a file with the commit that last changed it. The commit fields
+/// are `None` outside a git repository and for uncommitted lines.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BlameLine {
+    pub line_number: u32,
+    pub commit_hash: Option<String>,
+    pub author: Option<String>,
+    /// Commit time as RFC 3339 in UTC
+    pub date: Option<String>,
+    pub content: String,
+}
+
+/// A removed file whose content reappears in an added file, as found by
+/// `DiffyCore::detect_renames`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RenamedFile {
+    pub from: PathBuf,
+    pub to: PathBuf,
+    pub similarity: f32,
+}
+
+/// Which kinds of change to keep, in git's `--diff-filter` syntax: `A`
+/// (added), `D` (deleted), `M` (modified, including merge conflicts), `R`
+/// (renamed) and `U` (unchanged). Uppercase codes include only those kinds,
+/// lowercase codes exclude them, so `AM` keeps added and modified files and
+/// `d` keeps everything but deleted ones.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DiffFilter {
+    include: Vec<char>,
+    exclude: Vec<char>,
+}
+
+impl DiffFilter {
+    /// The codes understood by `--diff-filter`, in uppercase
+    pub const CODES: &'static str = "ADMRU";
+
+    /// Whether files with the uppercase `code` pass the filter
+    pub fn allows(&self, code: char) -> bool {
+        (self.include.is_empty() || self.include.contains(&code)) && !self.exclude.contains(&code)
+    }
+
+    /// The code of an entry with `status`, or `R` for either side of a rename
+    pub fn code_of(status: &DiffStatus, renamed: bool) -> char {
+        match status {
+            _ if renamed => 'R',
+            DiffStatus::Added => 'A',
+            DiffStatus::Removed => 'D',
+            DiffStatus::Modified | DiffStatus::Conflicted => 'M',
+            DiffStatus::Unchanged => 'U',
+        }
+    }
+
+    /// Whether renamed files are kept differently from added or deleted ones,
+    /// so renames have to be detected to apply the filter
+    pub fn needs_renames(&self) -> bool {
+        self.allows('R') != self.allows('A') || self.allows('R') != self.allows('D')
+    }
+}
+
+impl std::str::FromStr for DiffFilter {
+    type Err = DiffyError;
+
+    fn from_str(codes: &str) -> Result<Self, Self::Err> {
+        let mut filter = DiffFilter::default();
+        for code in codes.chars() {
+            let upper = code.to_ascii_uppercase();
+            if !Self::CODES.contains(upper) {
+                return Err(DiffyError::UnknownDiffFilter { code });
+            }
+            if code.is_ascii_uppercase() {
+                filter.include.push(upper);
+            } else {
+                filter.exclude.push(upper);
+            }
+        }
+        Ok(filter)
+    }
+}
+
+/// A subdirectory of a snapshot directory, as listed by
+/// `DiffyCore::list_snapshots`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SnapshotInfo {
+    pub name: String,
+    /// Creation time, or the modification time where the platform does not
+    /// record one
+    pub created: Option<SystemTime>,
+    /// Number of files in the snapshot, including ignored ones
+    pub file_count: usize,
+}
+
+/// Diffs for several files, keyed by the requested relative path. Paths that
+/// could not be diffed are listed in `errors` instead.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct FileDiffBatch {
+    pub diffs: BTreeMap<String, FileDiff>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub errors: BTreeMap<String, String>,
+}
+
+/// A tree entry whose path matched a search query
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SearchResult {
+    pub path: PathBuf,
+    pub status: DiffStatus,
+    pub is_directory: bool,
+}
+
+/// File- and line-level totals for a diff result
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DiffStatistics {
+    pub total_files: usize,
+    pub added_count: usize,
+    pub removed_count: usize,
+    pub modified_count: usize,
+    #[serde(default)]
+    pub unchanged_count: usize,
+    #[serde(default)]
+    pub binary_count: usize,
+    pub lines_added: usize,
+    pub lines_removed: usize,
+}
+
+impl DiffStatistics {
+    /// One line in the style of `git diff --shortstat`, e.g. "3 files changed
+    /// (1 added, 0 removed, 2 modified), 10 insertions(+), 2 deletions(-)"
+    pub fn to_shortstat(&self) -> String {
+        let changed = self.added_count + self.removed_count + self.modified_count;
+        format!(
+            "{} file{} changed ({} added, {} removed, {} modified), {} insertion{}(+), {} deletion{}(-)",
+            changed,
+            if changed == 1 { "" } else { "s" },
+            self.added_count,
+            self.removed_count,
+            self.modified_count,
+            self.lines_added,
+            if self.lines_added == 1 { "" } else { "s" },
+            self.lines_removed,
+            if self.lines_removed == 1 { "" } else { "s" },
+        )
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct FileDiff {
-    pub left_content: Option<String>,
-    pub right_content: Option<String>,
+    /// Text of each side, shared with other diffs of the same content
+    /// through the core's `ContentStore`
+    pub left_content: Option<Arc<String>>,
+    pub right_content: Option<Arc<String>>,
     pub hunks: Vec<DiffHunk>,
+    #[serde(default)]
+    pub is_binary: bool,
+    /// How much of the text is unchanged, from 0.0 (every line changed) to
+    /// 1.0 (identical): one minus the changed lines over the lines on both
+    /// sides. Always 0.0 for binary files.
+    #[serde(default)]
+    pub similarity: f32,
+    /// Sizes for files that were not diffed line by line
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub binary: Option<BinaryFileDiff>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// Why a file has no line diff, and how big each side is
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BinaryFileDiff {
+    pub left_size: Option<u64>,
+    pub right_size: Option<u64>,
+    /// Set when a side exceeds `DiffOptions::max_file_size` rather than being binary
+    pub too_large: bool,
+}
+
+/// One file section of a unified diff read by `DiffEngine::parse_unified_diff`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ParsedFileDiff {
+    /// Path from the `---` header, e.g. `a/src/main.rs` or `/dev/null`
+    pub old_path: PathBuf,
+    /// Path from the `+++` header
+    pub new_path: PathBuf,
+    pub hunks: Vec<DiffHunk>,
+    /// Set for `Binary files ... differ` sections, which have no hunks
+    pub is_binary: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct DiffHunk {
     pub old_start: u32,
     pub old_lines: u32,
@@ -47,7 +372,7 @@ pub struct DiffHunk {
     pub lines: Vec<DiffLine>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct DiffLine {
     pub kind: DiffLineKind,
     pub content: String,
@@ -55,6 +380,21 @@ pub struct DiffLine {
     pub new_line_number: Option<u32>,
 }
 
+/// A line of word-level diff output from `DiffEngine::word_diff`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum WordDiffLine {
+    Context(String),
+    Added(Vec<WordSpan>),
+    Removed(Vec<WordSpan>),
+}
+
+/// A run of text within a word-diff line; `changed` marks words that differ
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WordSpan {
+    pub changed: bool,
+    pub text: String,
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum DiffLineKind {
     Context,
@@ -62,6 +402,384 @@ pub enum DiffLineKind {
     Deletion,
 }
 
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DiffAlgorithm {
+    #[default]
+    Myers,
+    Patience,
+    Lcs,
+}
+
+/// Similarity above which `DiffyCore::detect_renames` pairs a removed file
+/// with an added one, as in git's default of 50%
+pub const DEFAULT_RENAME_THRESHOLD: f32 = 0.5;
+
+/// Default for `DiffOptions::max_file_size`: 50 MB
+pub const DEFAULT_MAX_FILE_SIZE: u64 = 50 * 1024 * 1024;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DiffOptions {
+    /// Line diff algorithm
+    pub algorithm: DiffAlgorithm,
+    /// Number of unchanged lines shown around each change
+    pub context_lines: usize,
+    /// Files with more lines than this are diffed in parallel segments
+    pub parallel_hunk_threshold: usize,
+    /// Diff every changed file during analysis to fill in `FileEntry::line_summary`
+    pub compute_line_stats: bool,
+    /// Skip lines containing only whitespace when comparing files
+    pub ignore_blank_lines: bool,
+    /// Files larger than this many bytes are not read for diffing
+    pub max_file_size: Option<u64>,
+}
+
+impl Default for DiffOptions {
+    fn default() -> Self {
+        Self {
+            algorithm: DiffAlgorithm::default(),
+            context_lines: 3,
+            parallel_hunk_threshold: 5000,
+            compute_line_stats: false,
+            ignore_blank_lines: false,
+            max_file_size: Some(DEFAULT_MAX_FILE_SIZE),
+        }
+    }
+}
+
+impl FileEntry {
+    /// The entry at `relative_path` in this subtree
+    pub fn find(&self, relative_path: &Path) -> Option<&FileEntry> {
+        if self.relative_path == relative_path {
+            return Some(self);
+        }
+        self.children
+            .iter()
+            .filter(|child| relative_path.starts_with(&child.relative_path))
+            .find_map(|child| child.find(relative_path))
+    }
+
+    /// Whether either side shares its inode with another scanned path
+    pub fn is_hardlinked(&self) -> bool {
+        [&self.left_metadata, &self.right_metadata]
+            .into_iter()
+            .flatten()
+            .any(FileMetadata::is_hardlinked)
+    }
+
+    /// Status of a file, or for a directory the combined status of its contents:
+    /// added/removed directories keep their own status, otherwise any change
+    /// below makes the directory modified.
+    pub fn aggregate_status(&self) -> DiffStatus {
+        if !self.is_directory || matches!(self.status, DiffStatus::Added | DiffStatus::Removed) {
+            return self.status.clone();
+        }
+
+        let changed = self.children
+            .iter()
+            .any(|child| child.aggregate_status() != DiffStatus::Unchanged);
+        if changed { DiffStatus::Modified } else { DiffStatus::Unchanged }
+    }
+
+    /// Call `f` for this entry and everything below it
+    pub(crate) fn visit(&self, f: &mut impl FnMut(&FileEntry)) {
+        f(self);
+        for child in &self.children {
+            child.visit(f);
+        }
+    }
+
+    /// Sort the children of every directory in this subtree with `comparator`
+    pub fn sort_children<F>(&mut self, comparator: F)
+    where
+        F: Fn(&FileEntry, &FileEntry) -> Ordering,
+    {
+        self.sort_children_by(&comparator);
+    }
+
+    fn sort_children_by<F>(&mut self, comparator: &F)
+    where
+        F: Fn(&FileEntry, &FileEntry) -> Ordering,
+    {
+        self.children.sort_by(comparator);
+        for child in &mut self.children {
+            child.sort_children_by(comparator);
+        }
+    }
+
+    /// Directories first, then by file name. This is the order trees are built in.
+    pub fn sort_by_name() -> fn(&FileEntry, &FileEntry) -> Ordering {
+        |a, b| Self::directories_first(a, b).then_with(|| a.file_name_order(b))
+    }
+
+    /// Directories first, then by status (see `SortMode::Status`), keeping the
+    /// existing order of entries with the same status
+    pub fn sort_by_status() -> fn(&FileEntry, &FileEntry) -> Ordering {
+        |a, b| Self::directories_first(a, b).then_with(|| a.status_rank().cmp(&b.status_rank()))
+    }
+
+    /// Directories first, then largest files first
+    pub fn sort_by_size() -> fn(&FileEntry, &FileEntry) -> Ordering {
+        |a, b| Self::directories_first(a, b).then_with(|| b.size.cmp(&a.size)).then_with(|| a.file_name_order(b))
+    }
+
+    /// Directories first, then by status, then by file name
+    pub fn sort_by_status_then_name() -> fn(&FileEntry, &FileEntry) -> Ordering {
+        |a, b| Self::sort_by_status()(a, b).then_with(|| a.file_name_order(b))
+    }
+
+    fn directories_first(a: &FileEntry, b: &FileEntry) -> Ordering {
+        b.is_directory.cmp(&a.is_directory)
+    }
+
+    fn file_name_order(&self, other: &FileEntry) -> Ordering {
+        self.relative_path.file_name().cmp(&other.relative_path.file_name())
+    }
+
+    /// Directories rank by their aggregate status so ones with changes come first
+    fn status_rank(&self) -> u8 {
+        match self.aggregate_status() {
+            DiffStatus::Conflicted => 0,
+            DiffStatus::Modified => 1,
+            DiffStatus::Added => 2,
+            DiffStatus::Removed => 3,
+            DiffStatus::Unchanged => 4,
+        }
+    }
+}
+
+struct TreeGlyphs {
+    branch: &'static str,
+    last_branch: &'static str,
+    pipe: &'static str,
+    space: &'static str,
+}
+
+const UNICODE_GLYPHS: TreeGlyphs = TreeGlyphs {
+    branch: "├── ",
+    last_branch: "└── ",
+    pipe: "│   ",
+    space: "    ",
+};
+
+const ASCII_GLYPHS: TreeGlyphs = TreeGlyphs {
+    branch: "|-- ",
+    last_branch: "`-- ",
+    pipe: "|   ",
+    space: "    ",
+};
+
+impl DiffResult {
+    /// Render the tree like the Unix `tree` command, prefixing each entry with
+    /// its status (`[+]`, `[-]`, `[~]`, `[ ]`)
+    pub fn to_tree_string(&self) -> String {
+        self.render_tree(&UNICODE_GLYPHS)
+    }
+
+    /// Same as [`DiffResult::to_tree_string`] using only ASCII characters
+    pub fn to_tree_string_ascii(&self) -> String {
+        self.render_tree(&ASCII_GLYPHS)
+    }
+
+    /// Entries whose relative path contains `query`, case-insensitively
+    pub fn search(&self, query: &str) -> Vec<SearchResult> {
+        fn walk(entry: &FileEntry, query: &str, results: &mut Vec<SearchResult>) {
+            let path = entry.relative_path.to_string_lossy().to_lowercase();
+            if !path.is_empty() && path.contains(query) {
+                results.push(SearchResult {
+                    path: entry.relative_path.clone(),
+                    status: entry.status.clone(),
+                    is_directory: entry.is_directory,
+                });
+            }
+            for child in &entry.children {
+                walk(child, query, results);
+            }
+        }
+
+        let mut results = Vec::new();
+        walk(&self.tree, &query.to_lowercase(), &mut results);
+        results
+    }
+
+    /// Combine two results into one whose root holds each tree in a directory
+    /// named after its `left_path` basename, with the counts summed.
+    ///
+    /// The merged result has empty root paths. Merging it again keeps its
+    /// entries where they are, so several results can be chained together.
+    /// File diffs for the merged entries still have to come from the
+    /// `DiffyCore` that produced each side.
+    pub fn merge(&self, other: &DiffResult) -> anyhow::Result<DiffResult> {
+        let left = self.namespaced_entries()?;
+        let right = other.namespaced_entries()?;
+
+        let mut seen = HashSet::new();
+        for entry in &left {
+            entry.visit(&mut |entry| {
+                seen.insert(entry.relative_path.clone());
+            });
+        }
+        let mut overlap = None;
+        for entry in &right {
+            entry.visit(&mut |entry| {
+                if overlap.is_none() && seen.contains(&entry.relative_path) {
+                    overlap = Some(entry.relative_path.clone());
+                }
+            });
+        }
+        if let Some(path) = overlap {
+            return Err(DiffyError::OverlappingPath { path }.into());
+        }
+
+        let mut tree = FileEntry {
+            path: PathBuf::new(),
+            relative_path: PathBuf::new(),
+            is_directory: true,
+            status: DiffStatus::Unchanged,
+            size: None,
+            children: left.into_iter().chain(right).collect(),
+            line_summary: None,
+            similarity: None,
+            is_binary: false,
+            left_metadata: None,
+            right_metadata: None,
+        };
+        tree.sort_children(FileEntry::sort_by_name());
+
+        Ok(DiffResult {
+            left_path: PathBuf::new(),
+            right_path: PathBuf::new(),
+            tree,
+            total_files: self.total_files + other.total_files,
+            added_count: self.added_count + other.added_count,
+            removed_count: self.removed_count + other.removed_count,
+            modified_count: self.modified_count + other.modified_count,
+            conflict_count: self.conflict_count + other.conflict_count,
+            unchanged_count: self.unchanged_count + other.unchanged_count,
+            binary_count: self.binary_count + other.binary_count,
+            description: None,
+        })
+    }
+
+    /// The top-level entries of a merged tree: the root moved below the
+    /// `left_path` basename, or the existing children of an earlier merge
+    fn namespaced_entries(&self) -> anyhow::Result<Vec<FileEntry>> {
+        fn prefix(entry: &mut FileEntry, namespace: &Path) {
+            for child in &mut entry.children {
+                child.relative_path = namespace.join(&child.relative_path);
+                prefix(child, namespace);
+            }
+        }
+
+        if self.left_path.as_os_str().is_empty() {
+            return Ok(self.tree.children.clone());
+        }
+
+        let namespace = self.left_path
+            .canonicalize()
+            .unwrap_or_else(|_| self.left_path.clone())
+            .file_name()
+            .map(PathBuf::from)
+            .ok_or_else(|| anyhow::anyhow!("{} has no basename to merge under", self.left_path.display()))?;
+
+        let mut tree = self.tree.clone();
+        prefix(&mut tree, &namespace);
+        tree.path = self.left_path.clone();
+        tree.relative_path = namespace;
+        Ok(vec![tree])
+    }
+
+    fn render_tree(&self, glyphs: &TreeGlyphs) -> String {
+        let mut output = String::from(".\n");
+        Self::render_children(&self.tree, "", glyphs, &mut output);
+        output
+    }
+
+    fn render_children(entry: &FileEntry, prefix: &str, glyphs: &TreeGlyphs, output: &mut String) {
+        for (i, child) in entry.children.iter().enumerate() {
+            let is_last = i + 1 == entry.children.len();
+            let name = child.relative_path
+                .file_name()
+                .map(|name| name.to_string_lossy().to_string())
+                .unwrap_or_default();
+
+            output.push_str(&format!(
+                "{}{}[{}] {}{}\n",
+                prefix,
+                if is_last { glyphs.last_branch } else { glyphs.branch },
+                child.aggregate_status().icon(),
+                name,
+                if child.is_directory { "/" } else { "" }
+            ));
+
+            if child.is_directory {
+                let child_prefix = format!("{}{}", prefix, if is_last { glyphs.space } else { glyphs.pipe });
+                Self::render_children(child, &child_prefix, glyphs, output);
+            }
+        }
+    }
+}
+
+impl DiffHunk {
+    /// Context lines before the first change
+    pub fn before_context(&self) -> &[DiffLine] {
+        &self.lines[..self.changes_start()]
+    }
+
+    /// Context lines after the last change
+    pub fn after_context(&self) -> &[DiffLine] {
+        &self.lines[self.changes_end()..]
+    }
+
+    /// Lines from the first change through the last, i.e. the hunk without its
+    /// leading and trailing context. Context between two separate changes in
+    /// the same hunk is kept.
+    pub fn changed_lines(&self) -> &[DiffLine] {
+        &self.lines[self.changes_start()..self.changes_end()]
+    }
+
+    pub fn has_additions(&self) -> bool {
+        self.lines.iter().any(|line| line.kind == DiffLineKind::Addition)
+    }
+
+    pub fn has_deletions(&self) -> bool {
+        self.lines.iter().any(|line| line.kind == DiffLineKind::Deletion)
+    }
+
+    fn changes_start(&self) -> usize {
+        self.lines
+            .iter()
+            .position(|line| line.kind != DiffLineKind::Context)
+            .unwrap_or(self.lines.len())
+    }
+
+    fn changes_end(&self) -> usize {
+        self.lines
+            .iter()
+            .rposition(|line| line.kind != DiffLineKind::Context)
+            .map_or(self.lines.len(), |last| last + 1)
+    }
+}
+
+impl FileDiff {
+    pub fn total_additions(&self) -> usize {
+        self.count_lines(DiffLineKind::Addition)
+    }
+
+    pub fn total_deletions(&self) -> usize {
+        self.count_lines(DiffLineKind::Deletion)
+    }
+
+    fn count_lines(&self, kind: DiffLineKind) -> usize {
+        self.hunks
+            .iter()
+            .flat_map(|hunk| &hunk.lines)
+            .filter(|line| line.kind == kind)
+            .count()
+    }
+}
+
 impl DiffStatus {
     pub fn color_code(&self) -> &'static str {
         match self {
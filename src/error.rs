@@ -0,0 +1,43 @@
+use std::path::PathBuf;
+use thiserror::Error;
+
+/// Errors callers may want to match on. They are returned wrapped in
+/// `anyhow::Error`; use `downcast_ref::<DiffyError>()` to inspect them.
+#[derive(Debug, Error)]
+pub enum DiffyError {
+    /// A hunk's context or removed lines do not match the text it is applied to.
+    /// `hunk` is the zero-based index of the failing hunk.
+    #[error("Hunk {} does not apply: expected {expected:?}, found {actual:?}", hunk + 1)]
+    PatchConflict { hunk: usize, expected: String, actual: String },
+
+    /// A file exceeds `DiffOptions::max_file_size` and was not read
+    #[error("{} is {size} bytes, over the {limit} byte limit", path.display())]
+    FileTooLarge { path: PathBuf, size: u64, limit: u64 },
+
+    /// Two results passed to `DiffResult::merge` both contain `path`
+    #[error("{} is present in both diff results", path.display())]
+    OverlappingPath { path: PathBuf },
+
+    /// A `DiffFilter` code other than A, D, M, R or U in either case
+    #[error("Unknown diff filter '{code}', expected A, D, M, R or U (lowercase to exclude)")]
+    UnknownDiffFilter { code: char },
+}
+
+/// Malformed unified diff input. `line` is the one-based line number in the input.
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum ParseError {
+    #[error("line {line}: expected a '+++' header after '---'")]
+    MissingNewHeader { line: usize },
+
+    #[error("line {line}: hunk found before any '---'/'+++' file header")]
+    HunkWithoutFile { line: usize },
+
+    #[error("line {line}: invalid hunk header {text:?}")]
+    InvalidHunkHeader { line: usize, text: String },
+
+    #[error("line {line}: unexpected {text:?} inside a hunk")]
+    UnexpectedLine { line: usize, text: String },
+
+    #[error("line {line}: hunk ended early, expected {old_lines} old and {new_lines} new lines")]
+    TruncatedHunk { line: usize, old_lines: u32, new_lines: u32 },
+}
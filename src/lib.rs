@@ -1,7 +1,19 @@
+pub mod config;
+pub mod error;
 pub mod core;
 pub mod cli;
 pub mod web;
 
-pub use core::DiffyCore;
+#[cfg(feature = "client")]
+pub mod client;
+
+#[cfg(any(test, feature = "testing"))]
+pub mod testing;
+
+pub use config::DiffConfig;
+pub use error::{DiffyError, ParseError};
+pub use core::{DiffyCore, DiffyCoreBuilder};
 pub use cli::TuiApp;
-pub use web::{create_app, start_server};
\ No newline at end of file
+pub use web::{create_app, start_server};
+#[cfg(unix)]
+pub use web::start_server_unix;
\ No newline at end of file
@@ -1,71 +1,454 @@
-use anyhow::Result;
-use clap::Parser;
-use diffy::{DiffyCore, TuiApp, start_server};
-use std::path::PathBuf;
+use anyhow::{Context, Result};
+use clap::error::ErrorKind;
+use clap::{CommandFactory, Parser, Subcommand, ValueEnum, ValueHint};
+use diffy::cli::completions::{self, Shell};
+use diffy::cli::picker::{self, PathPicker};
+use diffy::cli::{pretty, DiffViewMode};
+use diffy::core::diff::DiffEngine;
+use diffy::core::export::{changed_files, unified_with_names, word_diff_text, ExportFormat};
+use diffy::core::hook::{self, HookOptions, HookStatus};
+use diffy::core::patch::{PatchAction, PatchPlan};
+use diffy::core::types::{DiffFilter, DiffResult};
+use diffy::{DiffConfig, DiffyCore, DiffyCoreBuilder, TuiApp, start_server};
+use std::io::{BufRead, Write};
+use std::path::{Path, PathBuf};
 use tracing::Level;
 
 #[derive(Parser)]
 #[command(name = "diffy")]
 #[command(about = "A modular CLI and web directory/file diff tool")]
 #[command(version = "0.1.0")]
+#[command(subcommand_negates_reqs = true)]
 struct Cli {
-    /// Left directory or file path
-    #[arg(long, short)]
-    left: PathBuf,
+    #[command(subcommand)]
+    command: Option<Command>,
 
-    /// Right directory or file path  
-    #[arg(long, short)]
-    right: PathBuf,
+    /// Left directory or file path (`-` with `--right -` reads both paths from stdin).
+    /// Without --left and --right, diffy asks for both in a terminal.
+    #[arg(long, short, value_hint = ValueHint::AnyPath)]
+    left: Option<PathBuf>,
+
+    /// Right directory or file path
+    #[arg(long, short, value_hint = ValueHint::AnyPath)]
+    right: Option<PathBuf>,
+
+    /// Compare the working tree of the git repository with stash@{N}
+    /// (default: the most recent stash)
+    #[arg(long, value_name = "N", num_args = 0..=1, default_missing_value = "0", conflicts_with_all = ["left", "right", "batch"])]
+    git_stash: Option<usize>,
+
+    /// Compare the tips of two branches of the git repository
+    #[arg(long, num_args = 2, value_names = ["BASE", "HEAD"], conflicts_with_all = ["left", "right", "batch", "git_stash"])]
+    git_branch: Option<Vec<String>>,
+
+    /// Compare the last commit of the git repository with its staged files
+    #[arg(long, conflicts_with_all = ["left", "right", "batch", "git_stash", "git_branch"])]
+    git_staged: bool,
+
+    /// Compare two snapshots (subdirectories) of DIR, chosen with --old and --new,
+    /// or read --since snapshots from DIR
+    #[arg(long, visible_alias = "snapshots-dir", value_name = "DIR", value_hint = ValueHint::DirPath, conflicts_with_all = ["left", "batch", "git_stash", "git_branch"])]
+    snapshot_dir: Option<PathBuf>,
+
+    /// Older snapshot for --snapshot-dir; a unique prefix of its name is enough
+    #[arg(long, value_name = "NAME", requires_all = ["snapshot_dir", "new"], conflicts_with_all = ["right", "since"])]
+    old: Option<String>,
+
+    /// Newer snapshot for --snapshot-dir; a unique prefix of its name is enough
+    #[arg(long, value_name = "NAME", requires_all = ["snapshot_dir", "old"], conflicts_with_all = ["right", "since"])]
+    new: Option<String>,
+
+    /// Compare --right with its snapshot from this long ago (`24h`, `7d`) or from
+    /// a date (`2023-01-15`). Snapshots are saved with `diffy snapshot` and read
+    /// from ~/.cache/diffy/snapshots/<directory name> unless --snapshot-dir is given.
+    #[arg(long, value_name = "WHEN", requires = "right", conflicts_with_all = ["left", "batch", "git_stash", "git_branch"])]
+    since: Option<String>,
+
+    /// Repository used by --git-stash, --git-branch, --git-staged and `diffy hook`
+    /// (default: the current directory)
+    #[arg(long, value_name = "PATH", value_hint = ValueHint::DirPath)]
+    repo: Option<PathBuf>,
+
+    /// Read `<left_path>\t<right_path>` lines from stdin and print a result for each pair
+    #[arg(long, conflicts_with_all = ["left", "right", "web"])]
+    batch: bool,
 
     /// Start web server instead of TUI
     #[arg(long)]
     web: bool,
 
     /// Port for web server (default: 3000)
-    #[arg(long, default_value = "3000")]
-    port: u16,
+    #[arg(long)]
+    port: Option<u16>,
+
+    /// Serve on a Unix domain socket instead of a TCP port (with --web)
+    #[cfg(unix)]
+    #[arg(long, value_name = "PATH", value_hint = ValueHint::FilePath, requires = "web", conflicts_with_all = ["port", "open"])]
+    socket: Option<PathBuf>,
 
     /// Open browser automatically when using --web
     #[arg(long)]
     open: bool,
 
+    /// Re-analyze the directories every N seconds when using --web
+    #[arg(long, value_name = "SECONDS")]
+    auto_reload_interval: Option<u64>,
+
     /// Enable verbose logging
     #[arg(long, short)]
     verbose: bool,
 
+    /// Log level: error, warn, info, debug or trace (overrides --verbose)
+    #[arg(long, value_name = "LEVEL")]
+    log_level: Option<Level>,
+
     /// Include files normally ignored by .gitignore
     #[arg(long)]
     include_ignored: bool,
+
+    /// List directories that contain no files on either side
+    #[arg(long)]
+    show_empty_dirs: bool,
+
+    /// Match left and right paths regardless of case (e.g. Linux vs macOS copies)
+    #[arg(long)]
+    case_insensitive: bool,
+
+    /// Ignore lines that contain only whitespace
+    #[arg(long)]
+    ignore_blank_lines: bool,
+
+    /// Highlight changed words within lines (file diffs and the TUI)
+    #[arg(long)]
+    word_diff: bool,
+
+    /// Render diff content in plain colors in the TUI
+    #[arg(long)]
+    no_syntax_highlighting: bool,
+
+    /// Print the result in this format instead of starting the TUI
+    #[arg(long, value_enum)]
+    format: Option<OutputFormat>,
+
+    /// Never color --format unified output (also set by the NO_COLOR environment variable)
+    #[arg(long)]
+    no_color: bool,
+
+    /// Print a plain-English summary of the changes instead of starting the TUI
+    #[arg(long, conflicts_with_all = ["format", "web"])]
+    explain: bool,
+
+    /// Write a report instead of starting the TUI: a `<file>.diff` for each changed
+    /// file, laid out like the compared directories, and an index.html linking them
+    #[arg(long, value_name = "DIR", value_hint = ValueHint::DirPath, conflicts_with_all = ["format", "explain", "web", "batch"])]
+    output_dir: Option<PathBuf>,
+
+    /// Open the report's index.html in the browser (with --output-dir)
+    #[arg(long, requires = "output_dir")]
+    open_report: bool,
+
+    /// Only list some kinds of change, as with git: A (added), D (deleted),
+    /// M (modified), R (renamed), U (unchanged); lowercase excludes a kind.
+    /// Applies to every output, including the TUI and web UI.
+    #[arg(long, value_name = "FILTER")]
+    diff_filter: Option<DiffFilter>,
+
+    /// Exit with status 1 if an added file matches one of these comma-separated
+    /// gitignore-style patterns, e.g. `.env,*.pem` (with --format)
+    #[arg(long, value_name = "PATTERNS", value_delimiter = ',', requires = "format")]
+    guard_patterns: Vec<String>,
+
+    /// Path to a configuration file (overrides .diffy.toml and the global config)
+    #[arg(long, global = true, value_hint = ValueHint::FilePath)]
+    config: Option<PathBuf>,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum OutputFormat {
+    /// File tree with status markers, like the `tree` command
+    Tree,
+    /// File tree using only ASCII characters
+    TreeAscii,
+    /// Unified diff of every changed file
+    Unified,
+    Markdown,
+    Html,
+    Json,
+    /// Counts of changed files and lines, like `git diff --shortstat`
+    Stats,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Inspect and manage configuration files
+    Config {
+        #[command(subcommand)]
+        action: ConfigCommand,
+    },
+
+    /// Save a hard-linked copy of a directory for later comparison with --since
+    Snapshot {
+        /// Directory to snapshot
+        #[arg(value_hint = ValueHint::DirPath)]
+        path: PathBuf,
+
+        /// Snapshot name (default: the current UTC time, e.g. 20240115-094500)
+        #[arg(long)]
+        name: Option<String>,
+
+        /// Where to save it (default: ~/.cache/diffy/snapshots/<directory name>)
+        #[arg(long, visible_alias = "snapshots-dir", value_name = "DIR", value_hint = ValueHint::DirPath)]
+        snapshot_dir: Option<PathBuf>,
+    },
+
+    /// Manage a git pre-commit hook that rejects commits adding sensitive files
+    Hook {
+        #[command(subcommand)]
+        action: HookCommand,
+    },
+
+    /// Apply a unified diff, such as the output of `--format unified`, to a directory
+    Apply {
+        /// Patch file to read
+        #[arg(value_hint = ValueHint::FilePath)]
+        patch: PathBuf,
+
+        /// Directory the paths in the patch are relative to
+        #[arg(value_hint = ValueHint::DirPath)]
+        target_dir: PathBuf,
+
+        /// Show what would change without modifying any files
+        #[arg(long)]
+        dry_run: bool,
+
+        /// Undo the patch instead of applying it
+        #[arg(long)]
+        reverse: bool,
+
+        /// Only check whether the patch applies cleanly (exit status 1 if not)
+        #[arg(long, conflicts_with = "dry_run")]
+        check: bool,
+    },
+
+    /// Print a shell completion script; installation steps are in its header
+    Completions {
+        /// Shell to complete for
+        #[arg(value_enum)]
+        shell: Shell,
+    },
+}
+
+#[derive(Subcommand)]
+enum HookCommand {
+    /// Write the pre-commit hook, which runs `diffy --git-staged --format stats --guard-patterns ...`
+    Install {
+        /// Compare this path instead of the last commit
+        #[arg(long, value_hint = ValueHint::AnyPath)]
+        left: Option<PathBuf>,
+
+        /// Compare this path instead of the staged files
+        #[arg(long, value_hint = ValueHint::AnyPath)]
+        right: Option<PathBuf>,
+
+        /// Comma-separated gitignore-style patterns of files that must not be added
+        /// (default: .env, private keys and keystores)
+        #[arg(long, value_name = "PATTERNS", value_delimiter = ',')]
+        guard_patterns: Vec<String>,
+
+        /// Replace a pre-commit hook that diffy did not write
+        #[arg(long)]
+        force: bool,
+    },
+
+    /// Remove the pre-commit hook written by `diffy hook install`
+    Uninstall,
+
+    /// Check that the installed hook is diffy's current one (exit status 1 if not)
+    Check,
+}
+
+#[derive(Subcommand)]
+enum ConfigCommand {
+    /// Check the configuration files and report unknown keys
+    Validate,
+
+    /// Write a commented configuration template
+    Generate {
+        /// Where to write the template
+        #[arg(long, short, default_value = diffy::config::GENERATED_CONFIG_FILE, value_hint = ValueHint::FilePath)]
+        output: PathBuf,
+
+        /// Overwrite the output file if it already exists
+        #[arg(long)]
+        force: bool,
+    },
 }
 
 #[tokio::main]
 async fn main() -> Result<()> {
     let cli = Cli::parse();
 
-    // Setup logging
-    let log_level = if cli.verbose { Level::DEBUG } else { Level::INFO };
+    // Setup logging, on stderr so it never mixes with --format output
+    let log_level = cli.log_level.unwrap_or(if cli.verbose { Level::DEBUG } else { Level::INFO });
     tracing_subscriber::fmt()
         .with_max_level(log_level)
+        .with_writer(std::io::stderr)
         .init();
 
+    if let Some(command) = &cli.command {
+        return run_command(command, &cli);
+    }
+
+    if cli.batch {
+        return run_batch(&cli);
+    }
+
+    let repo = match &cli.repo {
+        Some(repo) => repo.clone(),
+        None => std::env::current_dir()?,
+    };
+
+    if let Some(stash_index) = cli.git_stash {
+        let config = load_config(&cli)?;
+        let core = DiffyCore::from_git_stash(repo, stash_index)?.with_config(&config);
+        return run_comparison(core, &cli, &config).await;
+    }
+
+    if cli.git_staged {
+        let config = load_config(&cli)?;
+        let core = DiffyCore::from_git_staged(repo)?.with_config(&config);
+        return run_comparison(core, &cli, &config).await;
+    }
+
+    if let Some([base, head]) = cli.git_branch.as_deref() {
+        let config = load_config(&cli)?;
+        let core = DiffyCore::from_git_branches(repo, base, head)?.with_config(&config);
+        return run_comparison(core, &cli, &config).await;
+    }
+
+    if let Some(since) = &cli.since {
+        let config = load_config(&cli)?;
+        let right = cli.right.clone().expect("--since requires --right");
+        let core = DiffyCore::from_snapshot_since(right, cli.snapshot_dir.clone(), since)?.with_config(&config);
+        return run_comparison(core, &cli, &config).await;
+    }
+
+    if let Some(snapshot_dir) = &cli.snapshot_dir {
+        let (Some(old), Some(new)) = (&cli.old, &cli.new) else {
+            anyhow::bail!("--snapshot-dir needs --old and --new, or --since with --right");
+        };
+        let config = load_config(&cli)?;
+        let core = DiffyCore::from_snapshots(snapshot_dir.clone(), old, new)?.with_config(&config);
+        return run_comparison(core, &cli, &config).await;
+    }
+
+    let (left, right) = match (cli.left.clone(), cli.right.clone()) {
+        (Some(left), Some(right)) => (left, right),
+        (None, None) if picker::is_available() => match PathPicker::new(std::env::current_dir()?)?.run()? {
+            Some(paths) => paths,
+            None => return Ok(()),
+        },
+        (left, right) => {
+            let missing = match (left, right) {
+                (None, None) => "--left <LEFT>\n  --right <RIGHT>",
+                (None, Some(_)) => "--left <LEFT>",
+                _ => "--right <RIGHT>",
+            };
+            Cli::command()
+                .error(ErrorKind::MissingRequiredArgument, format!("the following required arguments were not provided:\n  {}", missing))
+                .exit()
+        }
+    };
+
+    // `-l - -r -` takes both paths from the first two lines of stdin
+    let (left, right) = if left == Path::new("-") && right == Path::new("-") {
+        DiffyCore::read_path_pair(std::io::stdin().lock())?
+    } else {
+        (left, right)
+    };
+
     // Validate paths exist
-    if !cli.left.exists() {
-        eprintln!("Error: Left path '{}' does not exist", cli.left.display());
+    if !left.exists() {
+        eprintln!("Error: Left path '{}' does not exist", left.display());
         std::process::exit(1);
     }
-    
-    if !cli.right.exists() {
-        eprintln!("Error: Right path '{}' does not exist", cli.right.display());
+
+    if !right.exists() {
+        eprintln!("Error: Right path '{}' does not exist", right.display());
         std::process::exit(1);
     }
 
+    // Quick single-file diff: print a unified diff instead of launching the TUI
+    if !cli.web && left.is_file() && right.is_file() {
+        let engine = DiffEngine::with_options(load_config(&cli)?.diff);
+        if cli.word_diff {
+            let left_text = std::fs::read_to_string(&left)?;
+            let right_text = std::fs::read_to_string(&right)?;
+            print!("{}", word_diff_text(&engine.word_diff(&left_text, &right_text)));
+            return Ok(());
+        }
+
+        let diff = engine.diff_files(&left, &right)?;
+        let output = unified_with_names(&left.display().to_string(), &right.display().to_string(), &diff);
+        return pretty::print_diff(&output, pretty::use_color(cli.no_color));
+    }
+
+    let config = load_config(&cli)?;
+
     // Create core diff engine
-    let core = DiffyCore::new_with_options(cli.left.clone(), cli.right.clone(), cli.include_ignored);
+    let core = DiffyCoreBuilder::from_config(&config)
+        .left(left)
+        .right(right)
+        .build()?;
+
+    run_comparison(core, &cli, &config).await
+}
+
+/// Print, serve or browse the comparison, depending on the flags
+async fn run_comparison(core: DiffyCore, cli: &Cli, config: &DiffConfig) -> Result<()> {
+    let core = with_diff_filter(core, cli);
+    if let Some(format) = cli.format {
+        let result = core.analyze()?;
+        let output = render(&core, &result, format)?;
+        if matches!(format, OutputFormat::Unified) {
+            pretty::print_diff(&output, pretty::use_color(cli.no_color))?;
+        } else {
+            print!("{}", output);
+        }
+
+        let guarded = core.guarded_files(&result, &cli.guard_patterns)?;
+        for path in &guarded {
+            eprintln!("❌ {} matches a guard pattern", path.display());
+        }
+        if !guarded.is_empty() {
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+
+    if cli.explain {
+        let result = core.analyze()?;
+        println!("{}", core.explain(&result));
+        return Ok(());
+    }
+
+    if let Some(output_dir) = &cli.output_dir {
+        let result = core.analyze()?;
+        let index = core.export_report(&result, output_dir)?;
+        println!("Wrote a report of {} changed file(s) to {}", changed_files(&result.tree).len(), index.display());
+        if cli.open_report {
+            if let Err(e) = webbrowser::open(&index.display().to_string()) {
+                eprintln!("Warning: Failed to open browser: {}", e);
+                eprintln!("Please manually open: {}", index.display());
+            }
+        }
+        return Ok(());
+    }
 
     if cli.web {
         // Open browser if requested
-        if cli.open {
-            let url = format!("http://127.0.0.1:{}", cli.port);
+        if config.web.open {
+            let url = format!("http://127.0.0.1:{}", config.web.port);
             if let Err(e) = webbrowser::open(&url) {
                 eprintln!("Warning: Failed to open browser: {}", e);
                 eprintln!("Please manually open: {}", url);
@@ -73,12 +456,280 @@ async fn main() -> Result<()> {
         }
 
         // Start web server
-        start_server(core, cli.port).await?;
+        let auto_reload = (config.web.auto_reload_interval > 0)
+            .then(|| std::time::Duration::from_secs(config.web.auto_reload_interval));
+        #[cfg(unix)]
+        if let Some(socket) = cli.socket.clone() {
+            return diffy::start_server_unix(core, socket, auto_reload).await;
+        }
+        start_server(core, config.web.port, auto_reload).await?;
     } else {
-        // Start TUI
-        let mut app = TuiApp::new(core);
+        // Start TUI; its file tree shows the similarity of every modified file
+        let mut core = core;
+        core.diff_options.compute_line_stats = true;
+        let mode = if cli.word_diff { DiffViewMode::WordDiff } else { DiffViewMode::Unified };
+        let mut app = TuiApp::new(core)
+            .with_config(&config.tui)?
+            .with_view_mode(mode)
+            .with_syntax_highlighting(!cli.no_syntax_highlighting);
         app.run()?;
     }
 
     Ok(())
-}
\ No newline at end of file
+}
+
+/// Load configuration, letting CLI flags take priority over every config source
+fn load_config(cli: &Cli) -> Result<DiffConfig> {
+    let mut config = DiffConfig::load_from(cli.config.as_deref())?;
+    config.tree.include_ignored |= cli.include_ignored;
+    config.tree.show_empty_dirs |= cli.show_empty_dirs;
+    config.tree.case_insensitive |= cli.case_insensitive;
+    config.diff.ignore_blank_lines |= cli.ignore_blank_lines;
+    config.web.open |= cli.open;
+    if cli.web {
+        // The web file tree shows +N/-N next to every changed file
+        config.diff.compute_line_stats = true;
+    }
+    if let Some(port) = cli.port {
+        config.web.port = port;
+    }
+    #[cfg(unix)]
+    if cli.socket.is_some() {
+        // There is no URL to open for a socket
+        config.web.open = false;
+    }
+    if let Some(seconds) = cli.auto_reload_interval {
+        config.web.auto_reload_interval = seconds;
+    }
+    Ok(config)
+}
+
+/// Apply `--diff-filter`, if given, to every analysis of `core`
+fn with_diff_filter(core: DiffyCore, cli: &Cli) -> DiffyCore {
+    match &cli.diff_filter {
+        Some(filter) => core.with_diff_filter(filter.clone()),
+        None => core,
+    }
+}
+
+fn render_result(core: &DiffyCore, format: OutputFormat) -> Result<String> {
+    render(core, &core.analyze()?, format)
+}
+
+fn render(core: &DiffyCore, result: &DiffResult, format: OutputFormat) -> Result<String> {
+    Ok(match format {
+        OutputFormat::Tree => result.to_tree_string(),
+        OutputFormat::TreeAscii => result.to_tree_string_ascii(),
+        OutputFormat::Unified => core.export(result, ExportFormat::Unified)?,
+        OutputFormat::Markdown => core.export(result, ExportFormat::Markdown)?,
+        OutputFormat::Html => core.export(result, ExportFormat::Html)?,
+        OutputFormat::Json => core.export(result, ExportFormat::Json)?,
+        OutputFormat::Stats => format!("{}\n", core.statistics(result)?.to_shortstat()),
+    })
+}
+
+/// Diff every tab-separated path pair on stdin, separating results with `---`.
+/// File pairs print a unified diff; directory pairs use `--format` (tree by default).
+fn run_batch(cli: &Cli) -> Result<()> {
+    let config = load_config(cli)?;
+    let mut stdout = std::io::stdout().lock();
+    let mut printed = 0;
+    let mut failures = 0;
+
+    for line in std::io::stdin().lock().lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let output = DiffyCore::parse_batch_line(&line).and_then(|(left, right)| {
+            if !left.exists() || !right.exists() {
+                anyhow::bail!("'{}' or '{}' does not exist", left.display(), right.display());
+            }
+            if left.is_file() && right.is_file() {
+                let diff = DiffEngine::with_options(config.diff.clone()).diff_files(&left, &right)?;
+                return Ok(unified_with_names(&left.display().to_string(), &right.display().to_string(), &diff));
+            }
+
+            let core = DiffyCoreBuilder::from_config(&config).left(left).right(right).build()?;
+            render_result(&with_diff_filter(core, cli), cli.format.unwrap_or(OutputFormat::Tree))
+        });
+
+        match output {
+            Ok(output) => {
+                if printed > 0 {
+                    stdout.write_all(b"---\n")?;
+                }
+                stdout.write_all(output.as_bytes())?;
+                printed += 1;
+            }
+            Err(e) => {
+                eprintln!("Error: {:#}", e);
+                failures += 1;
+            }
+        }
+    }
+
+    stdout.flush()?;
+    if failures > 0 {
+        eprintln!("{} pair(s) failed", failures);
+        std::process::exit(1);
+    }
+    Ok(())
+}
+
+fn run_command(command: &Command, cli: &Cli) -> Result<()> {
+    match command {
+        Command::Config { action: ConfigCommand::Validate } => validate_config(cli.config.as_deref()),
+        Command::Config { action: ConfigCommand::Generate { output, force } } => generate_config(output, *force),
+        Command::Hook { action } => run_hook_command(action, cli.repo.as_deref()),
+        Command::Snapshot { path, name, snapshot_dir } => save_snapshot(path, name.as_deref(), snapshot_dir.as_deref()),
+        Command::Apply { patch, target_dir, dry_run, reverse, check } => {
+            apply_patch(patch, target_dir, *dry_run, *reverse, *check)
+        }
+        Command::Completions { shell } => {
+            print!("{}", completions::generate(*shell, Cli::command()));
+            Ok(())
+        }
+    }
+}
+
+fn run_hook_command(action: &HookCommand, repo: Option<&Path>) -> Result<()> {
+    let repo = match repo {
+        Some(repo) => repo.to_path_buf(),
+        None => std::env::current_dir()?,
+    };
+
+    match action {
+        HookCommand::Install { left, right, guard_patterns, force } => {
+            let mut options = HookOptions { left: left.clone(), right: right.clone(), ..HookOptions::default() };
+            if !guard_patterns.is_empty() {
+                options.guard_patterns = guard_patterns.clone();
+            }
+            let path = hook::install(&repo, &options, *force)?;
+            println!("✅ Installed {} (guarding {})", path.display(), options.guard_patterns.join(", "));
+        }
+        HookCommand::Uninstall => {
+            if hook::uninstall(&repo)? {
+                println!("Removed the diffy pre-commit hook");
+            } else {
+                println!("No pre-commit hook is installed");
+            }
+        }
+        HookCommand::Check => match hook::check(&repo)? {
+            HookStatus::Current => println!("✅ The diffy pre-commit hook is up to date"),
+            status => {
+                let problem = match status {
+                    HookStatus::Missing => "No pre-commit hook is installed; run `diffy hook install`",
+                    HookStatus::Outdated => "The pre-commit hook is from another diffy version; run `diffy hook install`",
+                    _ => "The pre-commit hook was not written by diffy",
+                };
+                eprintln!("❌ {}", problem);
+                std::process::exit(1);
+            }
+        },
+    }
+    Ok(())
+}
+
+fn save_snapshot(path: &Path, name: Option<&str>, snapshot_dir: Option<&Path>) -> Result<()> {
+    let name = name.map_or_else(|| DiffyCore::snapshot_name(std::time::SystemTime::now()), str::to_string);
+    let snapshot_dir = match snapshot_dir {
+        Some(dir) => dir.to_path_buf(),
+        None => DiffyCore::default_snapshots_dir(path)?,
+    };
+    DiffyCore::snapshot_into(path, &snapshot_dir, &name)?;
+    println!("📸 Saved snapshot {} in {}", name, snapshot_dir.display());
+    Ok(())
+}
+
+/// Nothing is written unless every file in the patch applies
+fn apply_patch(patch: &Path, target_dir: &Path, dry_run: bool, reverse: bool, check: bool) -> Result<()> {
+    let text = std::fs::read_to_string(patch)
+        .with_context(|| format!("Failed to read patch {}", patch.display()))?;
+    let plan = PatchPlan::prepare(&text, target_dir, reverse)?;
+
+    for (path, reason) in &plan.conflicts {
+        eprintln!("❌ {}: {}", path.display(), reason);
+    }
+    if !plan.is_clean() {
+        if !check {
+            eprintln!("Patch does not apply; no files were changed");
+        }
+        std::process::exit(1);
+    }
+    if check {
+        println!("Patch applies cleanly to {} file(s)", plan.files.len());
+        return Ok(());
+    }
+
+    if !dry_run {
+        plan.write(target_dir)?;
+    }
+    for file in &plan.files {
+        let verb = match (&file.action, dry_run) {
+            (PatchAction::Create(_), true) => "would create",
+            (PatchAction::Modify(_), true) => "would modify",
+            (PatchAction::Delete, true) => "would delete",
+            (PatchAction::Create(_), false) => "created",
+            (PatchAction::Modify(_), false) => "modified",
+            (PatchAction::Delete, false) => "deleted",
+        };
+        println!("{} {} (+{} -{})", verb, file.path.display(), file.additions, file.deletions);
+    }
+    Ok(())
+}
+
+fn generate_config(output: &std::path::Path, force: bool) -> Result<()> {
+    if output.exists() && !force {
+        eprintln!("Error: '{}' already exists (use --force to overwrite)", output.display());
+        std::process::exit(1);
+    }
+
+    std::fs::write(output, DiffConfig::generate_template())?;
+
+    println!(
+        "Configuration written to {}. Edit it and run `diffy --config {} ...`.",
+        output.display(),
+        output.display()
+    );
+    Ok(())
+}
+
+fn validate_config(explicit_path: Option<&std::path::Path>) -> Result<()> {
+    let mut problems = 0;
+
+    for path in DiffConfig::file_sources(explicit_path) {
+        if !path.exists() {
+            continue;
+        }
+
+        match DiffConfig::unknown_keys(&path) {
+            Ok(unknown) if unknown.is_empty() => println!("✅ {}", path.display()),
+            Ok(unknown) => {
+                println!("⚠️  {}", path.display());
+                for key in unknown {
+                    println!("   unknown key: {}", key);
+                    problems += 1;
+                }
+            }
+            Err(e) => {
+                println!("❌ {}: {:#}", path.display(), e);
+                problems += 1;
+            }
+        }
+    }
+
+    if let Err(e) = DiffConfig::load_from(explicit_path) {
+        println!("❌ {:#}", e);
+        problems += 1;
+    }
+
+    if problems > 0 {
+        eprintln!("Configuration has {} problem(s)", problems);
+        std::process::exit(1);
+    }
+
+    println!("Configuration is valid");
+    Ok(())
+}
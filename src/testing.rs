@@ -0,0 +1,272 @@
+//! Helpers for building temporary left/right directory pairs in tests and
+//! asserting on the resulting diffs.
+//!
+//! Available to the crate's own tests and, with the `testing` feature, to
+//! integration tests and downstream crates.
+
+use crate::core::diff::DiffEngine;
+use crate::core::types::{DiffLineKind, DiffResult, DiffStatus, FileDiff, FileEntry};
+use crate::core::DiffyCore;
+use std::path::{Path, PathBuf};
+use tempfile::TempDir;
+
+/// A pair of temporary directories that are removed when the fixture is dropped.
+///
+/// ```ignore
+/// let fixture = TestFixture::new();
+/// fixture
+///     .write_left("src/main.rs", "fn main() {}")
+///     .write_right("src/main.rs", "fn main() { println!(\"hello\"); }");
+/// let result = fixture.core().analyze()?;
+/// ```
+pub struct TestFixture {
+    left: TempDir,
+    right: TempDir,
+}
+
+impl Default for TestFixture {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TestFixture {
+    pub fn new() -> Self {
+        Self {
+            left: TempDir::new().expect("Failed to create left temp dir"),
+            right: TempDir::new().expect("Failed to create right temp dir"),
+        }
+    }
+
+    pub fn left_path(&self) -> &Path {
+        self.left.path()
+    }
+
+    pub fn right_path(&self) -> &Path {
+        self.right.path()
+    }
+
+    pub fn write_left(&self, relative_path: &str, contents: &str) -> &Self {
+        Self::write(self.left.path(), relative_path, contents.as_bytes());
+        self
+    }
+
+    pub fn write_right(&self, relative_path: &str, contents: &str) -> &Self {
+        Self::write(self.right.path(), relative_path, contents.as_bytes());
+        self
+    }
+
+    /// Write the same contents to both sides
+    pub fn write_both(&self, relative_path: &str, contents: &str) -> &Self {
+        self.write_left(relative_path, contents).write_right(relative_path, contents)
+    }
+
+    pub fn write_left_binary(&self, relative_path: &str, bytes: &[u8]) -> &Self {
+        Self::write(self.left.path(), relative_path, bytes);
+        self
+    }
+
+    pub fn write_right_binary(&self, relative_path: &str, bytes: &[u8]) -> &Self {
+        Self::write(self.right.path(), relative_path, bytes);
+        self
+    }
+
+    pub fn delete_left(&self, relative_path: &str) -> &Self {
+        Self::delete(self.left.path(), relative_path);
+        self
+    }
+
+    pub fn delete_right(&self, relative_path: &str) -> &Self {
+        Self::delete(self.right.path(), relative_path);
+        self
+    }
+
+    /// Overwrite the left copy of `relative_path` with the right one
+    pub fn copy_right_to_left(&self, relative_path: &str) -> &Self {
+        let bytes = std::fs::read(self.right.path().join(relative_path))
+            .unwrap_or_else(|e| panic!("Failed to read right/{}: {}", relative_path, e));
+        Self::write(self.left.path(), relative_path, &bytes);
+        self
+    }
+
+    /// A `DiffyCore` over the fixture. The fixture must outlive the core.
+    pub fn core(&self) -> DiffyCore {
+        DiffyCore::new(self.left.path().to_path_buf(), self.right.path().to_path_buf())
+    }
+
+    /// Consume the fixture and return a `DiffyCore` over it. The directories are
+    /// kept on disk rather than cleaned up, so prefer [`TestFixture::core`] when
+    /// the fixture can stay in scope.
+    pub fn into_core(self) -> DiffyCore {
+        DiffyCore::new(self.left.keep(), self.right.keep())
+    }
+
+    fn write(root: &Path, relative_path: &str, bytes: &[u8]) {
+        let path: PathBuf = root.join(relative_path);
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)
+                .unwrap_or_else(|e| panic!("Failed to create {}: {}", parent.display(), e));
+        }
+        std::fs::write(&path, bytes)
+            .unwrap_or_else(|e| panic!("Failed to write {}: {}", path.display(), e));
+    }
+
+    fn delete(root: &Path, relative_path: &str) {
+        let path = root.join(relative_path);
+        let result = if path.is_dir() {
+            std::fs::remove_dir_all(&path)
+        } else {
+            std::fs::remove_file(&path)
+        };
+        result.unwrap_or_else(|e| panic!("Failed to delete {}: {}", path.display(), e));
+    }
+}
+
+/// Fluent assertions over a [`DiffResult`]. Failures panic with a listing of
+/// the actual tree so the mismatch is visible in test output.
+///
+/// ```ignore
+/// DiffAssert::new(&result)
+///     .has_file_count(3)
+///     .has_modified("src/main.rs")
+///     .file("src/main.rs")
+///     .has_addition("println!");
+/// ```
+pub struct DiffAssert<'a> {
+    result: &'a DiffResult,
+}
+
+impl<'a> DiffAssert<'a> {
+    pub fn new(result: &'a DiffResult) -> Self {
+        Self { result }
+    }
+
+    pub fn has_file_count(&self, expected: usize) -> &Self {
+        if self.result.total_files != expected {
+            panic!(
+                "Expected {} files, found {}\n{}",
+                expected, self.result.total_files, self.describe_tree()
+            );
+        }
+        self
+    }
+
+    pub fn has_added(&self, path: &str) -> &Self {
+        self.has_status(path, DiffStatus::Added)
+    }
+
+    pub fn has_removed(&self, path: &str) -> &Self {
+        self.has_status(path, DiffStatus::Removed)
+    }
+
+    pub fn has_modified(&self, path: &str) -> &Self {
+        self.has_status(path, DiffStatus::Modified)
+    }
+
+    pub fn has_unchanged(&self, path: &str) -> &Self {
+        self.has_status(path, DiffStatus::Unchanged)
+    }
+
+    pub fn has_conflicted(&self, path: &str) -> &Self {
+        self.has_status(path, DiffStatus::Conflicted)
+    }
+
+    /// Compute the file diff for `path` and return assertions over it
+    pub fn file(&self, path: &str) -> FileDiffAssert {
+        let diff = DiffEngine::new()
+            .diff_files(&self.result.left_path.join(path), &self.result.right_path.join(path))
+            .unwrap_or_else(|e| panic!("Failed to diff {}: {}", path, e));
+        FileDiffAssert { path: path.to_string(), diff }
+    }
+
+    fn has_status(&self, path: &str, expected: DiffStatus) -> &Self {
+        match Self::find(&self.result.tree, Path::new(path)) {
+            Some(entry) if entry.status == expected => self,
+            Some(entry) => panic!(
+                "Expected {} to be {:?}, but it is {:?}\n{}",
+                path, expected, entry.status, self.describe_tree()
+            ),
+            None => panic!(
+                "Expected {} to be {:?}, but it is not in the tree\n{}",
+                path, expected, self.describe_tree()
+            ),
+        }
+    }
+
+    fn find<'e>(entry: &'e FileEntry, path: &Path) -> Option<&'e FileEntry> {
+        if entry.relative_path == path {
+            return Some(entry);
+        }
+        entry.children.iter().find_map(|child| Self::find(child, path))
+    }
+
+    fn describe_tree(&self) -> String {
+        format!("Actual tree:\n{}", self.result.to_tree_string())
+    }
+}
+
+/// Assertions over the line-level diff of a single file
+pub struct FileDiffAssert {
+    path: String,
+    diff: FileDiff,
+}
+
+impl FileDiffAssert {
+    pub fn diff(&self) -> &FileDiff {
+        &self.diff
+    }
+
+    /// Assert that some added line contains `text`
+    pub fn has_addition(&self, text: &str) -> &Self {
+        self.has_line(DiffLineKind::Addition, text)
+    }
+
+    /// Assert that some deleted line contains `text`
+    pub fn has_deletion(&self, text: &str) -> &Self {
+        self.has_line(DiffLineKind::Deletion, text)
+    }
+
+    pub fn has_hunk_count(&self, expected: usize) -> &Self {
+        if self.diff.hunks.len() != expected {
+            panic!(
+                "Expected {} hunks in {}, found {}\n{}",
+                expected, self.path, self.diff.hunks.len(), self.describe_diff()
+            );
+        }
+        self
+    }
+
+    fn has_line(&self, kind: DiffLineKind, text: &str) -> &Self {
+        let found = self.diff.hunks
+            .iter()
+            .flat_map(|hunk| &hunk.lines)
+            .any(|line| line.kind == kind && line.content.contains(text));
+
+        if !found {
+            panic!(
+                "Expected a {:?} containing {:?} in {}\n{}",
+                kind, text, self.path, self.describe_diff()
+            );
+        }
+        self
+    }
+
+    fn describe_diff(&self) -> String {
+        let mut output = String::from("Actual diff:\n");
+        for hunk in &self.diff.hunks {
+            output.push_str(&format!(
+                "  @@ -{},{} +{},{} @@\n",
+                hunk.old_start, hunk.old_lines, hunk.new_start, hunk.new_lines
+            ));
+            for line in &hunk.lines {
+                let prefix = match line.kind {
+                    DiffLineKind::Addition => "+",
+                    DiffLineKind::Deletion => "-",
+                    DiffLineKind::Context => " ",
+                };
+                output.push_str(&format!("  {}{}\n", prefix, line.content));
+            }
+        }
+        output
+    }
+}
@@ -0,0 +1,66 @@
+//! Recently compared directory pairs, kept across runs of the web server
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+/// Most comparisons kept in the history file
+pub const MAX_HISTORY_ENTRIES: usize = 20;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HistoryEntry {
+    pub left_path: PathBuf,
+    pub right_path: PathBuf,
+    /// When the pair was last compared, as RFC 3339
+    pub timestamp: String,
+    pub total_files: usize,
+    pub changed_files: usize,
+}
+
+impl HistoryEntry {
+    /// An entry for a comparison made now
+    pub fn now(left_path: PathBuf, right_path: PathBuf, total_files: usize, changed_files: usize) -> Self {
+        Self {
+            left_path,
+            right_path,
+            timestamp: humantime::format_rfc3339_seconds(SystemTime::now()).to_string(),
+            total_files,
+            changed_files,
+        }
+    }
+}
+
+/// `$XDG_DATA_HOME/diffy/history.json`, falling back to
+/// `~/.local/share/diffy/history.json`
+pub fn history_path() -> Option<PathBuf> {
+    let data_home = std::env::var_os("XDG_DATA_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".local").join("share")))?;
+    Some(data_home.join("diffy").join("history.json"))
+}
+
+/// Entries in `path`, newest first; a missing file is an empty history
+pub fn load(path: &Path) -> Result<Vec<HistoryEntry>> {
+    let contents = match std::fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e).with_context(|| format!("Failed to read {}", path.display())),
+    };
+    serde_json::from_str(&contents).with_context(|| format!("Failed to parse {}", path.display()))
+}
+
+/// Put `entry` at the top of the history in `path`, replacing an earlier
+/// entry for the same pair and dropping the oldest beyond the limit
+pub fn record(path: &Path, entry: HistoryEntry) -> Result<()> {
+    let mut entries = load(path)?;
+    entries.retain(|e| e.left_path != entry.left_path || e.right_path != entry.right_path);
+    entries.insert(0, entry);
+    entries.truncate(MAX_HISTORY_ENTRIES);
+
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).with_context(|| format!("Failed to create {}", parent.display()))?;
+    }
+    std::fs::write(path, serde_json::to_string_pretty(&entries)?)
+        .with_context(|| format!("Failed to write {}", path.display()))
+}
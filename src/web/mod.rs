@@ -1,3 +1,6 @@
+pub mod history;
 pub mod server;
 
-pub use server::{create_app, start_server};
\ No newline at end of file
+pub use server::{create_app, start_server};
+#[cfg(unix)]
+pub use server::start_server_unix;
@@ -1,26 +1,351 @@
-use crate::core::{DiffyCore, types::{DiffResult, FileDiff}};
+use super::history::{self, HistoryEntry};
+use crate::core::{
+    export::ExportFormat,
+    types::{BlameLine, DiffResult, DiffStatistics, FileDiff, FileDiffBatch, SearchResult, SortMode},
+    DiffyCore,
+};
 use axum::{
     extract::{Query, State},
-    http::StatusCode,
-    response::{Html, Json},
-    routing::{get, get_service},
+    http::{header, HeaderMap, HeaderValue, Request, StatusCode},
+    response::{Html, IntoResponse, Json, Response},
+    routing::{get, get_service, post},
     Router,
 };
 use serde::{Deserialize, Serialize};
-use std::path::PathBuf;
-use std::sync::Arc;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
+use tower::ServiceBuilder;
+use tower_http::request_id::{MakeRequestId, PropagateRequestIdLayer, RequestId, SetRequestIdLayer};
 use tower_http::services::ServeDir;
+use tower_http::trace::{DefaultOnResponse, TraceLayer};
+use tower_http::LatencyUnit;
+use tracing::Level;
 use tokio::io::AsyncReadExt;
-use anyhow::Result;
+use tokio::task::JoinSet;
+use anyhow::{Context, Result};
+use uuid::Uuid;
+
+/// Sessions unused for this long are dropped
+const SESSION_TTL: Duration = Duration::from_secs(60 * 60);
+
+/// Most paths accepted by one `/api/files/batch` request
+const MAX_BATCH_PATHS: usize = 20;
 
 #[derive(Clone)]
 pub struct AppState {
     pub core: Arc<DiffyCore>,
+    /// Analysis of `core`, cleared by `POST /api/diff/reload`
+    pub cache: Arc<AnalysisCache>,
+    /// Extra comparisons created through `POST /api/sessions`
+    pub sessions: Arc<RwLock<HashMap<Uuid, Session>>>,
+    /// Sessions created for `git_branch` ranges, so each range is checked out once
+    branch_sessions: Arc<RwLock<HashMap<String, Uuid>>>,
+    /// Number of requests received since the server started
+    pub request_counter: Arc<AtomicU64>,
+    /// Where recent comparisons are recorded; `None` keeps no history
+    pub history_path: Option<PathBuf>,
+}
+
+/// A diff result with its ETag, computed once per analysis
+#[derive(Clone)]
+pub struct CachedDiff {
+    pub result: Arc<DiffResult>,
+    /// Quoted hex SHA-256 of `result` serialized as JSON
+    pub etag: String,
+}
+
+impl CachedDiff {
+    pub fn new(result: DiffResult) -> Result<Self> {
+        let digest = Sha256::digest(serde_json::to_vec(&result)?);
+        Ok(Self { result: Arc::new(result), etag: format!("\"{:x}\"", digest) })
+    }
+}
+
+/// The analysis of one comparison, run once and shared by every request for
+/// it, with each requested sort order computed once on top
+#[derive(Default)]
+pub struct AnalysisCache {
+    /// Results by sort mode; `SortMode::Name` is the analysis itself
+    results: RwLock<HashMap<SortMode, CachedDiff>>,
+    /// Held while analyzing so concurrent requests share one run
+    lock: tokio::sync::Mutex<()>,
+    /// Bumped by every reload; an analysis started before the latest reload
+    /// may be stale and is not cached
+    generation: AtomicU64,
+}
+
+impl AnalysisCache {
+    fn get(&self, sort: SortMode) -> Option<CachedDiff> {
+        self.results.read().ok().and_then(|results| results.get(&sort).cloned())
+    }
+
+    /// The analysis of `core` in `sort` order, running it on a blocking
+    /// thread the first time
+    pub async fn get_or_analyze(&self, core: &Arc<DiffyCore>, sort: SortMode) -> Result<CachedDiff> {
+        if let Some(cached) = self.get(sort) {
+            return Ok(cached);
+        }
+
+        let _running = self.lock.lock().await;
+        if let Some(cached) = self.get(sort) {
+            return Ok(cached);
+        }
+
+        let generation = self.generation.load(Ordering::SeqCst);
+        let by_name = self.get(SortMode::Name);
+        let core = core.clone();
+        let (requested, computed) = tokio::task::spawn_blocking(move || -> Result<(CachedDiff, Vec<(SortMode, CachedDiff)>)> {
+            let mut computed = Vec::new();
+            let by_name = match by_name {
+                Some(cached) => cached,
+                None => {
+                    let cached = CachedDiff::new(core.analyze()?)?;
+                    computed.push((SortMode::Name, cached.clone()));
+                    cached
+                }
+            };
+            if sort == SortMode::Name {
+                return Ok((by_name, computed));
+            }
+
+            let mut result = DiffResult::clone(&by_name.result);
+            result.tree.sort_children(sort.comparator());
+            let sorted = CachedDiff::new(result)?;
+            computed.push((sort, sorted.clone()));
+            Ok((sorted, computed))
+        })
+        .await??;
+
+        if let Ok(mut results) = self.results.write() {
+            if self.generation.load(Ordering::SeqCst) == generation {
+                results.extend(computed);
+            }
+        }
+        Ok(requested)
+    }
+
+    /// Forget every cached result. An analysis already running keeps its
+    /// result to itself, since the files may have changed after it read them.
+    pub fn clear(&self) {
+        if let Ok(mut results) = self.results.write() {
+            self.generation.fetch_add(1, Ordering::SeqCst);
+            results.clear();
+        }
+    }
+}
+
+pub struct Session {
+    pub core: Arc<DiffyCore>,
+    pub cache: Arc<AnalysisCache>,
+    pub last_used: Instant,
+}
+
+impl AppState {
+    pub fn new(core: DiffyCore) -> Self {
+        Self {
+            core: Arc::new(core),
+            cache: Arc::new(AnalysisCache::default()),
+            sessions: Arc::new(RwLock::new(HashMap::new())),
+            branch_sessions: Arc::new(RwLock::new(HashMap::new())),
+            request_counter: Arc::new(AtomicU64::new(0)),
+            history_path: None,
+        }
+    }
+
+    /// Record the server's comparison in the history once it is analyzed
+    fn record_history(&self) {
+        let Some(path) = self.history_path.clone() else { return };
+        if self.core.uses_checkouts() {
+            return;
+        }
+
+        let state = self.clone();
+        tokio::spawn(async move {
+            let recorded = state.analysis().await.and_then(|CachedDiff { result, .. }| {
+                let entry = HistoryEntry::now(
+                    state.core.left_path.canonicalize().unwrap_or_else(|_| state.core.left_path.clone()),
+                    state.core.right_path.canonicalize().unwrap_or_else(|_| state.core.right_path.clone()),
+                    result.total_files,
+                    result.added_count + result.removed_count + result.modified_count + result.conflict_count,
+                );
+                history::record(&path, entry)
+            });
+            if let Err(e) = recorded {
+                tracing::warn!("Recording the comparison history failed: {}", e);
+            }
+        });
+    }
+
+    /// The cached analysis of the server's comparison, running it if needed
+    pub async fn analysis(&self) -> Result<CachedDiff> {
+        self.cache.get_or_analyze(&self.core, SortMode::Name).await
+    }
+
+    /// Drop the cached analysis and start a fresh one in the background
+    pub fn reload(&self) {
+        Self::reanalyze(self.core.clone(), self.cache.clone());
+    }
+
+    /// Same as [`AppState::reload`] for the comparison of `session_id`, or the
+    /// server's own when none is given
+    fn reload_session(&self, session_id: Option<Uuid>) -> Result<()> {
+        let (core, cache) = self.comparison(session_id)?;
+        Self::reanalyze(core, cache);
+        Ok(())
+    }
+
+    fn reanalyze(core: Arc<DiffyCore>, cache: Arc<AnalysisCache>) {
+        cache.clear();
+        tokio::spawn(async move {
+            if let Err(e) = cache.get_or_analyze(&core, SortMode::Name).await {
+                tracing::warn!("Reloading the diff failed: {}", e);
+            }
+        });
+    }
+
+    /// The cached analysis for `session_id` in `sort` order, or the server's
+    /// own comparison's when no session is given
+    async fn analysis_for(&self, session_id: Option<Uuid>, sort: SortMode) -> Result<CachedDiff> {
+        let (core, cache) = self.comparison(session_id)?;
+        cache.get_or_analyze(&core, sort).await
+    }
+
+    /// The core for `session_id`, or the server's own comparison when none is
+    /// given. Looking a session up keeps it alive.
+    fn core_for(&self, session_id: Option<Uuid>) -> Result<Arc<DiffyCore>> {
+        Ok(self.comparison(session_id)?.0)
+    }
+
+    /// The core and analysis cache for `session_id`, or the server's own when
+    /// none is given
+    fn comparison(&self, session_id: Option<Uuid>) -> Result<(Arc<DiffyCore>, Arc<AnalysisCache>)> {
+        let Some(id) = session_id else {
+            return Ok((self.core.clone(), self.cache.clone()));
+        };
+
+        let mut sessions = self.sessions.write().map_err(|_| anyhow::anyhow!("Session store is poisoned"))?;
+        sessions.retain(|_, session| session.last_used.elapsed() < SESSION_TTL);
+        let session = sessions
+            .get_mut(&id)
+            .ok_or_else(|| anyhow::anyhow!("Unknown or expired session '{}'", id))?;
+        session.last_used = Instant::now();
+        Ok((session.core.clone(), session.cache.clone()))
+    }
+
+    /// Register a comparison of `left` and `right` using the server's diff options
+    fn create_session(&self, left: PathBuf, right: PathBuf) -> Result<Uuid> {
+        for path in [&left, &right] {
+            if !path.exists() {
+                anyhow::bail!("Path does not exist: {}", path.display());
+            }
+        }
+
+        self.insert_session(self.core.with_paths(left, right))
+    }
+
+    fn insert_session(&self, core: DiffyCore) -> Result<Uuid> {
+        let id = Uuid::new_v4();
+        let mut sessions = self.sessions.write().map_err(|_| anyhow::anyhow!("Session store is poisoned"))?;
+        sessions.retain(|_, session| session.last_used.elapsed() < SESSION_TTL);
+        sessions.insert(id, Session { core: Arc::new(core), cache: Arc::default(), last_used: Instant::now() });
+        Ok(id)
+    }
+
+    /// The session a request refers to: the one comparing the `git_branch`
+    /// range when given, otherwise `session_id`
+    async fn session_for(&self, session_id: Option<Uuid>, git_branch: Option<&str>) -> Result<Option<Uuid>> {
+        match git_branch {
+            Some(range) => self.branch_session(range).await.map(Some),
+            None => Ok(session_id),
+        }
+    }
+
+    /// The session comparing a `base..head` range of branches in the repository
+    /// that contains the server's left path, checked out on first use
+    async fn branch_session(&self, range: &str) -> Result<Uuid> {
+        let (base, head) = DiffyCore::parse_branch_range(range)?;
+
+        let existing = self.branch_sessions.read().ok().and_then(|ids| ids.get(range).copied());
+        if let Some(id) = existing.filter(|id| self.core_for(Some(*id)).is_ok()) {
+            return Ok(id);
+        }
+
+        let (repo, base, head) = (self.core.left_path.clone(), base.to_string(), head.to_string());
+        let core = tokio::task::spawn_blocking(move || DiffyCore::from_git_branches(repo, &base, &head)).await??;
+        let id = self.insert_session(core.with_settings_from(&self.core))?;
+        if let Ok(mut ids) = self.branch_sessions.write() {
+            ids.insert(range.to_string(), id);
+        }
+        Ok(id)
+    }
+}
+
+#[derive(Deserialize)]
+pub struct DiffQuery {
+    session_id: Option<Uuid>,
+    /// A `base..head` branch range to compare instead of the served paths
+    git_branch: Option<String>,
+    /// Order of entries within each directory; trees are built sorted by name
+    sort: Option<SortMode>,
 }
 
 #[derive(Deserialize)]
 pub struct FileQuery {
     path: String,
+    session_id: Option<Uuid>,
+    git_branch: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct FileBatchRequest {
+    paths: Vec<String>,
+    #[serde(default)]
+    session_id: Option<Uuid>,
+    #[serde(default)]
+    git_branch: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct SearchQuery {
+    q: String,
+    session_id: Option<Uuid>,
+    git_branch: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct CreateSessionRequest {
+    left: PathBuf,
+    right: PathBuf,
+}
+
+#[derive(Serialize)]
+pub struct CreateSessionResponse {
+    session_id: Uuid,
+}
+
+#[derive(Serialize)]
+pub struct ReloadResponse {
+    status: &'static str,
+}
+
+#[derive(Deserialize)]
+pub struct ExportQuery {
+    format: ExportFormat,
+    /// Restrict the export to a single file
+    path: Option<String>,
+    session_id: Option<Uuid>,
+    git_branch: Option<String>,
+}
+
+/// Selects the comparison for requests that take no other parameters
+#[derive(Deserialize)]
+pub struct SessionQuery {
+    session_id: Option<Uuid>,
+    git_branch: Option<String>,
 }
 
 #[derive(Serialize)]
@@ -49,15 +374,54 @@ impl<T> ApiResponse<T> {
 }
 
 pub fn create_app(core: DiffyCore) -> Router {
-    let state = AppState {
-        core: Arc::new(core),
-    };
+    router(AppState::new(core))
+}
+
+/// Gives requests without an `X-Request-ID` header a fresh UUID
+#[derive(Clone)]
+struct UuidRequestId;
+
+impl MakeRequestId for UuidRequestId {
+    fn make_request_id<B>(&mut self, _request: &Request<B>) -> Option<RequestId> {
+        HeaderValue::from_str(&Uuid::new_v4().to_string()).ok().map(RequestId::new)
+    }
+}
+
+fn router(state: AppState) -> Router {
+    // Every log record for a request carries its ID, method and URI; the
+    // response is logged with its status and latency
+    let request_counter = state.request_counter.clone();
+    let tracing = ServiceBuilder::new()
+        .layer(SetRequestIdLayer::x_request_id(UuidRequestId))
+        .layer(
+            TraceLayer::new_for_http()
+                .make_span_with(move |request: &Request<_>| {
+                    request_counter.fetch_add(1, Ordering::Relaxed);
+                    let request_id = request
+                        .headers()
+                        .get("x-request-id")
+                        .and_then(|id| id.to_str().ok())
+                        .unwrap_or_default();
+                    tracing::info_span!("request", request_id, method = %request.method(), uri = %request.uri())
+                })
+                .on_response(DefaultOnResponse::new().level(Level::INFO).latency_unit(LatencyUnit::Millis)),
+        )
+        .layer(PropagateRequestIdLayer::x_request_id());
 
     Router::new()
         .route("/", get(index_handler))
+        .route("/api/sessions", post(create_session_handler))
         .route("/api/diff", get(diff_handler))
+        .route("/api/diff/reload", post(reload_handler))
         .route("/api/file", get(file_diff_handler))
+        .route("/api/files/batch", post(file_batch_handler))
+        .route("/api/blame", get(blame_handler))
+        .route("/api/search", get(search_handler))
+        .route("/api/stats", get(stats_handler))
+        .route("/api/export", get(export_handler))
+        .route("/api/history", get(history_handler))
         .nest_service("/static", get_service(ServeDir::new("static")))
+        .layer(tracing)
         .with_state(state)
 }
 
@@ -65,24 +429,204 @@ async fn index_handler() -> Html<&'static str> {
     Html(INDEX_HTML)
 }
 
-async fn diff_handler(State(state): State<AppState>) -> Result<Json<ApiResponse<DiffResult>>, StatusCode> {
-    match state.core.analyze() {
-        Ok(result) => Ok(Json(ApiResponse::success(result))),
+async fn create_session_handler(
+    State(state): State<AppState>,
+    Json(request): Json<CreateSessionRequest>,
+) -> Result<Json<ApiResponse<CreateSessionResponse>>, StatusCode> {
+    match state.create_session(request.left, request.right) {
+        Ok(session_id) => Ok(Json(ApiResponse::success(CreateSessionResponse { session_id }))),
         Err(e) => Ok(Json(ApiResponse::error(e.to_string()))),
     }
 }
 
+/// Serves the result with an `ETag`, answering `304 Not Modified` when the
+/// client's `If-None-Match` already names it
+async fn diff_handler(
+    Query(params): Query<DiffQuery>,
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> Response {
+    let analyzed = match state.session_for(params.session_id, params.git_branch.as_deref()).await {
+        Ok(session_id) => state.analysis_for(session_id, params.sort.unwrap_or_default()).await,
+        Err(e) => Err(e),
+    };
+    let cached = match analyzed {
+        Ok(cached) => cached,
+        Err(e) => return Json(ApiResponse::<DiffResult>::error(e.to_string())).into_response(),
+    };
+
+    let cache_headers = [(header::ETAG, cached.etag.clone()), (header::CACHE_CONTROL, "no-cache".to_string())];
+    let not_modified = headers
+        .get(header::IF_NONE_MATCH)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|tags| tags.split(',').any(|tag| tag.trim() == cached.etag || tag.trim() == "*"));
+    if not_modified {
+        return (StatusCode::NOT_MODIFIED, cache_headers).into_response();
+    }
+
+    (cache_headers, Json(ApiResponse::success(DiffResult::clone(&cached.result)))).into_response()
+}
+
+async fn reload_handler(
+    Query(params): Query<SessionQuery>,
+    State(state): State<AppState>,
+) -> Json<ApiResponse<ReloadResponse>> {
+    let reloaded = match state.session_for(params.session_id, params.git_branch.as_deref()).await {
+        Ok(session_id) => state.reload_session(session_id),
+        Err(e) => Err(e),
+    };
+    match reloaded {
+        Ok(()) => Json(ApiResponse::success(ReloadResponse { status: "reloading" })),
+        Err(e) => Json(ApiResponse::error(e.to_string())),
+    }
+}
+
 async fn file_diff_handler(
     Query(params): Query<FileQuery>,
     State(state): State<AppState>,
 ) -> Result<Json<ApiResponse<FileDiff>>, StatusCode> {
     let path = PathBuf::from(&params.path);
-    match state.core.get_file_diff(&path) {
+    let core = state
+        .session_for(params.session_id, params.git_branch.as_deref())
+        .await
+        .and_then(|session_id| state.core_for(session_id));
+    match core.and_then(|core| core.get_file_diff(&path)) {
         Ok(diff) => Ok(Json(ApiResponse::success(diff))),
         Err(e) => Ok(Json(ApiResponse::error(e.to_string()))),
     }
 }
 
+async fn blame_handler(
+    Query(params): Query<FileQuery>,
+    State(state): State<AppState>,
+) -> Result<Json<ApiResponse<Vec<BlameLine>>>, StatusCode> {
+    let path = PathBuf::from(&params.path);
+    let core = state
+        .session_for(params.session_id, params.git_branch.as_deref())
+        .await
+        .and_then(|session_id| state.core_for(session_id));
+    let blame = match core {
+        Ok(core) => tokio::task::spawn_blocking(move || core.get_file_blame(&path, None))
+            .await
+            .map_err(anyhow::Error::from)
+            .and_then(|blame| blame),
+        Err(e) => Err(e),
+    };
+    match blame {
+        Ok(lines) => Ok(Json(ApiResponse::success(lines))),
+        Err(e) => Ok(Json(ApiResponse::error(e.to_string()))),
+    }
+}
+
+/// Diffs up to `MAX_BATCH_PATHS` files concurrently, so the UI can prefetch
+/// several files in one round trip
+async fn file_batch_handler(
+    State(state): State<AppState>,
+    Json(request): Json<FileBatchRequest>,
+) -> Result<Json<ApiResponse<FileDiffBatch>>, StatusCode> {
+    if request.paths.len() > MAX_BATCH_PATHS {
+        let message = format!("At most {} paths can be requested at once, got {}", MAX_BATCH_PATHS, request.paths.len());
+        return Ok(Json(ApiResponse::error(message)));
+    }
+
+    let session_id = state.session_for(request.session_id, request.git_branch.as_deref()).await;
+    let core = match session_id.and_then(|session_id| state.core_for(session_id)) {
+        Ok(core) => core,
+        Err(e) => return Ok(Json(ApiResponse::error(e.to_string()))),
+    };
+
+    let mut tasks = JoinSet::new();
+    for path in request.paths {
+        let core = core.clone();
+        tasks.spawn_blocking(move || {
+            let diff = core.get_file_diff(Path::new(&path));
+            (path, diff)
+        });
+    }
+
+    let mut batch = FileDiffBatch::default();
+    while let Some(joined) = tasks.join_next().await {
+        match joined {
+            Ok((path, Ok(diff))) => {
+                batch.diffs.insert(path, diff);
+            }
+            Ok((path, Err(e))) => {
+                batch.errors.insert(path, e.to_string());
+            }
+            Err(e) => return Ok(Json(ApiResponse::error(e.to_string()))),
+        }
+    }
+
+    Ok(Json(ApiResponse::success(batch)))
+}
+
+async fn search_handler(
+    Query(params): Query<SearchQuery>,
+    State(state): State<AppState>,
+) -> Result<Json<ApiResponse<Vec<SearchResult>>>, StatusCode> {
+    let analyzed = match state.session_for(params.session_id, params.git_branch.as_deref()).await {
+        Ok(session_id) => state.analysis_for(session_id, SortMode::Name).await,
+        Err(e) => Err(e),
+    };
+    match analyzed {
+        Ok(cached) => Ok(Json(ApiResponse::success(cached.result.search(&params.q)))),
+        Err(e) => Ok(Json(ApiResponse::error(e.to_string()))),
+    }
+}
+
+async fn stats_handler(
+    Query(params): Query<SessionQuery>,
+    State(state): State<AppState>,
+) -> Result<Json<ApiResponse<DiffStatistics>>, StatusCode> {
+    let statistics = async {
+        let session_id = state.session_for(params.session_id, params.git_branch.as_deref()).await?;
+        let cached = state.analysis_for(session_id, SortMode::Name).await?;
+        let core = state.core_for(session_id)?;
+        tokio::task::spawn_blocking(move || core.statistics(&cached.result)).await?
+    };
+    match statistics.await {
+        Ok(stats) => Ok(Json(ApiResponse::success(stats))),
+        Err(e) => Ok(Json(ApiResponse::error(e.to_string()))),
+    }
+}
+
+async fn export_handler(
+    Query(params): Query<ExportQuery>,
+    State(state): State<AppState>,
+) -> Result<Json<ApiResponse<String>>, StatusCode> {
+    let exported = async {
+        let session_id = state.session_for(params.session_id, params.git_branch.as_deref()).await?;
+        let cached = state.analysis_for(session_id, SortMode::Name).await?;
+        let core = state.core_for(session_id)?;
+        tokio::task::spawn_blocking(move || match &params.path {
+            Some(path) => {
+                let scoped = DiffyCore::analyze_file_range(&cached.result, &[PathBuf::from(path)])?;
+                core.export(&scoped, params.format)
+            }
+            None => core.export(&cached.result, params.format),
+        })
+        .await?
+    };
+
+    match exported.await {
+        Ok(output) => Ok(Json(ApiResponse::success(output))),
+        Err(e) => Ok(Json(ApiResponse::error(e.to_string()))),
+    }
+}
+
+/// The most recently compared pairs, newest first
+async fn history_handler(State(state): State<AppState>) -> Result<Json<ApiResponse<Vec<HistoryEntry>>>, StatusCode> {
+    let entries = match &state.history_path {
+        Some(path) => history::load(path),
+        None => Ok(Vec::new()),
+    };
+
+    match entries {
+        Ok(entries) => Ok(Json(ApiResponse::success(entries))),
+        Err(e) => Ok(Json(ApiResponse::error(e.to_string()))),
+    }
+}
+
 const INDEX_HTML: &str = r#"<!DOCTYPE html>
 <html lang="en">
 <head>
@@ -129,6 +673,80 @@ const INDEX_HTML: &str = r#"<!DOCTYPE html>
             padding: 5px;
         }
 
+        .tree-search {
+            display: flex;
+            align-items: center;
+            gap: 4px;
+            padding: 4px 4px 8px;
+        }
+
+        .tree-search input {
+            flex: 1;
+            min-width: 0;
+            background-color: #000000;
+            color: #ffffff;
+            border: 1px solid #333;
+            border-radius: 3px;
+            padding: 4px 6px;
+            font-size: 12px;
+        }
+
+        .tree-search input:focus {
+            outline: none;
+            border-color: #094771;
+        }
+
+        .search-clear {
+            background: none;
+            border: none;
+            color: #6e7681;
+            cursor: pointer;
+            font-size: 16px;
+            padding: 0 4px;
+        }
+
+        .search-clear:hover {
+            color: #ffffff;
+        }
+
+        .search-count {
+            color: #6e7681;
+            font-size: 11px;
+            padding: 0 4px 6px;
+        }
+
+        .line-summary {
+            margin-left: auto;
+            padding-left: 8px;
+            font-family: monospace;
+            font-size: 11px;
+            white-space: nowrap;
+        }
+
+        .line-additions { color: #4caf50; }
+        .line-deletions { color: #f44336; }
+
+        .similarity-bar {
+            flex: none;
+            width: 36px;
+            height: 4px;
+            margin-left: 8px;
+            background-color: #30363d;
+            border-radius: 2px;
+            overflow: hidden;
+        }
+
+        .similarity-bar span {
+            display: block;
+            height: 100%;
+        }
+
+        .file-item mark {
+            background-color: #ff980055;
+            color: inherit;
+            border-radius: 2px;
+        }
+
         .file-item {
             padding: 4px 8px;
             cursor: pointer;
@@ -180,6 +798,8 @@ const INDEX_HTML: &str = r#"<!DOCTYPE html>
         .status-removed { color: #f44336; }
         .status-modified { color: #ff9800; }
         .status-unchanged { color: #9e9e9e; }
+        .status-binary { color: #ba68c8; }
+        .status-conflicted { color: #e040fb; }
 
         .diff-panel {
             flex: 1;
@@ -272,47 +892,297 @@ const INDEX_HTML: &str = r#"<!DOCTYPE html>
             gap: 4px;
         }
 
-        .error {
-            color: #f44336;
-            padding: 20px;
-            text-align: center;
+        .error {
+            color: #f44336;
+            padding: 20px;
+            text-align: center;
+        }
+
+        .breadcrumb-bar {
+            display: flex;
+            align-items: center;
+            gap: 8px;
+            min-width: 0;
+            flex: 1;
+        }
+
+        .breadcrumb {
+            font-size: 16px;
+            font-weight: bold;
+            white-space: nowrap;
+            overflow: hidden;
+            min-width: 0;
+        }
+
+        .crumb-dir {
+            color: #9e9e9e;
+            cursor: pointer;
+        }
+
+        .crumb-dir:hover {
+            color: #ffffff;
+            text-decoration: underline;
+        }
+
+        .crumb-sep, .crumb-ellipsis {
+            color: #6e7681;
+            margin: 0 4px;
+        }
+
+        .copy-btn {
+            background: none;
+            border: none;
+            cursor: pointer;
+            font-size: 14px;
+            opacity: 0.7;
+        }
+
+        .copy-btn:hover { opacity: 1; }
+        .hidden { display: none !important; }
+
+        .modal {
+            position: fixed;
+            inset: 0;
+            background-color: #000000aa;
+            display: flex;
+            align-items: center;
+            justify-content: center;
+            z-index: 20;
+        }
+
+        .modal-content {
+            background-color: #1a1a1a;
+            border: 1px solid #333;
+            border-radius: 6px;
+            width: min(500px, 90vw);
+            max-height: 70vh;
+            display: flex;
+            flex-direction: column;
+        }
+
+        .modal-header {
+            display: flex;
+            justify-content: space-between;
+            align-items: center;
+            padding: 10px;
+            background-color: #252526;
+            border-bottom: 1px solid #333;
+            font-weight: bold;
+        }
+
+        .modal-body {
+            overflow-y: auto;
+            padding: 5px;
+        }
+
+        .export-menu {
+            position: relative;
+        }
+
+        .export-options {
+            position: absolute;
+            right: 0;
+            top: calc(100% + 4px);
+            background-color: #1a1a1a;
+            border: 1px solid #333;
+            border-radius: 4px;
+            min-width: 220px;
+            z-index: 15;
+        }
+
+        .export-option {
+            display: block;
+            width: 100%;
+            text-align: left;
+            background: none;
+            border: none;
+            color: #ffffff;
+            padding: 8px 12px;
+            font-size: 12px;
+            cursor: pointer;
+        }
+
+        .export-option:hover {
+            background-color: #2a2d2e;
+        }
+
+        .history-options {
+            min-width: 320px;
+            max-height: 400px;
+            overflow-y: auto;
+        }
+
+        .history-option .history-paths {
+            display: block;
+            white-space: nowrap;
+            overflow: hidden;
+            text-overflow: ellipsis;
+        }
+
+        .history-option .history-meta {
+            display: block;
+            color: #6e7681;
+            font-size: 11px;
+        }
+
+        .history-empty {
+            color: #6e7681;
+            padding: 8px 12px;
+            font-size: 12px;
+        }
+
+        .spinner {
+            display: inline-block;
+            width: 10px;
+            height: 10px;
+            margin-left: 6px;
+            border: 2px solid #6e7681;
+            border-top-color: #ffffff;
+            border-radius: 50%;
+            animation: spin 0.8s linear infinite;
+        }
+
+        @keyframes spin {
+            to { transform: rotate(360deg); }
+        }
+
+        .menu-btn {
+            display: none;
+            background: none;
+            border: 1px solid #333;
+            border-radius: 4px;
+            color: #ffffff;
+            font-size: 18px;
+            padding: 2px 10px;
+            cursor: pointer;
+        }
+
+        .mobile-diff {
+            display: none;
+            flex: 1;
+            overflow: auto;
+            font-family: monospace;
+            font-size: 12px;
+        }
+
+        .mobile-line {
+            white-space: pre-wrap;
+            word-break: break-all;
+            padding: 0 6px;
+        }
+
+        .mobile-line.hunk { color: #00bcd4; background-color: #1a1a1a; padding: 4px 6px; }
+        .mobile-line.add { background-color: #9ccc2c22; color: #4caf50; }
+        .mobile-line.del { background-color: #ff000022; color: #f44336; }
+        .mobile-line.ctx { color: #9e9e9e; }
+
+        /* Small screens: plain-text diff instead of Monaco, file tree as a bottom sheet */
+        @media (max-width: 767px) {
+            .menu-btn { display: block; }
+
+            .file-tree {
+                position: fixed;
+                left: 0;
+                right: 0;
+                bottom: 0;
+                width: 100%;
+                max-width: none;
+                height: 60vh;
+                z-index: 10;
+                border-right: none;
+                border-top: 1px solid #333;
+                border-radius: 12px 12px 0 0;
+                transform: translateY(100%);
+                transition: transform 0.25s ease-out;
+            }
+
+            .file-tree.open { transform: translateY(0); }
+
+            .diff-header { flex-wrap: wrap; gap: 8px; }
+            .mode-toggle { display: none; }
+            #diffEditor { display: none; }
+            .mobile-diff { display: block; }
         }
     </style>
 </head>
 <body>
     <div class="container">
         <div class="file-tree">
-            <div class="file-tree-header">
+            <div class="file-tree-header" id="treeTitle">
                 Files
             </div>
             <div class="file-tree-content" id="fileTree">
-                <div class="loading">Loading...</div>
+                <div class="tree-search">
+                    <input type="text" id="treeSearch" placeholder="Filter files..." autocomplete="off">
+                    <button id="treeSearchClear" class="search-clear" title="Clear filter">×</button>
+                </div>
+                <div class="search-count" id="searchCount"></div>
+                <div id="treeNodes">
+                    <div class="loading">Loading...</div>
+                </div>
             </div>
         </div>
         
         <div class="diff-panel">
             <div class="diff-header">
-                <h2 id="currentFile">Select a file to view diff</h2>
+                <button id="menuBtn" class="menu-btn" title="Files">☰</button>
+                <div class="breadcrumb-bar">
+                    <h2 id="currentFile" class="breadcrumb">Select a file to view diff</h2>
+                    <button id="copyPathBtn" class="copy-btn hidden" title="Copy path">📋</button>
+                </div>
                 <div class="diff-controls">
                     <div class="mode-toggle">
                         <button id="sideBySideBtn" class="mode-btn active">Side-by-Side</button>
                         <button id="unifiedBtn" class="mode-btn">Unified</button>
                     </div>
+                    <button id="refreshBtn" class="mode-btn" title="Re-scan both directories">⟳ Refresh</button>
+                    <div class="export-menu">
+                        <button id="historyBtn" class="mode-btn" title="Recently compared directories">Recent ▾</button>
+                        <div id="historyOptions" class="export-options history-options hidden"></div>
+                    </div>
+                    <div class="export-menu">
+                        <button id="exportBtn" class="mode-btn">Export ▾</button>
+                        <div id="exportOptions" class="export-options hidden">
+                            <button class="export-option" data-format="unified">Download as Patch (.patch)</button>
+                            <button class="export-option" data-format="html">Download as HTML</button>
+                            <button class="export-option" data-format="markdown">Download as Markdown</button>
+                        </div>
+                    </div>
                     <div class="stats" id="stats"></div>
                 </div>
             </div>
-            <div class="diff-content">
+            <div class="diff-content" id="diffContent">
                 <div id="diffEditor" style="width: 100%; height: 100%;"></div>
+                <div id="mobileDiff" class="mobile-diff">
+                    <div class="welcome">Tap ☰ to pick a file, swipe to move between changes</div>
+                </div>
             </div>
         </div>
     </div>
 
+    <div id="dirModal" class="modal hidden">
+        <div class="modal-content">
+            <div class="modal-header">
+                <span id="dirModalTitle"></span>
+                <button id="dirModalClose" class="search-clear" title="Close">×</button>
+            </div>
+            <div class="modal-body" id="dirModalBody"></div>
+        </div>
+    </div>
+
     <script src="https://unpkg.com/monaco-editor@0.45.0/min/vs/loader.js"></script>
     <script>
         let diffEditor;
         let diffResult = null;
         let currentDiff = null;
         let diffMode = 'side-by-side'; // 'side-by-side' or 'unified'
+        let sessionId = null; // Set when a recent comparison is opened instead of the served one
+
+        // Point an API URL at the open session, if any
+        function withSession(url) {
+            if (!sessionId) return url;
+            return `${url}${url.includes('?') ? '&' : '?'}session_id=${sessionId}`;
+        }
 
         require.config({ paths: { 'vs': 'https://unpkg.com/monaco-editor@0.45.0/min/vs' }});
         require(['vs/editor/editor.main'], function() {
@@ -370,43 +1240,158 @@ const INDEX_HTML: &str = r#"<!DOCTYPE html>
                 setDiffMode('unified');
             });
 
+            setupMobileNavigation();
+            setupBreadcrumb();
+            setupExportMenu();
+            setupHistoryMenu();
+            document.getElementById('refreshBtn').addEventListener('click', refreshDiff);
+
+            // Filter the tree as the user types, debounced to avoid re-rendering on every key
+            let searchTimer = null;
+            document.getElementById('treeSearch').addEventListener('input', (e) => {
+                clearTimeout(searchTimer);
+                searchTimer = setTimeout(() => setTreeFilter(e.target.value), 150);
+            });
+
+            document.getElementById('treeSearchClear').addEventListener('click', () => {
+                document.getElementById('treeSearch').value = '';
+                setTreeFilter('');
+            });
+
             loadDiffResult();
         });
 
-        async function loadDiffResult() {
+        // On refresh the user's expanded directories are kept as they are
+        async function loadDiffResult(refreshing = false) {
             try {
-                const response = await fetch('/api/diff');
+                const response = await fetch(withSession('/api/diff'));
                 const result = await response.json();
                 
                 if (result.success) {
                     diffResult = result.data;
-                    initializeCollapsedState(result.data.tree);
+                    fileDiffCache = new Map();
+                    showDescription(result.data.description);
+                    if (!refreshing) initializeCollapsedState(result.data.tree);
                     renderFileTree(result.data.tree);
                     updateStats(result.data);
+                    prefetchVisibleDiffs();
                 } else {
-                    document.getElementById('fileTree').innerHTML = 
+                    document.getElementById('treeNodes').innerHTML = 
                         `<div class="error">Error: ${result.error}</div>`;
                 }
             } catch (error) {
-                document.getElementById('fileTree').innerHTML = 
+                document.getElementById('treeNodes').innerHTML = 
                     `<div class="error">Failed to load diff result</div>`;
                 console.error('Error loading diff result:', error);
             }
         }
 
+        // Comparisons such as git branch ranges are named in the page title
+        function showDescription(description) {
+            document.getElementById('treeTitle').textContent = description || 'Files';
+            document.title = description ? `${description} - Diffy` : 'Diffy - Directory & File Diff Tool';
+        }
+
+        // Diffs fetched for the current result, keyed by relative path
+        let fileDiffCache = new Map();
+        const PREFETCH_COUNT = 10;
+
+        // Fetch the first modified files shown in the tree in one request, so
+        // they open instantly when clicked
+        async function prefetchVisibleDiffs() {
+            const cache = fileDiffCache;
+            const paths = [...document.querySelectorAll('.file-item[data-modified]')]
+                .map(item => item.dataset.path)
+                .filter(path => !cache.has(path))
+                .slice(0, PREFETCH_COUNT);
+            if (paths.length === 0) return;
+
+            try {
+                const response = await fetch('/api/files/batch', {
+                    method: 'POST',
+                    headers: { 'Content-Type': 'application/json' },
+                    body: JSON.stringify({ paths, session_id: sessionId })
+                });
+                const result = await response.json();
+                if (result.success) {
+                    for (const [path, diff] of Object.entries(result.data.diffs)) {
+                        cache.set(path, diff);
+                    }
+                }
+            } catch (error) {
+                console.error('Error prefetching file diffs:', error);
+            }
+        }
+
         let collapsedDirs = new Set(); // Track collapsed directories - default to collapsed
+        let treeFilter = ''; // Lowercased search text, empty when no filter is active
         
         function renderFileTree(tree, level = 0) {
-            const container = document.getElementById('fileTree');
+            const container = document.getElementById('treeNodes');
             container.innerHTML = '';
             renderTreeNode(tree, container, level);
+            updateSearchCount(tree);
+        }
+
+        function setTreeFilter(text) {
+            treeFilter = text.trim().toLowerCase();
+            if (diffResult) {
+                renderFileTree(diffResult.tree);
+            }
+        }
+
+        function matchesFilter(node) {
+            return node.relative_path.toLowerCase().includes(treeFilter);
+        }
+
+        // A node stays visible while filtering if it or any descendant matches
+        function hasVisibleMatch(node) {
+            if (!treeFilter) return true;
+            if (node.relative_path && matchesFilter(node)) return true;
+            return (node.children || []).some(hasVisibleMatch);
+        }
+
+        function countFiles(node, predicate) {
+            const own = !node.is_directory && predicate(node) ? 1 : 0;
+            return (node.children || []).reduce((sum, child) => sum + countFiles(child, predicate), own);
+        }
+
+        function updateSearchCount(tree) {
+            const count = document.getElementById('searchCount');
+            if (!treeFilter) {
+                count.textContent = '';
+                return;
+            }
+            const total = countFiles(tree, () => true);
+            const matching = countFiles(tree, matchesFilter);
+            count.textContent = `${matching} of ${total} files`;
+        }
+
+        // Fill `element` with `name`, wrapping the first filter match in <mark>
+        function renderHighlightedName(element, name) {
+            const start = treeFilter ? name.toLowerCase().indexOf(treeFilter) : -1;
+            if (start < 0) {
+                element.textContent = name;
+                return;
+            }
+            const mark = document.createElement('mark');
+            mark.textContent = name.slice(start, start + treeFilter.length);
+            element.append(name.slice(0, start), mark, name.slice(start + treeFilter.length));
         }
 
         function renderTreeNode(node, container, level, shouldRender = true) {
+            if (!hasVisibleMatch(node)) {
+                return;
+            }
+
             // Only show the item if it has a path (skip the root empty node)
             if (node.relative_path && node.relative_path !== '' && shouldRender) {
                 const item = document.createElement('div');
                 item.className = 'file-item';
+                item.dataset.path = node.relative_path;
+                if (!node.is_directory && node.status === 'Modified') {
+                    item.dataset.modified = '';
+                }
                 item.style.paddingLeft = `${level * 16 + 8}px`;
                 
                 const statusIcon = document.createElement('span');
@@ -422,7 +1407,7 @@ const INDEX_HTML: &str = r#"<!DOCTYPE html>
                 const expandIcon = document.createElement('span');
                 expandIcon.className = 'expand-icon';
                 if (node.is_directory) {
-                    const isCollapsed = collapsedDirs.has(node.relative_path);
+                    const isCollapsed = isDirectoryCollapsed(node);
                     expandIcon.textContent = isCollapsed ? '▶ ' : '▼ ';
                     expandIcon.style.cursor = 'pointer';
                     expandIcon.addEventListener('click', (e) => {
@@ -439,13 +1424,22 @@ const INDEX_HTML: &str = r#"<!DOCTYPE html>
                 
                 const fileName = document.createElement('span');
                 const pathParts = node.relative_path.split(/[/\\]/);
-                fileName.textContent = pathParts[pathParts.length - 1];
+                renderHighlightedName(fileName, pathParts[pathParts.length - 1]);
                 
                 item.appendChild(statusIcon);
                 item.appendChild(treeConnector);
                 item.appendChild(expandIcon);
                 item.appendChild(fileIcon);
                 item.appendChild(fileName);
+
+                const lineSummary = renderLineSummary(node);
+                if (lineSummary) {
+                    item.appendChild(lineSummary);
+                }
+                const similarityBar = renderSimilarityBar(node);
+                if (similarityBar) {
+                    item.appendChild(similarityBar);
+                }
                 
                 if (!node.is_directory) {
                     item.addEventListener('click', () => selectFile(node.relative_path, fileName.textContent));
@@ -459,7 +1453,7 @@ const INDEX_HTML: &str = r#"<!DOCTYPE html>
             
             // Render children only if directory is not collapsed
             if (node.children && node.children.length > 0) {
-                const isCollapsed = node.is_directory && collapsedDirs.has(node.relative_path);
+                const isCollapsed = node.is_directory && isDirectoryCollapsed(node);
                 const childLevel = node.relative_path === '' ? level : level + 1;
                 
                 // Sort children: directories first, then files
@@ -475,6 +1469,52 @@ const INDEX_HTML: &str = r#"<!DOCTYPE html>
             }
         }
 
+        // "+12/-3" for modified files; added and removed files only show their own side
+        function renderLineSummary(node) {
+            if (!node.line_summary) return null;
+
+            const summary = document.createElement('span');
+            summary.className = 'line-summary';
+            const status = node.status.toLowerCase();
+
+            if (status !== 'removed') {
+                const additions = document.createElement('span');
+                additions.className = 'line-additions';
+                additions.textContent = `+${node.line_summary.additions}`;
+                summary.appendChild(additions);
+            }
+            if (status === 'modified' || status === 'conflicted') {
+                summary.append('/');
+            }
+            if (status !== 'added') {
+                const deletions = document.createElement('span');
+                deletions.className = 'line-deletions';
+                deletions.textContent = `-${node.line_summary.deletions}`;
+                summary.appendChild(deletions);
+            }
+            return summary;
+        }
+
+        // Bar filled to the share of unchanged lines, from red (rewritten) to green
+        function renderSimilarityBar(node) {
+            if (node.similarity == null) return null;
+
+            const percent = Math.round(node.similarity * 100);
+            const bar = document.createElement('span');
+            bar.className = 'similarity-bar';
+            bar.title = `${percent}% similar`;
+            const fill = document.createElement('span');
+            fill.style.width = `${percent}%`;
+            fill.style.backgroundColor = `hsl(${Math.round(node.similarity * 120)}, 60%, 45%)`;
+            bar.appendChild(fill);
+            return bar;
+        }
+
+        // Directories are expanded while filtering so matches inside them are visible
+        function isDirectoryCollapsed(node) {
+            return !treeFilter && collapsedDirs.has(node.relative_path);
+        }
+
         function toggleDirectory(dirPath) {
             if (collapsedDirs.has(dirPath)) {
                 collapsedDirs.delete(dirPath);
@@ -531,37 +1571,66 @@ const INDEX_HTML: &str = r#"<!DOCTYPE html>
 
         async function selectFile(filePath, fileName) {
             document.querySelectorAll('.file-item').forEach(item => {
-                item.classList.remove('selected');
+                item.classList.toggle('selected', item.dataset.path === filePath);
             });
-            event.target.closest('.file-item').classList.add('selected');
             
-            document.getElementById('currentFile').textContent = fileName;
+            currentFilePath = filePath;
+            renderBreadcrumb(filePath);
+            document.querySelector('.file-tree').classList.remove('open');
             
+            const cached = fileDiffCache.get(filePath);
+            if (cached) {
+                currentDiff = { diff: cached, fileName };
+                displayDiff(cached, fileName);
+                return;
+            }
+
             try {
-                const response = await fetch(`/api/file?path=${encodeURIComponent(filePath)}`);
+                const response = await fetch(withSession(`/api/file?path=${encodeURIComponent(filePath)}`));
                 const result = await response.json();
                 
                 if (result.success) {
+                    fileDiffCache.set(filePath, result.data);
                     currentDiff = { diff: result.data, fileName };
                     displayDiff(result.data, fileName);
                 } else {
-                    const errorModel = monaco.editor.createModel(`Error: ${result.error}`, 'text');
-                    diffEditor.setModel({
-                        original: errorModel,
-                        modified: errorModel
-                    });
+                    showDiffError(`Error: ${result.error}`);
                 }
             } catch (error) {
-                const errorModel = monaco.editor.createModel('Error loading file content', 'text');
-                diffEditor.setModel({
-                    original: errorModel,
-                    modified: errorModel
-                });
+                showDiffError('Error loading file content');
                 console.error('Error loading file diff:', error);
             }
         }
 
+        function showDiffError(message) {
+            if (isMobile()) {
+                document.getElementById('mobileDiff').innerHTML = '';
+                const error = document.createElement('div');
+                error.className = 'error';
+                error.textContent = message;
+                document.getElementById('mobileDiff').appendChild(error);
+                return;
+            }
+
+            const errorModel = monaco.editor.createModel(message, 'text');
+            diffEditor.setModel({
+                original: errorModel,
+                modified: errorModel
+            });
+        }
+
         function displayDiff(diff, fileName) {
+            if (diff.binary && diff.binary.too_large) {
+                const size = Math.max(diff.binary.left_size || 0, diff.binary.right_size || 0);
+                showDiffError(`File too large to diff (${(size / (1024 * 1024)).toFixed(1)} MB)`);
+                return;
+            }
+
+            if (isMobile()) {
+                renderMobileDiff(diff);
+                return;
+            }
+
             const leftContent = diff.left_content || '';
             const rightContent = diff.right_content || '';
             
@@ -603,6 +1672,381 @@ const INDEX_HTML: &str = r#"<!DOCTYPE html>
         }
 
 
+        let currentFilePath = null;
+        const SWIPE_THRESHOLD = 50;
+
+        function splitPath(path) {
+            return path.split(/[/\\]/).filter(part => part !== '');
+        }
+
+        function setupBreadcrumb() {
+            document.getElementById('copyPathBtn').addEventListener('click', async () => {
+                if (!currentFilePath) return;
+                const button = document.getElementById('copyPathBtn');
+                try {
+                    await navigator.clipboard.writeText(splitPath(currentFilePath).join('/'));
+                    button.textContent = '✅';
+                } catch (error) {
+                    console.error('Failed to copy path:', error);
+                    button.textContent = '❌';
+                }
+                setTimeout(() => { button.textContent = '📋'; }, 1000);
+            });
+
+            const modal = document.getElementById('dirModal');
+            document.getElementById('dirModalClose').addEventListener('click', closeDirectoryModal);
+            modal.addEventListener('click', (e) => {
+                if (e.target === modal) closeDirectoryModal();
+            });
+            document.addEventListener('keydown', (e) => {
+                if (e.key === 'Escape') closeDirectoryModal();
+            });
+            window.addEventListener('resize', () => {
+                if (currentFilePath) renderBreadcrumb(currentFilePath);
+            });
+        }
+
+        // "src / core / diff.rs", dropping leading segments behind "..." when too wide
+        function renderBreadcrumb(filePath) {
+            const header = document.getElementById('currentFile');
+            const parts = splitPath(filePath);
+            document.getElementById('copyPathBtn').classList.remove('hidden');
+
+            for (let skip = 0; skip < parts.length; skip++) {
+                header.innerHTML = '';
+                if (skip > 0) {
+                    const ellipsis = document.createElement('span');
+                    ellipsis.className = 'crumb-ellipsis';
+                    ellipsis.textContent = '...';
+                    header.appendChild(ellipsis);
+                }
+
+                parts.forEach((part, index) => {
+                    if (index < skip) return;
+                    if (index > skip || skip > 0) {
+                        const sep = document.createElement('span');
+                        sep.className = 'crumb-sep';
+                        sep.textContent = '/';
+                        header.appendChild(sep);
+                    }
+
+                    const crumb = document.createElement('span');
+                    crumb.textContent = part;
+                    if (index < parts.length - 1) {
+                        const dirParts = parts.slice(0, index + 1);
+                        crumb.className = 'crumb-dir';
+                        crumb.title = dirParts.join('/');
+                        crumb.addEventListener('click', () => openDirectoryModal(dirParts));
+                    }
+                    header.appendChild(crumb);
+                });
+
+                if (header.scrollWidth <= header.clientWidth) break;
+            }
+        }
+
+        // Ask the server to re-scan both directories, then reload the tree and open file
+        async function refreshDiff() {
+            const button = document.getElementById('refreshBtn');
+            if (button.disabled) return;
+            button.disabled = true;
+            button.innerHTML = 'Refreshing<span class="spinner"></span>';
+
+            try {
+                await fetch(withSession('/api/diff/reload'), { method: 'POST' });
+                await loadDiffResult(true);
+                if (currentFilePath) {
+                    await selectFile(currentFilePath, splitPath(currentFilePath).pop());
+                }
+            } catch (error) {
+                console.error('Refresh failed:', error);
+            } finally {
+                button.disabled = false;
+                button.textContent = '⟳ Refresh';
+            }
+        }
+
+        const EXPORT_EXTENSIONS = { unified: 'patch', html: 'html', markdown: 'md' };
+
+        function setupExportMenu() {
+            const button = document.getElementById('exportBtn');
+            const options = document.getElementById('exportOptions');
+
+            button.addEventListener('click', (e) => {
+                e.stopPropagation();
+                options.classList.toggle('hidden');
+            });
+            document.addEventListener('click', () => options.classList.add('hidden'));
+
+            options.querySelectorAll('.export-option').forEach(option => {
+                option.addEventListener('click', () => {
+                    options.classList.add('hidden');
+                    exportDiff(option.dataset.format);
+                });
+            });
+        }
+
+        // The Recent menu is filled from the history each time it is opened
+        function setupHistoryMenu() {
+            const button = document.getElementById('historyBtn');
+            const options = document.getElementById('historyOptions');
+
+            button.addEventListener('click', async (e) => {
+                e.stopPropagation();
+                if (!options.classList.contains('hidden')) {
+                    options.classList.add('hidden');
+                    return;
+                }
+                await loadHistory(options);
+                options.classList.remove('hidden');
+            });
+            document.addEventListener('click', () => options.classList.add('hidden'));
+        }
+
+        async function loadHistory(options) {
+            options.innerHTML = '';
+            let entries = [];
+            try {
+                const response = await fetch('/api/history');
+                const result = await response.json();
+                if (result.success) entries = result.data;
+            } catch (error) {
+                console.error('Error loading history:', error);
+            }
+
+            if (entries.length === 0) {
+                const empty = document.createElement('div');
+                empty.className = 'history-empty';
+                empty.textContent = 'No recent comparisons';
+                options.appendChild(empty);
+                return;
+            }
+
+            for (const entry of entries) {
+                const option = document.createElement('button');
+                option.className = 'export-option history-option';
+                option.title = `${entry.left_path}\n${entry.right_path}`;
+
+                const paths = document.createElement('span');
+                paths.className = 'history-paths';
+                paths.textContent = `${splitPath(entry.left_path).pop()} ↔ ${splitPath(entry.right_path).pop()}`;
+                const meta = document.createElement('span');
+                meta.className = 'history-meta';
+                meta.textContent = `${entry.changed_files} of ${entry.total_files} files changed · ${new Date(entry.timestamp).toLocaleString()}`;
+                option.append(paths, meta);
+
+                option.addEventListener('click', () => {
+                    options.classList.add('hidden');
+                    openComparison(entry.left_path, entry.right_path);
+                });
+                options.appendChild(option);
+            }
+        }
+
+        // Start a session for a past pair and show it in place of the current comparison
+        async function openComparison(left, right) {
+            try {
+                const response = await fetch('/api/sessions', {
+                    method: 'POST',
+                    headers: { 'Content-Type': 'application/json' },
+                    body: JSON.stringify({ left, right })
+                });
+                const result = await response.json();
+                if (!result.success) {
+                    throw new Error(result.error);
+                }
+
+                sessionId = result.data.session_id;
+                currentFilePath = null;
+                currentDiff = null;
+                document.getElementById('currentFile').textContent = 'Select a file to view diff';
+                await loadDiffResult();
+            } catch (error) {
+                console.error('Opening comparison failed:', error);
+                alert(`Could not open comparison: ${error.message}`);
+            }
+        }
+
+        // Download the selected file's diff, or the whole comparison when nothing is selected
+        async function exportDiff(format) {
+            const button = document.getElementById('exportBtn');
+            if (button.disabled) return;
+            button.disabled = true;
+            button.innerHTML = 'Exporting<span class="spinner"></span>';
+
+            try {
+                let url = `/api/export?format=${format}`;
+                if (currentFilePath) {
+                    url += `&path=${encodeURIComponent(currentFilePath)}`;
+                }
+                const response = await fetch(withSession(url));
+                const result = await response.json();
+                if (!result.success) {
+                    throw new Error(result.error);
+                }
+
+                const baseName = currentFilePath ? splitPath(currentFilePath).pop() : 'diffy';
+                const blob = new Blob([result.data], { type: 'text/plain;charset=utf-8' });
+                const link = document.createElement('a');
+                link.href = URL.createObjectURL(blob);
+                link.download = `${baseName}.${EXPORT_EXTENSIONS[format]}`;
+                document.body.appendChild(link);
+                link.click();
+                link.remove();
+                URL.revokeObjectURL(link.href);
+            } catch (error) {
+                console.error('Export failed:', error);
+                alert(`Export failed: ${error.message}`);
+            } finally {
+                button.disabled = false;
+                button.textContent = 'Export ▾';
+            }
+        }
+
+        function openDirectoryModal(dirParts) {
+            if (!diffResult) return;
+
+            const files = changedFilePaths(diffResult.tree).filter(path => {
+                const parts = splitPath(path);
+                return parts.length > dirParts.length && dirParts.every((part, i) => parts[i] === part);
+            });
+
+            document.getElementById('dirModalTitle').textContent =
+                `Changed files in ${dirParts.join('/')}/ (${files.length})`;
+
+            const body = document.getElementById('dirModalBody');
+            body.innerHTML = '';
+            if (files.length === 0) {
+                const empty = document.createElement('div');
+                empty.className = 'welcome';
+                empty.textContent = 'No changed files';
+                body.appendChild(empty);
+            }
+
+            const statuses = new Map();
+            (function collect(node) {
+                statuses.set(node.relative_path, node.status);
+                (node.children || []).forEach(collect);
+            })(diffResult.tree);
+
+            files.forEach(path => {
+                const item = document.createElement('div');
+                item.className = 'file-item';
+
+                const status = statuses.get(path) || 'Unchanged';
+                const statusIcon = document.createElement('span');
+                statusIcon.className = `status-icon status-${status.toLowerCase()}`;
+                statusIcon.textContent = getStatusIcon(status);
+
+                const name = document.createElement('span');
+                name.textContent = splitPath(path).slice(dirParts.length).join('/');
+
+                item.append(statusIcon, name);
+                item.addEventListener('click', () => {
+                    closeDirectoryModal();
+                    const parts = splitPath(path);
+                    selectFile(path, parts[parts.length - 1]);
+                });
+                body.appendChild(item);
+            });
+
+            document.getElementById('dirModal').classList.remove('hidden');
+        }
+
+        function closeDirectoryModal() {
+            document.getElementById('dirModal').classList.add('hidden');
+        }
+
+        function isMobile() {
+            return window.innerWidth < 768;
+        }
+
+        function setupMobileNavigation() {
+            document.getElementById('menuBtn').addEventListener('click', () => {
+                document.querySelector('.file-tree').classList.toggle('open');
+            });
+
+            const content = document.getElementById('diffContent');
+            let touchStartX = 0;
+            let touchStartY = 0;
+            content.addEventListener('touchstart', (e) => {
+                touchStartX = e.changedTouches[0].clientX;
+                touchStartY = e.changedTouches[0].clientY;
+            }, { passive: true });
+            content.addEventListener('touchend', (e) => {
+                const dx = e.changedTouches[0].clientX - touchStartX;
+                const dy = e.changedTouches[0].clientY - touchStartY;
+                // Ignore vertical scrolling and short drags
+                if (Math.abs(dx) < SWIPE_THRESHOLD || Math.abs(dx) < Math.abs(dy)) return;
+                navigateChangedFile(dx > 0 ? 1 : -1);
+            }, { passive: true });
+
+            // Re-render the current diff when crossing the mobile breakpoint
+            let wasMobile = isMobile();
+            window.addEventListener('resize', () => {
+                if (isMobile() !== wasMobile) {
+                    wasMobile = isMobile();
+                    if (currentDiff) displayDiff(currentDiff.diff, currentDiff.fileName);
+                }
+            });
+        }
+
+        // Changed files in tree order, used for swipe navigation
+        function changedFilePaths(node, paths = []) {
+            if (!node.is_directory && node.status.toLowerCase() !== 'unchanged') {
+                paths.push(node.relative_path);
+            }
+            const children = [...(node.children || [])].sort((a, b) => {
+                if (a.is_directory !== b.is_directory) return a.is_directory ? -1 : 1;
+                return a.relative_path.localeCompare(b.relative_path);
+            });
+            children.forEach(child => changedFilePaths(child, paths));
+            return paths;
+        }
+
+        function navigateChangedFile(step) {
+            if (!diffResult) return;
+            const paths = changedFilePaths(diffResult.tree);
+            if (paths.length === 0) return;
+
+            const index = paths.indexOf(currentFilePath);
+            const next = index < 0 ? 0 : (index + step + paths.length) % paths.length;
+            const parts = paths[next].split(/[/\\]/);
+            selectFile(paths[next], parts[parts.length - 1]);
+        }
+
+        function renderMobileDiff(diff) {
+            const container = document.getElementById('mobileDiff');
+            container.innerHTML = '';
+
+            const addLine = (className, text) => {
+                const line = document.createElement('div');
+                line.className = `mobile-line ${className}`;
+                line.textContent = text;
+                container.appendChild(line);
+            };
+
+            if (diff.is_binary) {
+                addLine('ctx', 'Binary file differs');
+                return;
+            }
+            if (!diff.hunks || diff.hunks.length === 0) {
+                addLine('ctx', 'No changes');
+                return;
+            }
+
+            diff.hunks.forEach(hunk => {
+                addLine('hunk', `@@ -${hunk.old_start},${hunk.old_lines} +${hunk.new_start},${hunk.new_lines} @@`);
+                hunk.lines.forEach(line => {
+                    switch (line.kind) {
+                        case 'Addition': addLine('add', `+${line.content}`); break;
+                        case 'Deletion': addLine('del', `-${line.content}`); break;
+                        default: addLine('ctx', ` ${line.content}`);
+                    }
+                });
+            });
+        }
+
         function getLanguageFromFileName(fileName) {
             const ext = fileName.split('.').pop().toLowerCase();
             const languageMap = {
@@ -651,6 +2095,20 @@ const INDEX_HTML: &str = r#"<!DOCTYPE html>
                     <span class="status-icon status-modified">~</span>
                     <span>${diffResult.modified_count}</span>
                 </div>
+                ${diffResult.conflict_count ? `
+                <div class="stat-item">
+                    <span class="status-icon status-conflicted">!</span>
+                    <span>${diffResult.conflict_count}</span>
+                </div>` : ''}
+                <div class="stat-item">
+                    <span class="status-icon status-unchanged">=</span>
+                    <span>${diffResult.unchanged_count}</span>
+                </div>
+                ${diffResult.binary_count ? `
+                <div class="stat-item">
+                    <span class="status-icon status-binary">b</span>
+                    <span>${diffResult.binary_count}</span>
+                </div>` : ''}
                 <div class="stat-item">
                     <span>Total: ${diffResult.total_files}</span>
                 </div>
@@ -660,14 +2118,98 @@ const INDEX_HTML: &str = r#"<!DOCTYPE html>
 </body>  
 </html>"#;
 
-pub async fn start_server(core: DiffyCore, port: u16) -> Result<()> {
-    let app = create_app(core);
+/// Serve `core` on `port`. With `auto_reload_interval` the comparison is
+/// re-analyzed on that schedule, as if `POST /api/diff/reload` were called.
+pub async fn start_server(core: DiffyCore, port: u16, auto_reload_interval: Option<Duration>) -> Result<()> {
+    let app = server_app(core, auto_reload_interval);
     
     let listener = tokio::net::TcpListener::bind(format!("127.0.0.1:{}", port)).await?;
     
     println!("🚀 Diffy web server running at http://127.0.0.1:{}", port);
     println!("Press Ctrl+C or 'q' + Enter to quit");
     
+    // Run the server with graceful shutdown
+    axum::serve(listener, app)
+        .with_graceful_shutdown(shutdown_signal())
+        .await?;
+    
+    Ok(())
+}
+
+/// Serve `core` on a Unix domain socket at `socket_path`, e.g. for editor
+/// plugins on the same machine. The socket file is removed on exit.
+#[cfg(unix)]
+pub async fn start_server_unix(core: DiffyCore, socket_path: PathBuf, auto_reload_interval: Option<Duration>) -> Result<()> {
+    use hyper_util::rt::{TokioExecutor, TokioIo};
+    use hyper_util::server::conn::auto;
+    use hyper_util::service::TowerToHyperService;
+    use std::os::unix::fs::FileTypeExt;
+
+    /// Removes the socket file when the server stops, however it stops
+    struct SocketGuard(PathBuf);
+
+    impl Drop for SocketGuard {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_file(&self.0);
+        }
+    }
+
+    // A socket left behind by a crashed server would make the bind fail
+    if std::fs::symlink_metadata(&socket_path).is_ok_and(|metadata| metadata.file_type().is_socket()) {
+        std::fs::remove_file(&socket_path)?;
+    }
+
+    let app = server_app(core, auto_reload_interval);
+    let listener = tokio::net::UnixListener::bind(&socket_path)
+        .with_context(|| format!("Failed to bind {}", socket_path.display()))?;
+    let _guard = SocketGuard(socket_path.clone());
+
+    println!("🚀 Diffy web server listening on unix:{}", socket_path.display());
+    println!("Press Ctrl+C or 'q' + Enter to quit");
+
+    let shutdown = shutdown_signal();
+    tokio::pin!(shutdown);
+    loop {
+        let stream = tokio::select! {
+            accepted = listener.accept() => accepted?.0,
+            _ = &mut shutdown => break,
+        };
+
+        let service = TowerToHyperService::new(app.clone());
+        tokio::spawn(async move {
+            if let Err(e) = auto::Builder::new(TokioExecutor::new())
+                .serve_connection_with_upgrades(TokioIo::new(stream), service)
+                .await
+            {
+                tracing::debug!("Unix socket connection failed: {}", e);
+            }
+        });
+    }
+
+    Ok(())
+}
+
+/// The router for a running server, reloading on `auto_reload_interval`
+fn server_app(core: DiffyCore, auto_reload_interval: Option<Duration>) -> Router {
+    let mut state = AppState::new(core);
+    state.history_path = history::history_path();
+    state.record_history();
+    if let Some(period) = auto_reload_interval {
+        let state = state.clone();
+        tokio::spawn(async move {
+            let mut ticks = tokio::time::interval(period);
+            ticks.tick().await;
+            loop {
+                ticks.tick().await;
+                state.reload();
+            }
+        });
+    }
+    router(state)
+}
+
+/// Resolves on Ctrl+C or when 'q' is typed on stdin
+async fn shutdown_signal() {
     // Create a channel for shutdown signal
     let (shutdown_tx, shutdown_rx) = tokio::sync::oneshot::channel::<()>();
     
@@ -678,12 +2220,10 @@ pub async fn start_server(core: DiffyCore, port: u16) -> Result<()> {
         let mut buffer = [0u8; 1];
         
         loop {
-            if let Ok(_) = stdin.read(&mut buffer).await {
-                if buffer[0] == b'q' || buffer[0] == b'Q' {
-                    println!("Shutting down server...");
-                    let _ = keyboard_shutdown_tx.send(());
-                    break;
-                }
+            if stdin.read(&mut buffer).await.is_ok() && (buffer[0] == b'q' || buffer[0] == b'Q') {
+                println!("Shutting down server...");
+                let _ = keyboard_shutdown_tx.send(());
+                break;
             }
         }
     });
@@ -695,19 +2235,12 @@ pub async fn start_server(core: DiffyCore, port: u16) -> Result<()> {
             .expect("Failed to install Ctrl+C handler");
     };
     
-    // Run the server with graceful shutdown
-    axum::serve(listener, app)
-        .with_graceful_shutdown(async {
-            tokio::select! {
-                _ = ctrl_c => {
-                    println!("\nReceived Ctrl+C, shutting down...");
-                }
-                _ = shutdown_rx => {
-                    println!("Keyboard shutdown signal received");
-                }
-            }
-        })
-        .await?;
-    
-    Ok(())
-}
\ No newline at end of file
+    tokio::select! {
+        _ = ctrl_c => {
+            println!("\nReceived Ctrl+C, shutting down...");
+        }
+        _ = shutdown_rx => {
+            println!("Keyboard shutdown signal received");
+        }
+    }
+}
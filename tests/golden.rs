@@ -0,0 +1,76 @@
+//! Golden file tests for the export formats.
+//!
+//! Every directory under `tests/fixtures/` holds a `left/` and `right/` pair. Each
+//! pair is analyzed and exported in every format (plus the plain-text tree), and
+//! the output is compared with `tests/golden/<fixture>.<ext>.golden`. Missing
+//! golden files are written on the first run; set `DIFFY_UPDATE_GOLDEN=1` to
+//! regenerate all of them.
+
+use diffy::core::export::ExportFormat;
+use diffy::DiffyCore;
+use std::path::{Path, PathBuf};
+
+const FIXTURES_DIR: &str = "tests/fixtures";
+const GOLDEN_DIR: &str = "tests/golden";
+
+fn check_fixture(name: &str) {
+    // Relative paths keep machine-specific prefixes out of the golden output
+    let fixture = Path::new(FIXTURES_DIR).join(name);
+    let core = DiffyCore::new(fixture.join("left"), fixture.join("right"));
+    let result = core.analyze().expect("analysis failed");
+
+    for format in ExportFormat::ALL {
+        let actual = core.export(&result, format).expect("export failed");
+        compare_golden(name, format.extension(), &actual);
+    }
+
+    compare_golden(name, "tree", &result.to_tree_string());
+}
+
+fn compare_golden(fixture: &str, extension: &str, actual: &str) {
+    let golden_path = golden_path(fixture, extension);
+    let update = std::env::var("DIFFY_UPDATE_GOLDEN").is_ok_and(|value| value == "1");
+
+    if update || !golden_path.exists() {
+        std::fs::create_dir_all(GOLDEN_DIR).unwrap();
+        std::fs::write(&golden_path, actual).unwrap();
+        return;
+    }
+
+    let expected = std::fs::read_to_string(&golden_path).unwrap();
+    assert_eq!(
+        expected, actual,
+        "{} output for fixture '{}' differs from {} (rerun with DIFFY_UPDATE_GOLDEN=1 if intended)",
+        extension, fixture, golden_path.display()
+    );
+}
+
+fn golden_path(fixture: &str, extension: &str) -> PathBuf {
+    Path::new(GOLDEN_DIR).join(format!("{}.{}.golden", fixture, extension))
+}
+
+#[test]
+fn identical_directories() {
+    check_fixture("identical");
+}
+
+#[test]
+fn single_added_file() {
+    check_fixture("added_file");
+}
+
+#[test]
+fn modified_file() {
+    check_fixture("modified_file");
+}
+
+// Exports deliberately don't pair renames, so this stays a removal plus an addition
+#[test]
+fn renamed_file() {
+    check_fixture("renamed_file");
+}
+
+#[test]
+fn binary_file_change() {
+    check_fixture("binary_change");
+}
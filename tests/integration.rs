@@ -0,0 +1,1459 @@
+//! End-to-end tests against real temporary directories.
+
+use diffy::client::DiffyClient;
+use diffy::core::diff::DiffEngine;
+use diffy::core::export::ExportFormat;
+use diffy::core::patch::{PatchAction, PatchPlan};
+use diffy::core::types::{BinaryFileDiff, DEFAULT_RENAME_THRESHOLD, DiffFilter, DiffLineKind, DiffOptions, DiffStatus, FileEntry, LineSummary, SortMode, WordDiffLine, WordSpan};
+use diffy::testing::{DiffAssert, TestFixture};
+use diffy::web::history::{self, HistoryEntry};
+use diffy::{DiffConfig, DiffyCore, DiffyError, ParseError, TuiApp};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+#[test]
+fn identical_directories_have_no_changes() {
+    let fixture = TestFixture::new();
+    fixture
+        .write_both("README.md", "# Project\n")
+        .write_both("src/lib.rs", "pub fn answer() -> u32 { 42 }\n");
+
+    let result = fixture.core().analyze().unwrap();
+
+    DiffAssert::new(&result)
+        .has_file_count(2)
+        .has_unchanged("README.md")
+        .has_unchanged("src/lib.rs");
+    assert_eq!(result.added_count + result.removed_count + result.modified_count, 0);
+}
+
+#[test]
+fn added_file_is_reported() {
+    let fixture = TestFixture::new();
+    fixture
+        .write_both("src/main.rs", "fn main() {}\n")
+        .write_right("src/new_module.rs", "fn new_function() {}\n");
+
+    let result = fixture.core().analyze().unwrap();
+
+    assert_eq!(result.added_count, 1);
+    DiffAssert::new(&result)
+        .has_added("src/new_module.rs")
+        .file("src/new_module.rs")
+        .has_addition("fn new_function");
+}
+
+#[test]
+fn modified_and_removed_files_are_reported() {
+    let fixture = TestFixture::new();
+    fixture
+        .write_left("src/main.rs", "fn main() {\n    old_code();\n}\n")
+        .write_right("src/main.rs", "fn main() {\n    println!(\"hello\");\n}\n")
+        .write_left("obsolete.txt", "gone\n");
+
+    let result = fixture.core().analyze().unwrap();
+
+    DiffAssert::new(&result)
+        .has_removed("obsolete.txt")
+        .has_modified("src/main.rs")
+        .file("src/main.rs")
+        .has_deletion("old_code")
+        .has_addition("println!")
+        .has_hunk_count(1);
+}
+
+#[test]
+fn two_files_can_be_compared_directly() {
+    let fixture = TestFixture::new();
+    fixture
+        .write_left("config.toml", "port = 3000\n")
+        .write_right("config.toml", "port = 8080\n");
+
+    let diff = DiffyCore::diff_two_files(
+        fixture.left_path().join("config.toml"),
+        fixture.right_path().join("config.toml"),
+    )
+    .unwrap();
+
+    assert_eq!(diff.total_deletions(), 1);
+    assert_eq!(diff.total_additions(), 1);
+}
+
+#[test]
+fn binary_files_mixed_with_text() {
+    let fixture = TestFixture::new();
+    fixture
+        .write_left_binary("assets/logo.png", &[0x89, b'P', b'N', b'G', 0, 1, 2])
+        .write_right_binary("assets/logo.png", &[0x89, b'P', b'N', b'G', 0, 3, 4])
+        .write_left("notes.txt", "first\n")
+        .write_right("notes.txt", "second\n")
+        .write_left("same.txt", "same\n")
+        .write_right("same.txt", "same\n");
+
+    let result = fixture.core().analyze().unwrap();
+
+    let assert = DiffAssert::new(&result);
+    assert.has_modified("assets/logo.png").has_modified("notes.txt");
+    assert.file("notes.txt").has_addition("second");
+
+    let binary = assert.file("assets/logo.png");
+    assert!(binary.diff().is_binary);
+    assert!(binary.diff().hunks.is_empty());
+
+    assert_eq!((result.modified_count, result.unchanged_count, result.binary_count), (1, 1, 1));
+    assert_eq!(
+        result.total_files,
+        result.added_count + result.removed_count + result.modified_count
+            + result.unchanged_count + result.binary_count + result.conflict_count
+    );
+}
+
+#[test]
+fn empty_directory_against_populated_one() {
+    let fixture = TestFixture::new();
+    fixture
+        .write_right("a.txt", "a\n")
+        .write_right("nested/b.txt", "b\n");
+
+    let result = fixture.core().analyze().unwrap();
+
+    DiffAssert::new(&result)
+        .has_file_count(2)
+        .has_added("a.txt")
+        .has_added("nested")
+        .has_added("nested/b.txt");
+    assert_eq!(result.added_count, 2);
+}
+
+#[test]
+fn copying_a_file_across_makes_it_unchanged() {
+    let fixture = TestFixture::new();
+    fixture
+        .write_left("data.csv", "a,b\n")
+        .write_right("data.csv", "a,b,c\n");
+    DiffAssert::new(&fixture.core().analyze().unwrap()).has_modified("data.csv");
+
+    fixture.copy_right_to_left("data.csv");
+    DiffAssert::new(&fixture.core().analyze().unwrap()).has_unchanged("data.csv");
+}
+
+// Only contents are compared, so a permission-only change is not a modification
+#[cfg(unix)]
+#[test]
+fn permission_only_difference_is_unchanged() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let fixture = TestFixture::new();
+    fixture.write_both("run.sh", "#!/bin/sh\necho hi\n");
+    std::fs::set_permissions(
+        fixture.right_path().join("run.sh"),
+        std::fs::Permissions::from_mode(0o755),
+    )
+    .unwrap();
+
+    let result = fixture.core().analyze().unwrap();
+
+    DiffAssert::new(&result).has_unchanged("run.sh");
+}
+
+#[tokio::test]
+async fn web_api_serves_diff_result() {
+    let fixture = TestFixture::new();
+    fixture
+        .write_both("same.txt", "same\n")
+        .write_right("added.txt", "new\n");
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap();
+    let app = diffy::create_app(fixture.core());
+    tokio::spawn(async move {
+        axum::serve(listener, app).await.unwrap();
+    });
+
+    let response: serde_json::Value = reqwest::get(format!("http://{}/api/diff", address))
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+
+    assert_eq!(response["success"], true);
+    assert_eq!(response["data"]["added_count"], 1);
+    assert_eq!(response["data"]["total_files"], 2);
+
+    let http = reqwest::Client::new();
+    let first = http.get(format!("http://{}/api/diff", address)).send().await.unwrap();
+    let etag = first.headers()["etag"].to_str().unwrap().to_string();
+    assert_eq!(first.headers()["cache-control"], "no-cache");
+    let request_id = first.headers()["x-request-id"].to_str().unwrap();
+    assert!(uuid::Uuid::parse_str(request_id).is_ok());
+    let revalidated = http
+        .get(format!("http://{}/api/diff", address))
+        .header("If-None-Match", &etag)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(revalidated.status(), reqwest::StatusCode::NOT_MODIFIED);
+    let stale = http
+        .get(format!("http://{}/api/diff", address))
+        .header("If-None-Match", "\"stale\"")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(stale.status(), reqwest::StatusCode::OK);
+
+    let by_size: serde_json::Value = reqwest::get(format!("http://{}/api/diff?sort=size", address))
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(by_size["data"]["tree"]["children"][0]["relative_path"], "same.txt");
+
+    let export: serde_json::Value = reqwest::get(format!("http://{}/api/export?format=unified&path=added.txt", address))
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(export["data"], "--- /dev/null\n+++ b/added.txt\n@@ -0,0 +1,1 @@\n+new\n");
+}
+
+#[tokio::test]
+async fn client_reads_every_endpoint() {
+    let fixture = TestFixture::new();
+    fixture
+        .write_both("same.txt", "same\n")
+        .write_left("notes.txt", "one\ntwo\n")
+        .write_right("notes.txt", "one\nthree\nfour\n");
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap();
+    let app = diffy::create_app(fixture.core());
+    tokio::spawn(async move {
+        axum::serve(listener, app).await.unwrap();
+    });
+
+    let client = DiffyClient::new(&format!("http://{}", address)).unwrap();
+
+    assert_eq!(client.get_diff().await.unwrap().modified_count, 1);
+    assert_eq!(client.get_file_diff(Path::new("notes.txt")).await.unwrap().total_additions(), 2);
+
+    let blame = client.get_blame(Path::new("notes.txt")).await.unwrap();
+    assert_eq!(blame.iter().map(|line| line.content.as_str()).collect::<Vec<_>>(), ["one", "three", "four"]);
+
+    let batch = client.get_file_diffs(&[Path::new("notes.txt"), Path::new("same.txt")]).await.unwrap();
+    assert_eq!(batch.diffs["notes.txt"].total_additions(), 2);
+    assert!(batch.diffs["same.txt"].hunks.is_empty());
+    assert!(batch.errors.is_empty());
+    assert!(client.get_file_diffs(&[Path::new("notes.txt"); 21]).await.is_err());
+
+    let matches = client.search("NOTES").await.unwrap();
+    assert_eq!(matches.len(), 1);
+    assert_eq!(matches[0].status, DiffStatus::Modified);
+
+    let stats = client.get_stats().await.unwrap();
+    assert_eq!((stats.total_files, stats.lines_added, stats.lines_removed), (2, 2, 1));
+
+    let patch = client.export(ExportFormat::Unified).await.unwrap();
+    assert!(patch.contains("+++ b/notes.txt"));
+
+    assert!(client.get_history().await.unwrap().is_empty());
+
+    assert!(DiffyClient::new("not a url").is_err());
+}
+
+#[tokio::test]
+async fn client_keeps_the_path_prefix_of_its_base_url() {
+    let fixture = TestFixture::new();
+    fixture.write_left("notes.txt", "one
+").write_right("notes.txt", "two
+");
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap();
+    let app = axum::Router::new().nest("/tools/diffy", diffy::create_app(fixture.core()));
+    tokio::spawn(async move {
+        axum::serve(listener, app).await.unwrap();
+    });
+
+    for base in ["tools/diffy", "tools/diffy/"] {
+        let client = DiffyClient::new(&format!("http://{}/{}", address, base)).unwrap();
+        assert_eq!(client.base_url.path(), "/tools/diffy/");
+        assert_eq!(client.get_diff().await.unwrap().modified_count, 1);
+        assert_eq!(client.get_file_diffs(&[Path::new("notes.txt")]).await.unwrap().diffs.len(), 1);
+    }
+}
+
+#[tokio::test]
+async fn web_reload_picks_up_external_changes() {
+    let fixture = TestFixture::new();
+    fixture.write_both("same.txt", "same\n");
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap();
+    let app = diffy::create_app(fixture.core());
+    tokio::spawn(async move {
+        axum::serve(listener, app).await.unwrap();
+    });
+
+    let added_count = || async {
+        let response: serde_json::Value = reqwest::get(format!("http://{}/api/diff", address))
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+        response["data"]["added_count"].clone()
+    };
+    assert_eq!(added_count().await, 0);
+
+    fixture.write_right("added.txt", "new\n");
+    assert_eq!(added_count().await, 0, "results are cached until reloaded");
+
+    let reload: serde_json::Value = reqwest::Client::new()
+        .post(format!("http://{}/api/diff/reload", address))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(reload["data"]["status"], "reloading");
+    assert_eq!(added_count().await, 1);
+}
+
+#[tokio::test]
+async fn web_sessions_compare_other_directories() {
+    let served = TestFixture::new();
+    served.write_both("same.txt", "same\n");
+    let other = TestFixture::new();
+    other.write_right("added.txt", "new\n");
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap();
+    let app = diffy::create_app(served.core());
+    tokio::spawn(async move {
+        axum::serve(listener, app).await.unwrap();
+    });
+
+    let http = reqwest::Client::new();
+    let created: serde_json::Value = http
+        .post(format!("http://{}/api/sessions", address))
+        .json(&serde_json::json!({ "left": other.left_path(), "right": other.right_path() }))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    let session_id = created["data"]["session_id"].as_str().unwrap();
+
+    let get = |url: String| async move { reqwest::get(url).await.unwrap().json::<serde_json::Value>().await.unwrap() };
+    let default = get(format!("http://{}/api/diff", address)).await;
+    let session = get(format!("http://{}/api/diff?session_id={}", address, session_id)).await;
+    assert_eq!(default["data"]["added_count"], 0);
+    assert_eq!(session["data"]["added_count"], 1);
+
+    let file = get(format!("http://{}/api/file?path=added.txt&session_id={}", address, session_id)).await;
+    assert_eq!(file["success"], true);
+
+    // Reloading a session re-scans its comparison, not only the server's
+    other.write_right("later.txt", "new\n");
+    let reload: serde_json::Value = http
+        .post(format!("http://{}/api/diff/reload?session_id={}", address, session_id))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(reload["success"], true);
+    let session = get(format!("http://{}/api/diff?session_id={}", address, session_id)).await;
+    assert_eq!(session["data"]["added_count"], 2);
+
+    let unknown = get(format!("http://{}/api/search?q=a&session_id={}", address, uuid::Uuid::nil())).await;
+    assert_eq!(unknown["success"], false);
+}
+
+#[test]
+fn results_can_be_scoped_without_rescanning() {
+    let fixture = TestFixture::new();
+    fixture
+        .write_both("src/lib.rs", "lib\n")
+        .write_right("src/new.rs", "new\n")
+        .write_left("docs/guide.md", "old\n")
+        .write_right("docs/guide.md", "new\n");
+    let result = fixture.core().analyze().unwrap();
+
+    let src = DiffyCore::analyze_subdirectory(&result, std::path::Path::new("src")).unwrap();
+    assert_eq!(src.right_path, fixture.right_path().join("src"));
+    DiffAssert::new(&src).has_file_count(2).has_added("new.rs").has_unchanged("lib.rs");
+
+    let range = DiffyCore::analyze_file_range(&result, &["docs/guide.md".into(), "src/lib.rs".into()]).unwrap();
+    DiffAssert::new(&range).has_file_count(2).has_modified("docs/guide.md");
+    assert_eq!((range.added_count, range.modified_count), (0, 1));
+
+    assert!(DiffyCore::analyze_file_range(&result, &["missing.txt".into()]).is_err());
+}
+
+#[test]
+fn stdin_path_pairs_are_parsed() {
+    let (left, right) = DiffyCore::read_path_pair("/tmp/a\r\n/tmp/b\n".as_bytes()).unwrap();
+    assert_eq!((left, right), ("/tmp/a".into(), "/tmp/b".into()));
+    assert!(DiffyCore::read_path_pair("/tmp/a\n".as_bytes()).is_err());
+
+    let (left, right) = DiffyCore::parse_batch_line("old dir/x.rs\tnew dir/x.rs").unwrap();
+    assert_eq!((left, right), ("old dir/x.rs".into(), "new dir/x.rs".into()));
+    assert!(DiffyCore::parse_batch_line("no tab here").is_err());
+}
+
+#[test]
+fn progress_counts_up_to_discovered_paths() {
+    let fixture = TestFixture::new();
+    fixture
+        .write_both("same.txt", "same\n")
+        .write_left("old.txt", "gone\n")
+        .write_right("src/new.rs", "fn main() {}\n");
+
+    let mut reports = Vec::new();
+    let result = fixture.core().analyze_with_progress(|done, total| reports.push((done, total))).unwrap();
+
+    let &(_, total) = reports.last().unwrap();
+    assert!(total >= result.total_files);
+    assert_eq!(reports.last(), Some(&(total, total)));
+    assert!(reports.iter().all(|&(done, reported_total)| done <= total && reported_total == total));
+    assert!(reports.windows(2).all(|pair| pair[0].0 <= pair[1].0));
+}
+
+#[test]
+fn empty_directories_are_listed_on_request() {
+    let fixture = TestFixture::new();
+    fixture.write_both("kept/file.txt", "same\n");
+    std::fs::create_dir_all(fixture.left_path().join("emptied")).unwrap();
+    std::fs::create_dir_all(fixture.left_path().join("both/empty")).unwrap();
+    std::fs::create_dir_all(fixture.right_path().join("both/empty")).unwrap();
+
+    let hidden = fixture.core().analyze().unwrap();
+    assert!(hidden.search("empt").is_empty());
+    DiffAssert::new(&hidden).has_unchanged("kept").has_unchanged("kept/file.txt");
+
+    let mut core = fixture.core();
+    core.show_empty_dirs = true;
+    let shown = core.analyze().unwrap();
+    DiffAssert::new(&shown)
+        .has_removed("emptied")
+        .has_unchanged("both/empty")
+        .has_unchanged("kept/file.txt");
+}
+
+#[test]
+fn paths_can_be_matched_regardless_of_case() {
+    let fixture = TestFixture::new();
+    fixture
+        .write_left("Readme.md", "hello\n")
+        .write_right("README.md", "hello\n")
+        .write_left("Docs/guide.txt", "old\n")
+        .write_right("docs/guide.txt", "new\n")
+        .write_left("Src/a.txt", "a\n")
+        .write_right("src/a.txt", "a\n")
+        .write_both("same.txt", "same\n");
+
+    let sensitive = fixture.core().analyze().unwrap();
+    DiffAssert::new(&sensitive).has_removed("Readme.md").has_added("README.md");
+
+    // Only differences in content count; a name that changed case alone does not
+    let mut core = fixture.core();
+    core.case_insensitive = true;
+    let result = core.analyze().unwrap();
+    DiffAssert::new(&result)
+        .has_file_count(4)
+        .has_unchanged("Readme.md")
+        .has_modified("Docs/guide.txt")
+        .has_unchanged("Src/a.txt")
+        .has_unchanged("same.txt");
+    assert_eq!((result.modified_count, result.unchanged_count), (1, 3));
+
+    let diff = core.get_file_diff(Path::new("Docs/guide.txt")).unwrap();
+    assert_eq!((diff.total_additions(), diff.total_deletions()), (1, 1));
+}
+
+#[cfg(unix)]
+#[test]
+fn hardlinks_are_reported_on_both_sides() {
+    let fixture = TestFixture::new();
+    fixture
+        .write_left("original.txt", "shared\n")
+        .write_left("moved.txt", "moved\n")
+        .write_both("plain.txt", "plain\n");
+    std::fs::hard_link(fixture.left_path().join("original.txt"), fixture.left_path().join("alias.txt")).unwrap();
+    std::fs::hard_link(fixture.left_path().join("moved.txt"), fixture.right_path().join("renamed.txt")).unwrap();
+
+    let result = fixture.core().analyze().unwrap();
+    let entry = |path: &str| result.tree.children.iter().find(|entry| entry.relative_path == Path::new(path)).unwrap();
+
+    let original = entry("original.txt").left_metadata.as_ref().unwrap();
+    assert_eq!(original.hardlinks, vec![Path::new("alias.txt").to_path_buf()]);
+    assert_eq!(original.nlinks, Some(2));
+    assert!(entry("alias.txt").is_hardlinked());
+
+    let renamed = entry("renamed.txt");
+    assert_eq!(renamed.status, DiffStatus::Added);
+    assert_eq!(renamed.right_metadata.as_ref().unwrap().linked_as.as_deref(), Some(Path::new("moved.txt")));
+    assert_eq!(entry("moved.txt").left_metadata.as_ref().unwrap().linked_as.as_deref(), Some(Path::new("renamed.txt")));
+
+    assert!(!entry("plain.txt").is_hardlinked());
+    assert!(entry("plain.txt").left_metadata.is_none());
+}
+
+#[test]
+fn root_diffyignore_applies_to_both_sides() {
+    let fixture = TestFixture::new();
+    fixture
+        .write_left(".diffyignore", "build/\n*.log\n!keep.log\n")
+        .write_left("build/out.bin", "old")
+        .write_right("build/out.bin", "new")
+        .write_right("nested/debug.log", "noise\n")
+        .write_right("nested/keep.log", "kept\n")
+        .write_both("src/lib.rs", "fn a() {}\n");
+
+    let result = fixture.core().analyze().unwrap();
+    assert!(result.search("build").is_empty());
+    assert!(result.search("debug.log").is_empty());
+    DiffAssert::new(&result)
+        .has_removed(".diffyignore")
+        .has_added("nested/keep.log")
+        .has_unchanged("src/lib.rs");
+}
+
+#[test]
+fn tree_children_can_be_sorted() {
+    let fixture = TestFixture::new();
+    fixture
+        .write_both("a_same.txt", "same\n")
+        .write_right("b_added.txt", "a much longer new file\n")
+        .write_left("c_changed.txt", "old\n")
+        .write_right("c_changed.txt", "new\n")
+        .write_both("dir/inner.txt", "same\n");
+
+    let mut tree = fixture.core().analyze().unwrap().tree;
+    let names = |tree: &FileEntry| -> Vec<String> {
+        tree.children.iter().map(|child| child.relative_path.display().to_string()).collect()
+    };
+    assert_eq!(names(&tree), ["dir", "a_same.txt", "b_added.txt", "c_changed.txt"]);
+
+    tree.sort_children(FileEntry::sort_by_status_then_name());
+    assert_eq!(names(&tree), ["dir", "c_changed.txt", "b_added.txt", "a_same.txt"]);
+
+    tree.sort_children(SortMode::Size.comparator());
+    assert_eq!(names(&tree), ["dir", "b_added.txt", "a_same.txt", "c_changed.txt"]);
+
+    tree.sort_children(|a, b| b.relative_path.cmp(&a.relative_path));
+    assert_eq!(names(&tree), ["dir", "c_changed.txt", "b_added.txt", "a_same.txt"]);
+}
+
+#[test]
+fn merge_conflict_markers_are_reported() {
+    let fixture = TestFixture::new();
+    fixture
+        .write_left("merged.rs", "fn a() {}\n")
+        .write_right("merged.rs", "<<<<<<< HEAD\nfn a() {}\n=======\nfn b() {}\n>>>>>>> feature\n")
+        .write_left("README.md", "Title\n")
+        .write_right("README.md", "Title\n=======\n");
+
+    let result = fixture.core().analyze().unwrap();
+    DiffAssert::new(&result)
+        .has_conflicted("merged.rs")
+        .has_modified("README.md");
+    assert_eq!((result.conflict_count, result.modified_count), (1, 1));
+}
+
+#[test]
+fn git_stash_is_compared_with_the_working_tree() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut repo = git2::Repository::init(dir.path()).unwrap();
+    let signature = git2::Signature::now("Diffy", "diffy@example.com").unwrap();
+    std::fs::write(dir.path().join("notes.txt"), "one\n").unwrap();
+    let mut index = repo.index().unwrap();
+    index.add_path(Path::new("notes.txt")).unwrap();
+    let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+    repo.commit(Some("HEAD"), &signature, &signature, "initial", &tree, &[]).unwrap();
+    drop(tree);
+
+    std::fs::write(dir.path().join("notes.txt"), "one\ntwo\n").unwrap();
+    repo.stash_save(&signature, "wip", None).unwrap();
+
+    let core = DiffyCore::from_git_stash(dir.path().to_path_buf(), 0).unwrap();
+    assert_eq!(core.description.as_deref(), Some("Stash@{0} vs Working Tree"));
+    let result = core.analyze().unwrap();
+    assert!(result.search(".git").is_empty());
+    DiffAssert::new(&result).has_file_count(1).has_modified("notes.txt");
+    assert_eq!(core.get_file_diff(Path::new("notes.txt")).unwrap().total_additions(), 1);
+
+    let checkout = core.right_path.clone();
+    drop(core);
+    assert!(!checkout.exists());
+    assert!(DiffyCore::from_git_stash(dir.path().to_path_buf(), 1).is_err());
+}
+
+#[test]
+fn snapshots_are_listed_and_matched_by_prefix() {
+    let dir = tempfile::tempdir().unwrap();
+    for (name, files) in [("20240115-094500", 2), ("20240101-120000", 1), ("20240115-180000", 0)] {
+        std::fs::create_dir_all(dir.path().join(name).join("nested")).unwrap();
+        for index in 0..files {
+            std::fs::write(dir.path().join(name).join("nested").join(format!("{index}.txt")), "x").unwrap();
+        }
+    }
+    std::fs::write(dir.path().join("README"), "not a snapshot").unwrap();
+
+    let snapshots = DiffyCore::list_snapshots(dir.path()).unwrap();
+    let listed: Vec<_> = snapshots.iter().map(|snapshot| (snapshot.name.as_str(), snapshot.file_count)).collect();
+    assert_eq!(listed, [("20240101-120000", 1), ("20240115-094500", 2), ("20240115-180000", 0)]);
+    assert!(snapshots.iter().all(|snapshot| snapshot.created.is_some()));
+
+    let core = DiffyCore::from_snapshots(dir.path().to_path_buf(), "2024-01-01", "20240115-09").unwrap();
+    let result = core.analyze().unwrap();
+    assert_eq!(result.left_path, dir.path().join("20240101-120000"));
+    assert_eq!(result.right_path, dir.path().join("20240115-094500"));
+    assert_eq!((result.added_count, result.unchanged_count), (1, 1));
+
+    let Err(ambiguous) = DiffyCore::from_snapshots(dir.path().to_path_buf(), "20240101", "20240115") else {
+        panic!("an ambiguous prefix should be rejected");
+    };
+    assert!(ambiguous.to_string().contains("20240115-094500, 20240115-180000"));
+    assert!(DiffyCore::from_snapshots(dir.path().to_path_buf(), "2023", "20240101").is_err());
+}
+
+#[test]
+fn history_keeps_the_latest_entry_for_each_pair() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("diffy").join("history.json");
+    assert!(history::load(&path).unwrap().is_empty());
+
+    for index in 0..history::MAX_HISTORY_ENTRIES + 2 {
+        let entry = HistoryEntry::now(PathBuf::from(format!("/old/{index}")), PathBuf::from("/new"), 3, 1);
+        history::record(&path, entry).unwrap();
+    }
+    history::record(&path, HistoryEntry::now(PathBuf::from("/old/5"), PathBuf::from("/new"), 4, 2)).unwrap();
+
+    let entries = history::load(&path).unwrap();
+    assert_eq!(entries.len(), history::MAX_HISTORY_ENTRIES);
+    assert_eq!((entries[0].left_path.as_path(), entries[0].changed_files), (Path::new("/old/5"), 2));
+    assert_eq!(entries[1].left_path, Path::new("/old/21"));
+    assert_eq!(entries.iter().filter(|entry| entry.left_path == Path::new("/old/5")).count(), 1);
+    assert!(!entries.iter().any(|entry| entry.left_path == Path::new("/old/1")));
+}
+
+#[test]
+fn since_compares_with_the_snapshot_taken_by_then() {
+    let fixture = TestFixture::new();
+    fixture.write_right("notes.txt", "january\n").write_right("keep.txt", "same\n");
+    let snapshots = tempfile::tempdir().unwrap();
+    DiffyCore::snapshot_into(fixture.right_path(), snapshots.path(), "20240101-000000").unwrap();
+    assert!(DiffyCore::snapshot_into(fixture.right_path(), snapshots.path(), "20240101-000000").is_err());
+
+    // Replaced rather than rewritten, as editors save, so the hard link keeps the old text
+    std::fs::remove_file(fixture.right_path().join("notes.txt")).unwrap();
+    fixture.write_right("notes.txt", "february\n");
+    DiffyCore::snapshot_into(fixture.right_path(), snapshots.path(), "20240201-000000").unwrap();
+    fixture.write_right("added.txt", "new\n");
+
+    let core = DiffyCore::from_snapshot_since(fixture.right_path().to_path_buf(), Some(snapshots.path().to_path_buf()), "2024-01-15").unwrap();
+    assert_eq!(core.description.as_deref(), Some("Snapshot 20240101-000000 vs now"));
+    let result = core.analyze().unwrap();
+    assert_eq!((result.added_count, result.modified_count, result.unchanged_count), (1, 1, 1));
+
+    let latest = DiffyCore::from_snapshot_since(fixture.right_path().to_path_buf(), Some(snapshots.path().to_path_buf()), "1h").unwrap();
+    assert_eq!(latest.left_path, snapshots.path().join("20240201-000000"));
+    let oldest = DiffyCore::from_snapshot_since(fixture.right_path().to_path_buf(), Some(snapshots.path().to_path_buf()), "2023-06-01").unwrap();
+    assert_eq!(oldest.left_path, snapshots.path().join("20240101-000000"));
+    assert!(DiffyCore::from_snapshot_since(fixture.right_path().to_path_buf(), Some(snapshots.path().to_path_buf()), "last week").is_err());
+    assert_eq!(DiffyCore::snapshot_name(std::time::UNIX_EPOCH), "19700101-000000");
+}
+
+#[test]
+fn batched_analysis_matches_a_single_pass() {
+    let fixture = TestFixture::new();
+    for index in 0..12 {
+        fixture.write_both(&format!("src/module_{}/lib.rs", index % 3), "shared\n");
+        fixture.write_both(&format!("src/module_{}/file_{}.rs", index % 3, index), "fn a() {}\n");
+    }
+    fixture
+        .write_left("src/module_1/file_4.rs", "fn b() {}\n")
+        .write_left("old/gone.txt", "bye\n")
+        .write_right("new/deep/added.txt", "hi\n")
+        .write_right("src/module_2/extra.rs", "fn c() {}\n");
+
+    let core = fixture.core();
+    let expected = core.analyze().unwrap();
+    for batch_size in [1, 4, 7, diffy::core::tree::DEFAULT_BATCH_SIZE] {
+        let batched = core.analyze_batched(batch_size).unwrap();
+        assert_eq!(batched.to_tree_string(), expected.to_tree_string(), "batch size {}", batch_size);
+        assert_eq!(
+            (batched.total_files, batched.added_count, batched.removed_count, batched.modified_count),
+            (expected.total_files, expected.added_count, expected.removed_count, expected.modified_count),
+        );
+    }
+
+    let mut partials = Vec::new();
+    let builder = diffy::core::tree::FileTreeBuilder::new(fixture.left_path().to_path_buf(), fixture.right_path().to_path_buf());
+    builder.build_batched(10, |partial| partials.push(partial.total_files)).unwrap();
+    assert_eq!(partials.len(), 3);
+    assert_eq!(partials.iter().sum::<usize>(), expected.total_files);
+    assert!(core.analyze_batched(0).is_err());
+}
+
+#[test]
+fn staged_sensitive_files_are_guarded() {
+    let dir = tempfile::tempdir().unwrap();
+    let repo = git2::Repository::init(dir.path()).unwrap();
+    let signature = git2::Signature::now("Ada", "ada@example.com").unwrap();
+    std::fs::write(dir.path().join("app.rs"), "fn main() {}\n").unwrap();
+    let mut index = repo.index().unwrap();
+    index.add_path(Path::new("app.rs")).unwrap();
+    let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+    repo.commit(Some("HEAD"), &signature, &signature, "initial", &tree, &[]).unwrap();
+
+    std::fs::create_dir_all(dir.path().join("config")).unwrap();
+    std::fs::write(dir.path().join("config/.env"), "TOKEN=1\n").unwrap();
+    std::fs::write(dir.path().join("notes.txt"), "hi\n").unwrap();
+    std::fs::write(dir.path().join("unstaged.pem"), "key\n").unwrap();
+    // Latin-1 text is not valid UTF-8 and counts as binary
+    std::fs::write(dir.path().join("app.rs"), b"// caf\xe9\nfn main() {}\n").unwrap();
+    index.add_path(Path::new("config/.env")).unwrap();
+    index.add_path(Path::new("notes.txt")).unwrap();
+    index.add_path(Path::new("app.rs")).unwrap();
+    index.write().unwrap();
+
+    // Only the staged changes are checked out, not the unchanged files
+    let core = DiffyCore::from_git_staged(dir.path().to_path_buf()).unwrap();
+    let result = core.analyze().unwrap();
+    assert_eq!((result.added_count, result.modified_count, result.unchanged_count), (2, 1, 0));
+
+    let patterns: Vec<String> = diffy::core::hook::DEFAULT_GUARD_PATTERNS.iter().map(|p| p.to_string()).collect();
+    assert_eq!(core.guarded_files(&result, &patterns).unwrap(), [PathBuf::from("config/.env")]);
+    assert_eq!(core.guarded_files(&result, &["*.txt".to_string()]).unwrap(), [PathBuf::from("notes.txt")]);
+    assert_eq!(core.statistics(&result).unwrap().to_shortstat(), "3 files changed (2 added, 0 removed, 1 modified), 2 insertions(+), 0 deletions(-)");
+}
+
+#[test]
+fn pre_commit_hook_is_installed_checked_and_removed() {
+    use diffy::core::hook::{self, HookOptions, HookStatus};
+
+    let dir = tempfile::tempdir().unwrap();
+    git2::Repository::init(dir.path()).unwrap();
+    let hook_path = dir.path().join(".git/hooks/pre-commit");
+    assert_eq!(hook::check(dir.path()).unwrap(), HookStatus::Missing);
+
+    let options = HookOptions { guard_patterns: vec![".env".to_string(), "*.pem".to_string()], ..HookOptions::default() };
+    assert_eq!(hook::install(dir.path(), &options, false).unwrap(), hook_path);
+    let script = std::fs::read_to_string(&hook_path).unwrap();
+    assert!(script.starts_with("#!/bin/sh\n"));
+    assert!(script.contains("diffy --git-staged --repo . --format stats --guard-patterns '.env,*.pem'"));
+    assert_eq!(hook::check(dir.path()).unwrap(), HookStatus::Current);
+
+    std::fs::write(&hook_path, script.replace("diffy-hook-version: 1", "diffy-hook-version: 0")).unwrap();
+    assert_eq!(hook::check(dir.path()).unwrap(), HookStatus::Outdated);
+    assert!(hook::uninstall(dir.path()).unwrap());
+    assert!(!hook_path.exists());
+    assert!(!hook::uninstall(dir.path()).unwrap());
+
+    std::fs::write(&hook_path, "#!/bin/sh\nmake lint\n").unwrap();
+    assert_eq!(hook::check(dir.path()).unwrap(), HookStatus::Foreign);
+    assert!(hook::install(dir.path(), &options, false).is_err());
+    assert!(hook::uninstall(dir.path()).is_err());
+    hook::install(dir.path(), &options, true).unwrap();
+    assert_eq!(hook::check(dir.path()).unwrap(), HookStatus::Current);
+}
+
+#[test]
+fn tui_config_is_checked_before_the_tui_starts() {
+    let fixture = TestFixture::new();
+    let with_tui = |edit: fn(&mut DiffConfig)| {
+        let mut config = DiffConfig::default();
+        edit(&mut config);
+        TuiApp::new(fixture.core()).with_config(&config.tui).map(|_| ())
+    };
+
+    assert!(with_tui(|_| {}).is_ok());
+    assert!(with_tui(|config| {
+        config.tui.added_color = "#00ff88".into();
+        config.tui.key_quit = "x".into();
+        config.tui.key_unified = "q".into();
+    })
+    .is_ok());
+    assert!(with_tui(|config| config.tui.removed_color = "reddish".into()).is_err());
+    assert!(with_tui(|config| config.tui.key_quit = "qq".into()).is_err());
+    assert!(with_tui(|config| config.tui.key_sort = "u".into()).is_err());
+    assert!(DiffConfig::generate_template().contains("[tui]\n"));
+}
+
+#[test]
+fn completion_scripts_complete_paths() {
+    let completions = |shell: &str| {
+        let output = std::process::Command::new(env!("CARGO_BIN_EXE_diffy")).args(["completions", shell]).output().unwrap();
+        assert!(output.status.success());
+        String::from_utf8(output.stdout).unwrap()
+    };
+
+    let dir = tempfile::tempdir().unwrap();
+    let script = dir.path().join("diffy.bash");
+    std::fs::write(&script, completions("bash")).unwrap();
+    let check = std::process::Command::new("bash").arg("-n").arg(&script).output().unwrap();
+    assert!(check.status.success(), "{}", String::from_utf8_lossy(&check.stderr));
+
+    assert!(completions("fish").contains("-s l -l left -d 'Left directory or file path"));
+    assert!(completions("fish").contains("-r -f -a '(__diffy_complete_path)'"));
+    assert!(completions("zsh").contains("'--right=[Right directory or file path]:RIGHT:_diffy_path'"));
+    assert!(completions("zsh").contains("_path_files -W \"$PWD\""));
+    assert!(completions("powershell").contains("[System.IO.Directory]::GetDirectories($base)"));
+}
+
+#[test]
+fn path_picker_picks_a_directory_in_each_panel() {
+    use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+    use diffy::cli::picker::{PathPicker, PickerEvent};
+
+    let dir = tempfile::tempdir().unwrap();
+    let root = dir.path().canonicalize().unwrap();
+    std::fs::create_dir_all(root.join("left")).unwrap();
+    std::fs::create_dir_all(root.join("right")).unwrap();
+    std::fs::write(root.join("left/a.txt"), "a\n").unwrap();
+    let mut picker = PathPicker::new(&root).unwrap();
+    let mut press = |code: KeyCode| picker.handle_key(KeyEvent::new(code, KeyModifiers::NONE));
+
+    // Rows: "use this directory", left/, right/
+    assert_eq!(press(KeyCode::Tab), PickerEvent::Continue);
+    assert_eq!(press(KeyCode::Tab), PickerEvent::Continue);
+    assert_eq!(press(KeyCode::Enter), PickerEvent::Continue, "nothing picked yet");
+    press(KeyCode::Tab);
+    press(KeyCode::Down);
+    press(KeyCode::Enter);
+    press(KeyCode::Enter);
+    press(KeyCode::Down);
+    press(KeyCode::Down);
+    press(KeyCode::Enter);
+    press(KeyCode::Backspace);
+    press(KeyCode::Enter);
+    press(KeyCode::Enter);
+    assert_eq!(
+        press(KeyCode::Enter),
+        PickerEvent::Picked(root.join("left"), root.join("right"))
+    );
+    assert_eq!(press(KeyCode::Esc), PickerEvent::Cancelled);
+}
+
+#[test]
+fn file_blame_names_the_last_commit() {
+    let dir = tempfile::tempdir().unwrap();
+    let repo = git2::Repository::init(dir.path()).unwrap();
+    let signature = git2::Signature::now("Ada", "ada@example.com").unwrap();
+    std::fs::write(dir.path().join("notes.txt"), "one\n").unwrap();
+    let mut index = repo.index().unwrap();
+    index.add_path(Path::new("notes.txt")).unwrap();
+    let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+    let commit = repo.commit(Some("HEAD"), &signature, &signature, "initial", &tree, &[]).unwrap();
+    std::fs::write(dir.path().join("notes.txt"), "one\ntwo\n").unwrap();
+
+    let fixture = TestFixture::new();
+    fixture.write_left("notes.txt", "one\n");
+    let core = DiffyCore::new(fixture.left_path().to_path_buf(), dir.path().to_path_buf());
+    let blame = core.get_file_blame(Path::new("notes.txt"), None).unwrap();
+
+    assert_eq!(blame.len(), 2);
+    assert_eq!(blame[0].commit_hash, Some(commit.to_string()));
+    assert_eq!(blame[0].author.as_deref(), Some("Ada"));
+    assert!(blame[0].date.is_some());
+    assert_eq!((blame[1].line_number, blame[1].content.as_str(), blame[1].commit_hash.as_deref()), (2, "two", None));
+
+    let untracked = fixture.core().get_file_blame(Path::new("notes.txt"), None).unwrap();
+    assert_eq!((untracked[0].content.as_str(), untracked[0].author.as_deref()), ("one", None));
+}
+
+#[tokio::test]
+async fn git_branches_are_compared_at_their_tips() {
+    let dir = tempfile::tempdir().unwrap();
+    let repo = git2::Repository::init(dir.path()).unwrap();
+    let signature = git2::Signature::now("Diffy", "diffy@example.com").unwrap();
+    let commit = |files: &[(&str, &str)], parents: &[&git2::Commit]| {
+        let mut index = repo.index().unwrap();
+        for (path, contents) in files {
+            let file = dir.path().join(path);
+            std::fs::create_dir_all(file.parent().unwrap()).unwrap();
+            std::fs::write(file, contents).unwrap();
+            index.add_path(Path::new(path)).unwrap();
+        }
+        let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+        let id = repo.commit(None, &signature, &signature, "commit", &tree, parents).unwrap();
+        repo.find_commit(id).unwrap()
+    };
+    let base = commit(&[("notes.txt", "one\n")], &[]);
+    let head = commit(&[("notes.txt", "one\ntwo\n"), ("feature/new.rs", "fn new() {}\n")], &[&base]);
+    repo.branch("main", &base, true).unwrap();
+    repo.branch("feature/new", &head, true).unwrap();
+
+    let core = DiffyCore::from_git_branches(dir.path().to_path_buf(), "main", "feature/new").unwrap();
+    assert_eq!(core.description.as_deref(), Some("main..feature/new"));
+    let result = core.analyze().unwrap();
+    assert_eq!(result.description.as_deref(), Some("main..feature/new"));
+    DiffAssert::new(&result).has_modified("notes.txt").has_added("feature/new.rs");
+    assert!(DiffyCore::from_git_branches(dir.path().to_path_buf(), "main", "missing").is_err());
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap();
+    let app = diffy::create_app(DiffyCore::new(dir.path().to_path_buf(), dir.path().to_path_buf()));
+    tokio::spawn(async move {
+        axum::serve(listener, app).await.unwrap();
+    });
+
+    let get = |endpoint: String| async move {
+        reqwest::get(format!("http://{}{}", address, endpoint))
+            .await
+            .unwrap()
+            .json::<serde_json::Value>()
+            .await
+            .unwrap()
+    };
+    let diff = get("/api/diff?git_branch=main..feature/new".to_string()).await;
+    assert_eq!(diff["data"]["added_count"], 1);
+    assert_eq!(diff["data"]["description"], "main..feature/new");
+    let file = get("/api/file?path=notes.txt&git_branch=main..feature/new".to_string()).await;
+    assert_eq!(file["data"]["right_content"], "one\ntwo\n");
+    assert_eq!(get("/api/diff?git_branch=main".to_string()).await["success"], false);
+}
+
+#[test]
+fn ignore_patterns_can_be_added_programmatically() {
+    let fixture = TestFixture::new();
+    fixture
+        .write_left("build/out.bin", "old")
+        .write_right("build/out.bin", "new")
+        .write_right("debug.log", "noise\n")
+        .write_right("notes.tmp", "scratch\n")
+        .write_both("src/lib.rs", "fn a() {}\n");
+    let ignore_dir = tempfile::tempdir().unwrap();
+    let ignore_file = ignore_dir.path().join("extra.ignore");
+    std::fs::write(&ignore_file, "# scratch files\n*.tmp\n").unwrap();
+
+    let result = fixture
+        .core()
+        .with_ignore_string("build/\n*.log")
+        .with_ignore_file(ignore_file)
+        .unwrap()
+        .analyze()
+        .unwrap();
+    DiffAssert::new(&result).has_file_count(1).has_unchanged("src/lib.rs");
+
+    assert!(fixture.core().with_ignore_file(ignore_dir.path().join("missing")).is_err());
+}
+
+#[cfg(unix)]
+#[test]
+fn file_metadata_is_read_for_both_sides() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let fixture = TestFixture::new();
+    fixture.write_both("run.sh", "echo hi\n").write_left("gone.txt", "x\n");
+    std::fs::set_permissions(fixture.left_path().join("run.sh"), std::fs::Permissions::from_mode(0o644)).unwrap();
+    std::fs::set_permissions(fixture.right_path().join("run.sh"), std::fs::Permissions::from_mode(0o755)).unwrap();
+
+    let core = fixture.core();
+    let (left, right) = core.get_file_metadata(Path::new("run.sh"));
+    assert_eq!(left.unwrap().permissions_string().as_deref(), Some("rw-r--r--"));
+    let right = right.unwrap();
+    assert_eq!(right.permissions_string().as_deref(), Some("rwxr-xr-x"));
+    assert_eq!(right.size, 8);
+    assert!(!right.is_symlink);
+
+    let (left, right) = core.get_file_metadata(Path::new("gone.txt"));
+    assert!(left.is_some() && right.is_none());
+}
+
+#[test]
+fn line_summaries_are_computed_when_enabled() {
+    let fixture = TestFixture::new();
+    fixture
+        .write_both("same.txt", "same\n")
+        .write_left("notes.txt", "one\ntwo\n")
+        .write_right("notes.txt", "one\nthree\nfour\n")
+        .write_right("src/new.rs", "a\nb\nc\n")
+        .write_left_binary("logo.png", &[0, 1, 2])
+        .write_right_binary("logo.png", &[0, 1, 3]);
+
+    let mut core = fixture.core();
+    assert!(core.analyze().unwrap().tree.children.iter().all(|entry| entry.line_summary.is_none()));
+
+    core.diff_options.compute_line_stats = true;
+    let result = core.analyze().unwrap();
+    fn find<'a>(entry: &'a FileEntry, path: &str) -> Option<&'a FileEntry> {
+        if entry.relative_path == Path::new(path) {
+            return Some(entry);
+        }
+        entry.children.iter().find_map(|child| find(child, path))
+    }
+    let summary_of = |path: &str| find(&result.tree, path).and_then(|entry| entry.line_summary);
+
+    assert_eq!(summary_of("notes.txt"), Some(LineSummary { additions: 2, deletions: 1 }));
+    assert_eq!(summary_of("src/new.rs"), Some(LineSummary { additions: 3, deletions: 0 }));
+    assert_eq!(summary_of("same.txt"), None);
+    assert_eq!(summary_of("logo.png"), None);
+
+    // 3 of the 5 lines on both sides changed
+    let similarity_of = |path: &str| find(&result.tree, path).and_then(|entry| entry.similarity);
+    assert_eq!(similarity_of("notes.txt").map(|similarity| (similarity * 100.0).round()), Some(40.0));
+    assert_eq!(similarity_of("src/new.rs"), None);
+}
+
+#[cfg(unix)]
+#[test]
+fn files_that_fail_to_diff_get_no_line_summary() {
+    let fixture = TestFixture::new();
+    fixture.write_left("notes.txt", "one\n").write_right("notes.txt", "two\n").write_left("odd", "text\n");
+    std::fs::create_dir(fixture.right_path().join("dir")).unwrap();
+    // Reading a symlink to a directory as text fails
+    std::os::unix::fs::symlink(fixture.right_path().join("dir"), fixture.right_path().join("odd")).unwrap();
+
+    let mut core = fixture.core();
+    core.diff_options.compute_line_stats = true;
+    assert!(core.get_file_diff(Path::new("odd")).is_err());
+    let result = core.analyze().unwrap();
+    let summary_of = |name: &str| result.tree.children.iter().find(|entry| entry.relative_path == Path::new(name)).unwrap().line_summary;
+    assert_eq!(summary_of("notes.txt"), Some(LineSummary { additions: 1, deletions: 1 }));
+    assert_eq!(summary_of("odd"), None);
+}
+
+#[test]
+fn similar_added_and_removed_files_are_renames() {
+    let body: String = (1..=10).map(|line| format!("line {line}\n")).collect();
+    let fixture = TestFixture::new();
+    fixture
+        .write_left("old/config.rs", &body)
+        .write_right("new/settings.rs", &body.replace("line 10", "line ten"))
+        .write_left("notes.txt", "alpha\nbeta\n")
+        .write_right("todo.txt", "gamma\ndelta\n");
+
+    let core = fixture.core();
+    assert_eq!(DiffEngine::new().diff_strings(&body, &body).similarity, 1.0);
+    assert_eq!(DiffEngine::new().diff_strings("a\n", "b\n").similarity, 0.0);
+
+    let result = core.analyze().unwrap();
+    let renames = core.detect_renames(&result, DEFAULT_RENAME_THRESHOLD).unwrap();
+    assert_eq!(renames.len(), 1);
+    assert_eq!((renames[0].from.as_path(), renames[0].to.as_path()), (Path::new("old/config.rs"), Path::new("new/settings.rs")));
+    assert_eq!(renames[0].similarity, 0.9);
+
+    assert!(core.detect_renames(&result, 0.95).unwrap().is_empty());
+}
+
+#[test]
+fn changes_are_explained_in_plain_english() {
+    let body: String = (1..=20).map(|line| format!("line {line}\n")).collect();
+    let fixture = TestFixture::new();
+    fixture
+        .write_both("same.txt", "same\n")
+        .write_left("notes.txt", "one\ntwo\n")
+        .write_right("notes.txt", "one\nthree\nfour\n")
+        .write_left("src/main.rs", "fn main() {}\n")
+        .write_right("src/main.rs", "fn main() {\n    run();\n}\n")
+        .write_left("old.txt", &body)
+        .write_right("renamed.txt", &body.replace("line 20", "line twenty"))
+        .write_right("new.txt", "a\nb\n");
+
+    let core = fixture.core();
+    let result = core.analyze().unwrap();
+    assert_eq!(
+        core.explain(&result),
+        "6 files were compared. 2 files were added (total 22 lines), 1 file was deleted, and 2 files were modified. \
+         The largest change was in `src/main.rs` (+3/-1 lines). 1 file was renamed with 95% similarity."
+    );
+
+    let unchanged = TestFixture::new();
+    unchanged.write_both("same.txt", "same\n");
+    let same = unchanged.core().analyze().unwrap();
+    assert_eq!(unchanged.core().explain(&same), "1 file was compared. No files changed.");
+
+    // A file that can't be diffed any more is left out of the line counts
+    std::fs::remove_file(fixture.right_path().join("new.txt")).unwrap();
+    std::fs::create_dir(fixture.right_path().join("new.txt")).unwrap();
+    assert!(core.explain(&result).starts_with("6 files were compared. 2 files were added (total 20 lines)"));
+}
+
+#[test]
+fn diff_filter_keeps_only_the_chosen_changes() {
+    let body: String = (1..=20).map(|line| format!("line {line}\n")).collect();
+    let fixture = TestFixture::new();
+    fixture
+        .write_both("same.txt", "same\n")
+        .write_left("src/notes.txt", "one\n")
+        .write_right("src/notes.txt", "two\n")
+        .write_left("gone.txt", "gone\n")
+        .write_right("src/new.rs", "fn new() {}\n")
+        .write_left("old.txt", &body)
+        .write_right("moved.txt", &body);
+
+    let core = fixture.core();
+    let kept = |codes: &str| {
+        let result = core.analyze_filtered(&codes.parse::<DiffFilter>().unwrap()).unwrap();
+        let mut paths = Vec::new();
+        fn walk(entry: &FileEntry, paths: &mut Vec<String>) {
+            if !entry.is_directory {
+                paths.push(entry.relative_path.display().to_string());
+            }
+            entry.children.iter().for_each(|child| walk(child, paths));
+        }
+        walk(&result.tree, &mut paths);
+        paths.sort();
+        (paths, result.total_files)
+    };
+
+    assert_eq!(kept("AM"), (vec!["src/new.rs".to_string(), "src/notes.txt".to_string()], 2));
+    assert_eq!(kept("R").0, ["moved.txt", "old.txt"]);
+    assert_eq!(kept("d").0, ["moved.txt", "old.txt", "same.txt", "src/new.rs", "src/notes.txt"]);
+    assert_eq!(kept("Du").0, ["gone.txt"]);
+    assert_eq!(kept("").1, 6);
+
+    // A core's own filter applies to every analysis, and to cores that share its settings
+    let filtered = fixture.core().with_diff_filter("AM".parse().unwrap());
+    assert_eq!(filtered.analyze().unwrap().total_files, 2);
+    let sibling = filtered.with_paths(fixture.left_path().to_path_buf(), fixture.right_path().to_path_buf());
+    assert_eq!(sibling.analyze_with_progress(|_, _| {}).unwrap().total_files, 2);
+
+    assert!(matches!("AMx".parse::<DiffFilter>(), Err(DiffyError::UnknownDiffFilter { code: 'x' })));
+}
+
+#[test]
+fn patches_apply_forward_and_in_reverse() {
+    let original = "fn main() {\n    println!(\"hi\");\n}\n";
+    let modified = "fn main() {\n    println!(\"hello\");\n    exit(0);\n}\n";
+    let hunks = DiffEngine::new().diff_strings(original, modified).hunks;
+
+    assert_eq!(DiffEngine::apply_patch(original, &hunks).unwrap(), modified);
+    assert_eq!(DiffEngine::apply_patch_reversed(modified, &hunks).unwrap(), original);
+
+    let error = DiffEngine::apply_patch("fn main() {\n    todo!();\n}\n", &hunks).unwrap_err();
+    match error.downcast_ref::<DiffyError>() {
+        Some(DiffyError::PatchConflict { hunk, actual, .. }) => {
+            assert_eq!(*hunk, 0);
+            assert!(actual.contains("todo!()"));
+        }
+        other => panic!("expected a patch conflict, got {:?}", other),
+    }
+}
+
+#[test]
+fn addition_past_the_end_of_the_file_is_a_conflict() {
+    let patch = "--- a/notes.txt\n+++ b/notes.txt\n@@ -10,0 +11,1 @@\n+eleven\n";
+    let hunks = &DiffEngine::parse_unified_diff(patch).unwrap()[0].hunks;
+
+    let error = DiffEngine::apply_patch("one\ntwo\nthree\n", hunks).unwrap_err();
+    assert!(matches!(error.downcast_ref::<DiffyError>(), Some(DiffyError::PatchConflict { hunk: 0, .. })));
+}
+
+#[test]
+fn exported_patches_parse_back_to_the_same_hunks() {
+    let fixture = TestFixture::new();
+    fixture
+        .write_both("same.txt", "same\n")
+        .write_left("src/lib.rs", "a\nb\nc\nd\ne\nf\ng\nh\ni\nj\n")
+        .write_right("src/lib.rs", "a\nB\nc\nd\ne\nf\ng\nh\ni\nj\nk\n")
+        .write_right("added.txt", "-- not a header\n+plus\n")
+        .write_left("removed.txt", "gone\n")
+        .write_left_binary("logo.png", &[0, 1, 2])
+        .write_right_binary("logo.png", &[0, 1, 3]);
+    let core = fixture.core();
+    let result = core.analyze().unwrap();
+
+    let parsed = DiffEngine::parse_unified_diff(&core.export(&result, ExportFormat::Unified).unwrap()).unwrap();
+    assert_eq!(parsed.len(), 4);
+
+    for file in &parsed {
+        let path = if file.new_path == Path::new("/dev/null") { &file.old_path } else { &file.new_path };
+        let relative = path.strip_prefix("b/").or_else(|_| path.strip_prefix("a/")).unwrap();
+        let diff = core.get_file_diff(relative).unwrap();
+        assert_eq!(file.is_binary, diff.is_binary, "{}", relative.display());
+        assert_eq!(file.hunks, diff.hunks, "{}", relative.display());
+    }
+}
+
+#[test]
+fn output_dir_report_links_a_diff_per_changed_file() {
+    let fixture = TestFixture::new();
+    fixture
+        .write_both("same.txt", "same\n")
+        .write_left("src/my lib.rs", "a\nb\n")
+        .write_right("src/my lib.rs", "a\nB\nc\n")
+        .write_right("added.txt", "new\n");
+    let core = fixture.core();
+    let result = core.analyze().unwrap();
+    let dir = tempfile::tempdir().unwrap();
+    let report = dir.path().join("report");
+
+    let index = core.export_report(&result, &report).unwrap();
+    assert_eq!(index, report.join("index.html"));
+    let diff = std::fs::read_to_string(report.join("src/my lib.rs.diff")).unwrap();
+    assert!(diff.starts_with("--- a/src/my lib.rs\n+++ b/src/my lib.rs\n@@"));
+    assert!(report.join("added.txt.diff").is_file());
+    assert!(!report.join("same.txt.diff").exists());
+
+    let html = std::fs::read_to_string(&index).unwrap();
+    assert!(html.contains("<a href=\"src/my%20lib.rs.diff\">src/my lib.rs</a></td><td class=\"add\">+2</td><td class=\"del\">-1</td>"));
+    assert!(!html.contains("http"), "the report must not load anything remote");
+
+    // A second report into the same directory would mix the two
+    assert!(core.export_report(&result, &report).is_err());
+}
+
+#[test]
+fn external_unified_diffs_are_parsed() {
+    let patch = "\
+diff --git a/greet.c b/greet.c
+index 3b18e51..a8c1f2e 100644
+--- a/greet.c\t2024-01-01 10:00:00.000000000 +0000
++++ b/greet.c\t2024-01-02 10:00:00.000000000 +0000
+@@ -1,3 +1,3 @@ int main(void)
+ int main(void) {
+-    puts(\"hi\");
++    puts(\"hello\");
+ }
+\\ No newline at end of file
+";
+    let parsed = DiffEngine::parse_unified_diff(patch).unwrap();
+    assert_eq!(parsed.len(), 1);
+    assert_eq!(parsed[0].old_path, Path::new("a/greet.c"));
+    assert_eq!(parsed[0].hunks[0].lines.len(), 4);
+
+    let original = "int main(void) {\n    puts(\"hi\");\n}";
+    let patched = DiffEngine::apply_patch(original, &parsed[0].hunks).unwrap();
+    assert_eq!(patched, "int main(void) {\n    puts(\"hello\");\n}");
+
+    let truncated = DiffEngine::parse_unified_diff("--- a/x\n+++ b/x\n@@ -1,2 +1,2 @@\n-x\n").unwrap_err();
+    assert_eq!(
+        truncated.downcast_ref::<ParseError>(),
+        Some(&ParseError::TruncatedHunk { line: 5, old_lines: 2, new_lines: 2 })
+    );
+    assert!(DiffEngine::parse_unified_diff("@@ -1 +1 @@\n-x\n+y\n").is_err());
+    assert!(DiffEngine::parse_unified_diff("--- a/x\n+++ b/x\n@@ -x +1 @@\n").is_err());
+}
+
+#[test]
+fn word_diff_marks_only_changed_words() {
+    let lines = DiffEngine::new().word_diff("let total = price * count;\nkeep\n", "let total = price * quantity;\nkeep\nextra\n");
+
+    let span = |changed: bool, text: &str| WordSpan { changed, text: text.to_string() };
+    assert_eq!(
+        lines,
+        vec![
+            WordDiffLine::Removed(vec![span(false, "let total = price * "), span(true, "count;")]),
+            WordDiffLine::Added(vec![span(false, "let total = price * "), span(true, "quantity;")]),
+            WordDiffLine::Context("keep".to_string()),
+            WordDiffLine::Added(vec![span(true, "extra")]),
+        ]
+    );
+}
+
+#[test]
+fn blank_lines_can_be_ignored() {
+    let left = "fn a() {}\nfn b() {}\nfn c() {}\n";
+    let right = "fn a() {}\n\n\nfn b() {}\n   \nfn c() {}\n";
+    let engine = DiffEngine::with_options(DiffOptions { ignore_blank_lines: true, ..DiffOptions::default() });
+
+    assert!(!DiffEngine::new().diff_strings(left, right).hunks.is_empty());
+    assert!(engine.diff_strings(left, right).hunks.is_empty());
+
+    // Line numbers still refer to the original, unfiltered files
+    let changed = "fn a() {}\n\n\nfn b() {}\n\nfn d() {}\n";
+    let hunks = engine.diff_strings(left, changed).hunks;
+    assert_eq!(hunks.len(), 1);
+    let added = hunks[0].lines.iter().find(|line| line.content == "fn d() {}").unwrap();
+    assert_eq!((&added.kind, added.new_line_number), (&DiffLineKind::Addition, Some(6)));
+    assert_eq!(hunks[0].new_start, 1);
+
+    // Blank lines inside a hunk are kept, so the hunks still apply as a patch
+    assert_eq!(DiffEngine::apply_patch(left, &hunks).unwrap(), changed);
+    let left = "a\n\nb\n\n\nc\n";
+    let right = "a\n\nb\n\nd\n";
+    let hunks = engine.diff_strings(left, right).hunks;
+    assert_eq!((hunks[0].old_start, hunks[0].old_lines, hunks[0].new_start, hunks[0].new_lines), (1, 6, 1, 5));
+    assert_eq!(DiffEngine::apply_patch(left, &hunks).unwrap(), right);
+    let blank = &hunks[0].lines[1];
+    assert_eq!((&blank.kind, blank.old_line_number, blank.new_line_number), (&DiffLineKind::Context, Some(2), Some(2)));
+}
+
+#[test]
+fn oversized_files_are_reported_instead_of_diffed() {
+    let fixture = TestFixture::new();
+    fixture
+        .write_left("big.txt", &"old line\n".repeat(100))
+        .write_right("big.txt", &"new line\n".repeat(100));
+    let mut core = fixture.core();
+    core.diff_options.max_file_size = Some(512);
+
+    let direct = DiffEngine::with_options(core.diff_options.clone())
+        .diff_files(&fixture.left_path().join("big.txt"), &fixture.right_path().join("big.txt"))
+        .unwrap_err();
+    assert!(matches!(
+        direct.downcast_ref::<DiffyError>(),
+        Some(DiffyError::FileTooLarge { size: 900, limit: 512, .. })
+    ));
+
+    let diff = core.get_file_diff(Path::new("big.txt")).unwrap();
+    assert!(diff.hunks.is_empty());
+    assert_eq!(
+        diff.binary,
+        Some(BinaryFileDiff { left_size: Some(900), right_size: Some(900), too_large: true })
+    );
+
+    let patch = core.export(&core.analyze().unwrap(), ExportFormat::Unified).unwrap();
+    assert_eq!(patch, "Files a/big.txt and b/big.txt differ (too large to diff)\n");
+}
+
+#[test]
+fn hunks_can_be_selected_by_line_and_merged() {
+    let base: String = (1..=30).map(|n| format!("line {}\n", n)).collect();
+    let modified = base.replace("line 5\n", "five\n").replace("line 9\n", "nine\n").replace("line 25\n", "");
+    let engine = DiffEngine::with_options(DiffOptions { context_lines: 0, ..DiffOptions::default() });
+    let hunks = engine.diff_strings(&base, &modified).hunks;
+    assert_eq!(hunks.len(), 3);
+
+    let near: Vec<u32> = DiffEngine::get_hunks_near_line(&hunks, 7, 2).iter().map(|hunk| hunk.old_start).collect();
+    assert_eq!(near, vec![5, 9]);
+    assert_eq!(DiffEngine::get_hunks_near_line(&hunks, 17, 3).len(), 0);
+
+    // A hunk removing lines from "line 0" can't be placed in the text, so it stays apart
+    let mut invalid = hunks.clone();
+    invalid[1].old_start = 0;
+    assert_eq!(DiffEngine::merge_close_hunks(invalid, u32::MAX, &base).len(), 3);
+
+    // Lines 6-8 separate the first two hunks, line 25 is far away
+    let merged = DiffEngine::merge_close_hunks(hunks, 4, &base);
+    assert_eq!(merged.len(), 2);
+    assert_eq!((merged[0].old_start, merged[0].old_lines, merged[0].new_lines), (5, 5, 5));
+    assert_eq!(DiffEngine::apply_patch(&base, &merged).unwrap(), modified);
+}
+
+#[test]
+fn hunk_context_is_split_from_changes() {
+    let base: String = (1..=12).map(|n| format!("line {}\n", n)).collect();
+    let modified = base.replace("line 5\n", "five\n").replace("line 7\n", "line 7\nextra\n");
+    let hunks = DiffEngine::new().diff_strings(&base, &modified).hunks;
+    assert_eq!(hunks.len(), 1);
+    let hunk = &hunks[0];
+
+    let content = |lines: &[diffy::core::types::DiffLine]| -> Vec<String> {
+        lines.iter().map(|line| line.content.trim_end().to_string()).collect()
+    };
+    assert_eq!(content(hunk.before_context()), ["line 2", "line 3", "line 4"]);
+    assert_eq!(content(hunk.changed_lines()), ["line 5", "five", "line 6", "line 7", "extra"]);
+    assert_eq!(content(hunk.after_context()), ["line 8", "line 9", "line 10"]);
+    assert!(hunk.has_additions() && hunk.has_deletions());
+
+    let added_only = &DiffEngine::new().diff_strings("a\n", "a\nb\n").hunks[0];
+    assert!(added_only.has_additions() && !added_only.has_deletions());
+    assert!(added_only.after_context().is_empty());
+}
+
+#[test]
+fn diff_results_merge_under_their_basenames() {
+    let first = TestFixture::new();
+    first.write_right("added.txt", "new\n").write_both("same.txt", "same\n");
+    let second = TestFixture::new();
+    second.write_left("gone.txt", "old\n");
+
+    let mut a = first.core().analyze().unwrap();
+    let mut b = second.core().analyze().unwrap();
+    a.left_path = PathBuf::from("service-a");
+    b.left_path = PathBuf::from("nested/service-a");
+    let err = a.merge(&b).unwrap_err();
+    assert!(matches!(err.downcast_ref(), Some(DiffyError::OverlappingPath { path }) if path == Path::new("service-a")));
+
+    b.left_path = PathBuf::from("service-b");
+    let merged = a.merge(&b).unwrap();
+
+    DiffAssert::new(&merged)
+        .has_file_count(3)
+        .has_added("service-a/added.txt")
+        .has_unchanged("service-a/same.txt")
+        .has_removed("service-b/gone.txt");
+    assert_eq!((merged.added_count, merged.removed_count, merged.unchanged_count), (1, 1, 1));
+
+    let mut c = second.core().analyze().unwrap();
+    c.left_path = PathBuf::from("service-c");
+    let chained = merged.merge(&c).unwrap();
+    assert_eq!(chained.total_files, 4);
+    assert!(chained.tree.find(Path::new("service-c/gone.txt")).is_some());
+    assert!(merged.merge(&b).is_err());
+}
+
+#[test]
+fn identical_files_share_loaded_content() {
+    let fixture = TestFixture::new();
+    fixture
+        .write_both("vendor/a/lib.js", "module.exports = 1;\n")
+        .write_both("vendor/b/lib.js", "module.exports = 1;\n")
+        .write_left("app.js", "old\n")
+        .write_right("app.js", "new\n");
+
+    let core = fixture.core();
+    core.analyze().unwrap();
+
+    let store = core.content_store();
+    let held = store.load(&fixture.left_path().join("vendor/a/lib.js")).unwrap();
+    let shared = store.load(&fixture.right_path().join("vendor/b/lib.js")).unwrap();
+    assert!(Arc::ptr_eq(&held, &shared));
+    assert_eq!(store.live_entries(), 1);
+
+    drop((held, shared));
+    assert_eq!(store.live_entries(), 0);
+    DiffAssert::new(&core.analyze().unwrap()).file("app.js").has_addition("new");
+
+    // Both sides of an identical pair hold the same text
+    let diff = core.get_file_diff(Path::new("vendor/a/lib.js")).unwrap();
+    assert!(Arc::ptr_eq(diff.left_content.as_ref().unwrap(), diff.right_content.as_ref().unwrap()));
+
+    // An edit after the pair was diffed is picked up, even while the old text is shared
+    fixture.write_right("vendor/a/lib.js", "module.exports = 2;
+");
+    let edited = core.get_file_diff(Path::new("vendor/a/lib.js")).unwrap();
+    assert_eq!(edited.total_additions(), 1);
+    assert_eq!(edited.right_content.as_deref().map(String::as_str), Some("module.exports = 2;\n"));
+    drop(diff);
+}
+
+#[test]
+fn exported_patch_applies_and_reverses() {
+    let fixture = TestFixture::new();
+    fixture
+        .write_left("src/lib.rs", "fn a() {}\nfn b() {}\n")
+        .write_right("src/lib.rs", "fn a() {}\nfn c() {}\n")
+        .write_right("docs/new.md", "# New\n")
+        .write_left("old.txt", "gone\n")
+        .write_both("same.txt", "same\n");
+    let core = fixture.core();
+    let patch = core.export(&core.analyze().unwrap(), ExportFormat::Unified).unwrap();
+
+    let target = tempfile::tempdir().unwrap();
+    for file in ["src/lib.rs", "old.txt", "same.txt"] {
+        std::fs::create_dir_all(target.path().join(file).parent().unwrap()).unwrap();
+        std::fs::copy(fixture.left_path().join(file), target.path().join(file)).unwrap();
+    }
+
+    let plan = PatchPlan::prepare(&patch, target.path(), false).unwrap();
+    assert!(plan.is_clean());
+    let actions: Vec<_> = plan.files.iter().map(|file| (file.path.to_str().unwrap(), &file.action)).collect();
+    assert!(actions.contains(&("old.txt", &PatchAction::Delete)));
+    assert!(actions.contains(&("docs/new.md", &PatchAction::Create("# New\n".to_string()))));
+    plan.write(target.path()).unwrap();
+    let patched = DiffyCore::new(target.path().to_path_buf(), fixture.right_path().to_path_buf()).analyze().unwrap();
+    assert_eq!((patched.unchanged_count, patched.total_files), (3, 3));
+
+    let again = PatchPlan::prepare(&patch, target.path(), false).unwrap();
+    assert_eq!(again.conflicts.len(), 3);
+
+    PatchPlan::prepare(&patch, target.path(), true).unwrap().write(target.path()).unwrap();
+    assert_eq!(std::fs::read_to_string(target.path().join("old.txt")).unwrap(), "gone\n");
+    assert!(!target.path().join("docs/new.md").exists());
+
+    let escaping = "--- a/../outside.txt\n+++ b/../outside.txt\n@@ -1 +1 @@\n-a\n+b\n";
+    assert_eq!(PatchPlan::prepare(escaping, target.path(), false).unwrap().conflicts.len(), 1);
+}
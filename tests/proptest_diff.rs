@@ -0,0 +1,116 @@
+//! Property-based tests for `DiffEngine` invariants, run against every algorithm.
+
+use diffy::core::diff::DiffEngine;
+use diffy::core::types::{DiffAlgorithm, DiffLineKind, DiffOptions};
+use proptest::prelude::*;
+
+const ALGORITHMS: [DiffAlgorithm; 3] = [DiffAlgorithm::Myers, DiffAlgorithm::Patience, DiffAlgorithm::Lcs];
+
+#[derive(Debug, Clone)]
+enum Edit {
+    Insert(usize, String),
+    Delete(usize),
+    Substitute(usize, String),
+}
+
+// A small alphabet makes repeated lines (and therefore ambiguous alignments) common
+fn line() -> impl Strategy<Value = String> {
+    prop_oneof![
+        Just(String::new()),
+        Just("}".to_string()),
+        "[a-e]{1,3}",
+        "[a-z ]{0,20}",
+    ]
+}
+
+fn edit() -> impl Strategy<Value = Edit> {
+    prop_oneof![
+        (any::<usize>(), line()).prop_map(|(at, text)| Edit::Insert(at, text)),
+        any::<usize>().prop_map(Edit::Delete),
+        (any::<usize>(), line()).prop_map(|(at, text)| Edit::Substitute(at, text)),
+    ]
+}
+
+/// A base text and a modified text derived from it by random edits
+fn text_pair() -> impl Strategy<Value = (String, String)> {
+    (prop::collection::vec(line(), 0..40), prop::collection::vec(edit(), 0..10)).prop_map(|(base, edits)| {
+        let mut modified = base.clone();
+        for edit in edits {
+            match edit {
+                Edit::Insert(at, text) => {
+                    let at = at % (modified.len() + 1);
+                    modified.insert(at, text);
+                }
+                Edit::Delete(at) if !modified.is_empty() => {
+                    let at = at % modified.len();
+                    modified.remove(at);
+                }
+                Edit::Substitute(at, text) if !modified.is_empty() => {
+                    let at = at % modified.len();
+                    modified[at] = text;
+                }
+                _ => {}
+            }
+        }
+        (join(&base), join(&modified))
+    })
+}
+
+fn join(lines: &[String]) -> String {
+    lines.iter().map(|line| format!("{}\n", line)).collect()
+}
+
+fn engine(algorithm: DiffAlgorithm) -> DiffEngine {
+    DiffEngine::with_options(DiffOptions { algorithm, ..DiffOptions::default() })
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(1000))]
+
+    #[test]
+    fn applying_diff_reproduces_modified_text((base, modified) in text_pair()) {
+        for algorithm in ALGORITHMS {
+            let diff = engine(algorithm).diff_strings(&base, &modified);
+            prop_assert_eq!(DiffEngine::apply_patch(&base, &diff.hunks).unwrap(), modified.clone(), "{:?}", algorithm);
+        }
+    }
+
+    #[test]
+    fn reversing_diff_reproduces_base_text((base, modified) in text_pair()) {
+        for algorithm in ALGORITHMS {
+            let diff = engine(algorithm).diff_strings(&base, &modified);
+            prop_assert_eq!(DiffEngine::apply_patch_reversed(&modified, &diff.hunks).unwrap(), base.clone(), "{:?}", algorithm);
+        }
+    }
+
+    #[test]
+    fn merged_hunks_still_apply((base, modified) in text_pair(), threshold in 0u32..8) {
+        let engine = DiffEngine::with_options(DiffOptions { context_lines: 0, ..DiffOptions::default() });
+        let hunks = engine.diff_strings(&base, &modified).hunks;
+        let merged = DiffEngine::merge_close_hunks(hunks.clone(), threshold, &base);
+
+        prop_assert!(merged.len() <= hunks.len());
+        prop_assert_eq!(DiffEngine::apply_patch(&base, &merged).unwrap(), modified.clone());
+        prop_assert_eq!(DiffEngine::apply_patch_reversed(&modified, &merged).unwrap(), base.clone());
+    }
+
+    #[test]
+    fn identical_texts_have_no_hunks((base, _) in text_pair()) {
+        for algorithm in ALGORITHMS {
+            let diff = engine(algorithm).diff_strings(&base, &base);
+            prop_assert!(diff.hunks.is_empty(), "{:?}", algorithm);
+        }
+    }
+
+    #[test]
+    fn hunk_headers_count_their_lines((base, modified) in text_pair()) {
+        for algorithm in ALGORITHMS {
+            for hunk in engine(algorithm).diff_strings(&base, &modified).hunks {
+                let count = |kind: DiffLineKind| hunk.lines.iter().filter(|line| line.kind == kind).count() as u32;
+                let context = count(DiffLineKind::Context);
+                prop_assert_eq!(context + count(DiffLineKind::Deletion), hunk.old_lines, "{:?}", algorithm);
+                prop_assert_eq!(context + count(DiffLineKind::Addition), hunk.new_lines, "{:?}", algorithm);
+            }
+        }
+    }
+}